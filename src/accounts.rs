@@ -0,0 +1,110 @@
+//! Account signals which jami-rs doesn't forward, read directly on dbus.
+
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+use dbus::Message;
+use tokio::sync::mpsc::UnboundedSender;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const CONFIGURATION_MANAGER_IFACE: &str = "cx.ring.Ring.ConfigurationManager";
+/// How long a PIN generated by /pin can be used by another device
+pub const PIN_VALIDITY: Duration = Duration::from_secs(10 * 60);
+
+/**
+ * Account signals of the daemon
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccountEvent {
+    /// End of a PIN generation
+    PinExported {
+        account_id: String,
+        /// 0 on success, 1 for a wrong password, 2 for a network error
+        status: i32,
+        pin: String,
+    },
+    /// The devices linked to an account changed
+    KnownDevicesChanged {
+        account_id: String,
+        /// Name of each device id
+        devices: HashMap<String, String>,
+    },
+    /// End of the registration of a username
+    NameRegistered {
+        account_id: String,
+        /// 0 on success, 1 for a wrong password, 2 for an invalid name, 3 if already taken, 4 for a network error
+        status: i32,
+        name: String,
+    },
+    /// End of a device revocation
+    DeviceRevoked {
+        account_id: String,
+        device_id: String,
+        /// 0 on success, 1 for a wrong password, 2 for an unknown device
+        status: i32,
+    },
+}
+
+/**
+ * Forward account signals of the daemon until stopped. Blocking.
+ * @param tx        where to send events
+ * @param stop      set to true to stop listening
+ */
+pub fn listen_signals(tx: UnboundedSender<AccountEvent>, stop: Arc<AtomicBool>) -> Result<(), dbus::Error> {
+    let conn = Connection::new_session()?;
+
+    let pin_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CONFIGURATION_MANAGER_IFACE, "exportOnRingEnded"),
+        move |(account_id, status, pin): (String, i32, String), _: &Connection, _: &Message| {
+            let _ = pin_tx.send(AccountEvent::PinExported {
+                account_id,
+                status,
+                pin,
+            });
+            true
+        },
+    )?;
+
+    let name_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CONFIGURATION_MANAGER_IFACE, "nameRegistrationEnded"),
+        move |(account_id, status, name): (String, i32, String), _: &Connection, _: &Message| {
+            let _ = name_tx.send(AccountEvent::NameRegistered {
+                account_id,
+                status,
+                name,
+            });
+            true
+        },
+    )?;
+
+    let devices_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CONFIGURATION_MANAGER_IFACE, "knownDevicesChanged"),
+        move |(account_id, devices): (String, HashMap<String, String>), _: &Connection, _: &Message| {
+            let _ = devices_tx.send(AccountEvent::KnownDevicesChanged { account_id, devices });
+            true
+        },
+    )?;
+
+    conn.add_match(
+        MatchRule::new_signal(CONFIGURATION_MANAGER_IFACE, "deviceRevocationEnded"),
+        move |(account_id, device_id, status): (String, String, i32), _: &Connection, _: &Message| {
+            let _ = tx.send(AccountEvent::DeviceRevoked {
+                account_id,
+                device_id,
+                status,
+            });
+            true
+        },
+    )?;
+
+    while !stop.load(Ordering::Relaxed) {
+        conn.process(Duration::from_millis(500))?;
+    }
+    Ok(())
+}
@@ -1,444 +1,1118 @@
-use crate::appdata::AppData;
+use crate::accounts::{AccountEvent, PIN_VALIDITY};
+use crate::appdata::{AppData, JamiSnapshot};
+use crate::backend::{Backend, Event, TransferInfo};
+use crate::backfill::{Backfill, PageRequest};
+use crate::calls::{format_duration, normalize_uri, Call, CallEvent, CallState, Conference, Participant};
+use crate::commands::{self, ArgKind, ParsedCommand};
+use crate::config::Config;
+use crate::completion::{self, Completion};
+use crate::editor;
+use crate::input::{self, InputEvent};
+use crate::keys::Action;
+use crate::notifications::{self, NotifyMode};
+use crate::search::{SearchHit, SearchIndex};
+use crate::transfers::{free_path, safe_file_name};
+use crate::ui;
 use crate::util::*;
+use crate::wizard::{AccountWizard, PasswordPrompt, PasswordRequest, Progress, PromptAction, WizardAction, WizardKind};
 
 use app_dirs::{get_app_dir, AppDataType, AppInfo};
-use chrono::{TimeZone, Utc};
-use crossterm::event::KeyCode;
+use chrono::{Local, TimeZone, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use dirs;
-use jami_rs::{ImportType, Jami};
+use jami_rs::Jami;
 use jami_rs::account::Account;
-use unicode_width::UnicodeWidthStr;
 
-use std::collections::HashMap;
-use std::fs::{copy, create_dir, File};
+use std::collections::{HashMap, HashSet};
+use std::fs::{copy, create_dir_all, File};
 use std::io::Write;
-use std::path::Path;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Number of commits asked to the daemon per history load
+pub const HISTORY_BATCH_SIZE: u32 = 50;
+/// Number of messages scrolled by PageUp/PageDown
+const SCROLL_STEP: usize = 10;
+/// Minimum delay between two writes of the cache
+const CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Max number of results for /search
+const SEARCH_LIMIT: u32 = 100;
+const SEARCH_CHANNEL_ID: &str = "🔍 Search";
+/// Number of messages scrolled by a turn of the mouse wheel
+const WHEEL_STEP: usize = 3;
+
+/**
+ * Pane receiving Up and Down keys
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Focus {
+    Input,
+    Channels,
+    Messages,
+    /// Transfers panel, replacing the messages
+    Transfers,
+    /// Devices of an account, replacing the messages
+    Devices,
+}
+
+impl Focus {
+    fn next(self) -> Self {
+        match self {
+            Focus::Input => Focus::Channels,
+            Focus::Channels => Focus::Messages,
+            Focus::Messages | Focus::Transfers | Focus::Devices => Focus::Input,
+        }
+    }
+}
+
+/**
+ * Message the next text will reply to
+ */
+pub struct Reply {
+    pub conversation_id: String,
+    pub commit_id: String,
+    pub author: String,
+    pub body: String,
+}
 
 pub struct App {
     pub should_quit: bool,
     pub log_file: Option<File>,
     pub data: AppData,
+    backend: Box<dyn Backend>,
+    search_index: Option<SearchIndex>,
+    search_results: Vec<SearchHit>,
+    /// Indexing of the histories, for the search
+    backfill: Backfill,
+    /// Account, conversation and commit to scroll to once loaded
+    pending_jump: Option<(String, String, String)>,
+    completion: Option<Completion>,
+    pub config: Config,
+    pub focus: Focus,
+    pub reply: Option<Reply>,
+    /// Member shown in the member list
+    pub highlighted_member: Option<String>,
+    /// Result of the last action, shown until the next key
+    pub status: Option<String>,
+    /// Escape sequences to write to the terminal, like clipboard requests
+    pub terminal_output: Vec<String>,
+    /// Set by the UI on each draw
+    pub click_areas: ui::ClickAreas,
+    /// Calls in progress, the most recent last
+    pub calls: Vec<Call>,
+    pub conferences: Vec<Conference>,
+    /// Account and conversation of the conference being created
+    pending_conference: Option<(String, String)>,
+    /// Last progress of ongoing transfers, to compute their rate
+    transfer_samples: HashMap<u64, (Instant, i64)>,
+    /// Transfers of the account, newest first
+    pub transfers: StatefulList<TransferEntry>,
+    /// Transfers accepted by the policy of the config, to only accept them once
+    auto_accepted: HashSet<u64>,
+    /// Devices linked to the account shown by /devices, the current one first
+    pub devices: StatefulList<DeviceEntry>,
+    devices_account: String,
+    /// Files dropped on the terminal, sent if the next key is Enter
+    pub dropped_files: Vec<String>,
+    /// Words mentioning the user, computed once per account
+    mention_names: HashMap<String, Vec<String>>,
+    /// Accounts whose channels are folded under their header in unified mode
+    pub collapsed_accounts: HashSet<String>,
+    /// Form to add an account, drawn over the chat
+    pub wizard: Option<AccountWizard>,
+    /// Password asked by an account command, drawn over the chat
+    pub password_prompt: Option<PasswordPrompt>,
+    /// Position in the input history of the channel while recalling inputs
+    input_history_idx: Option<usize>,
+    /// Input being written before recalling the history
+    input_draft: String,
+    /// True if data comes from the cache and must be refreshed with the daemon
+    needs_sync: bool,
+    /// False to never write the cache, like in tests
+    use_cache: bool,
+    cache_dirty: bool,
+    cache_saved_at: Instant,
 }
 
 impl App {
     /**
      * Create new app
+     * @param verbose   log into jami-cli.log
+     * @param backend   daemon to use
+     * @param config    user configuration
      */
-    pub fn try_new(verbose: bool) -> anyhow::Result<Self> {
+    pub fn try_new(verbose: bool, backend: Box<dyn Backend>, config: Config) -> anyhow::Result<Self> {
         let log_file = if verbose {
             Some(File::create("jami-cli.log").unwrap())
         } else {
             None
         };
-        let mut data = AppData::init_from_jami()?;
-        data.lookup_members();
+        let (data, needs_sync) = match AppData::load_from_cache() {
+            Some(mut data) => {
+                data.unified = config.accounts.unified;
+                (data, true)
+            }
+            None => {
+                let mut data = AppData::init_from_jami(&*backend, config.accounts.unified)?;
+                data.lookup_members(&*backend);
+                (data, false)
+            }
+        };
+        let search_index = SearchIndex::open().ok();
+        Ok(Self::with_data(data, backend, config, search_index, needs_sync, true, log_file))
+    }
+
+    /**
+     * Create new app without using the cache
+     * @param backend   daemon to use
+     */
+    pub fn with_backend(backend: Box<dyn Backend>) -> anyhow::Result<Self> {
+        let mut data = AppData::init_from_jami(&*backend, false)?;
+        data.lookup_members(&*backend);
+        let search_index = SearchIndex::in_memory().ok();
+        Ok(Self::with_data(data, backend, Config::default(), search_index, false, false, None))
+    }
+
+    fn with_data(
+        mut data: AppData,
+        backend: Box<dyn Backend>,
+        config: Config,
+        search_index: Option<SearchIndex>,
+        needs_sync: bool,
+        use_cache: bool,
+        log_file: Option<File>,
+    ) -> Self {
         if data.channels.state.selected().is_none() && !data.channels.items.is_empty() {
             data.channels.state.select(Some(0));
         }
 
-        Ok(Self {
+        Self {
             data,
             should_quit: false,
             log_file,
-        })
+            backend,
+            search_index,
+            search_results: Vec::new(),
+            backfill: Backfill::default(),
+            pending_jump: None,
+            completion: None,
+            config,
+            focus: Focus::Input,
+            reply: None,
+            highlighted_member: None,
+            status: None,
+            terminal_output: Vec::new(),
+            click_areas: ui::ClickAreas::default(),
+            calls: Vec::new(),
+            conferences: Vec::new(),
+            pending_conference: None,
+            transfer_samples: HashMap::new(),
+            transfers: StatefulList::with_items(Vec::new()),
+            auto_accepted: HashSet::new(),
+            devices: StatefulList::with_items(Vec::new()),
+            devices_account: String::new(),
+            dropped_files: Vec::new(),
+            mention_names: HashMap::new(),
+            collapsed_accounts: HashSet::new(),
+            wizard: None,
+            password_prompt: None,
+            input_history_idx: None,
+            input_draft: String::new(),
+            needs_sync,
+            use_cache,
+            cache_dirty: false,
+            cache_saved_at: Instant::now(),
+        }
     }
 
     /**
-     * Handle key events
+     * Dispatch an event from the terminal or the daemon
      * @param self
-     * @param key   key code
+     * @param event
      */
-    pub fn on_key(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Char(c) => {
-                let idx = self
+    pub async fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Input(event) if self.password_prompt.is_some() => self.on_prompt_key(event),
+            Event::Input(event) if self.wizard.is_some() => self.on_wizard_key(event),
+            Event::Input(event) => match self.config.keys.action(&event) {
+                Some(action) => self.on_action(action),
+                None => self.on_key(event),
+            },
+            Event::Message {
+                account_id,
+                conversation_id,
+                payloads,
+            } => {
+                let known = self
                     .data
-                    .input
-                    .chars()
-                    .take(self.data.input_cursor)
-                    .map(|c| c.len_utf8())
-                    .sum();
-                self.data.input.insert(idx, c);
-                self.data.input_cursor += 1;
-            }
-            KeyCode::Enter if !self.data.input.is_empty() => {
+                    .channels
+                    .items
+                    .iter()
+                    .find(|channel| channel.id == conversation_id)
+                    .map(|channel| channel.messages.len())
+                    .unwrap_or(0);
+                self.on_message(&account_id, &conversation_id, payloads)
+                    .await;
+                self.on_new_messages(&account_id, &conversation_id, known);
+            }
+            Event::Resize => {
+                // will just redraw the app
+            }
+            Event::RegistrationStateChanged(account_id, registration_state) => {
+                self.on_registration_state_changed(&account_id, &registration_state)
+                    .await;
+            }
+            Event::ConversationReady(account_id, conversation_id) => {
+                self.on_conversation_ready(account_id, conversation_id).await;
+            }
+            Event::ConversationRemoved(account_id, conversation_id) => {
+                self.on_conversation_removed(account_id, conversation_id)
+                    .await;
+            }
+            Event::ConversationRequest(account_id, conversation_id) => {
+                self.on_conversation_request(account_id, conversation_id)
+                    .await;
+            }
+            Event::RegisteredNameFound(account_id, status, address, name) => {
+                self.on_registered_name_found(account_id, status, address, name)
+                    .await;
+            }
+            Event::ConversationLoaded(id, account_id, conversation_id, messages) => {
+                self.on_conversation_loaded(id, account_id, conversation_id, messages)
+                    .await;
+            }
+            Event::DataTransferEvent(account_id, conversation_id, tid, status) => {
+                self.on_data_transfer_event(account_id, conversation_id, tid, status)
+                    .await;
+            }
+            Event::ProfileReceived(account_id, from, path) => {
+                self.on_profile_received(&account_id, &from, &path).await;
+            }
+            Event::IncomingTrustRequest(account_id, from, payload, receive_time) => {
+                self.on_incoming_trust_request(&account_id, &from, payload, receive_time)
+                    .await;
+            }
+            Event::MemberPresenceChanged(account_id, uri, flag) => {
+                self.on_member_presence_changed(&account_id, &uri, flag)
+                    .await;
+            }
+            Event::AccountsChanged() => {
+                self.on_accounts_changed().await;
+            }
+        }
+    }
+
+    /**
+     * What to read from the daemon to refresh data loaded from the cache
+     * @param self
+     * @return the cached account and if every account is shown, None if there is nothing to refresh
+     */
+    pub fn sync_request(&self) -> Option<(String, bool)> {
+        match self.needs_sync {
+            true => Some((self.data.account.id.clone(), self.data.unified)),
+            false => None,
+        }
+    }
+
+    /**
+     * Refresh data loaded from the cache with what the daemon sent
+     * @param self
+     * @param snapshot
+     */
+    pub fn on_synced(&mut self, snapshot: JamiSnapshot) {
+        // Already reloaded if the account or the view changed meanwhile
+        if !self.needs_sync {
+            return;
+        }
+        self.needs_sync = false;
+        self.data.merge_from_jami(snapshot);
+        self.data.lookup_members(&*self.backend);
+        self.cache_dirty = true;
+    }
+
+    /**
+     * Write the cache if something changed since the last write
+     * @param self
+     */
+    pub fn flush_cache(&mut self) {
+        if self.cache_dirty && self.cache_saved_at.elapsed() >= CACHE_SAVE_INTERVAL {
+            self.save_cache();
+        }
+    }
+
+    /**
+     * Write the cache now
+     * @param self
+     */
+    pub fn save_cache(&mut self) {
+        if !self.use_cache {
+            return;
+        }
+        if let Err(err) = self.data.save_to_cache() {
+            self.log(format!("cannot save cache: {}", err));
+        }
+        self.cache_dirty = false;
+        self.cache_saved_at = Instant::now();
+    }
+
+    /**
+     * Handle events read from the terminal
+     * @param self
+     * @param event
+     */
+    pub async fn handle_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Key(key) => self.handle_event(Event::Input(key)).await,
+            InputEvent::Paste(text) => self.on_paste(&text),
+            // The wizard and the password prompt are modal
+            InputEvent::Mouse(_) if self.wizard.is_some() || self.password_prompt.is_some() => {}
+            InputEvent::Mouse(event) => self.on_mouse(event),
+            InputEvent::Resize => self.handle_event(Event::Resize).await,
+        }
+    }
+
+    /**
+     * Handle mouse events, using the areas of the last draw
+     * @param self
+     * @param event
+     */
+    pub fn on_mouse(&mut self, event: MouseEvent) {
+        let (column, row) = (event.column, event.row);
+        match event.kind {
+            MouseEventKind::ScrollUp if self.click_areas.in_messages(column, row) => self.scroll_up(WHEEL_STEP),
+            MouseEventKind::ScrollDown if self.click_areas.in_messages(column, row) => {
+                self.on_scroll_down(WHEEL_STEP)
+            }
+            MouseEventKind::Down(MouseButton::Left) => self.on_click(column, row),
+            _ => {}
+        }
+    }
+
+    /**
+     * Select the channel, show the member or act on the file transfer clicked
+     * @param self
+     * @param column
+     * @param row
+     */
+    fn on_click(&mut self, column: u16, row: u16) {
+        self.status = None;
+        if let Some(channel_idx) = self.click_areas.channel_at(column, row) {
+            if channel_idx >= self.data.channels.items.len() {
+                return;
+            }
+            if self.data.channels.state.selected() != Some(channel_idx) {
+                self.select_channel(channel_idx);
+            } else {
+                self.toggle_account(channel_idx);
+            }
+            return;
+        }
+        let channel_idx = match self.data.channels.state.selected() {
+            Some(channel_idx) => channel_idx,
+            None => return,
+        };
+        if let Some(hash) = self.click_areas.member_at(column, row).cloned() {
+            self.show_member(channel_idx, hash);
+            return;
+        }
+        let message = self
+            .click_areas
+            .message_at(column, row)
+            .and_then(|msg_idx| self.data.channels.items[channel_idx].messages.get(msg_idx))
+            .cloned();
+        if let Some(message) = message {
+            match message.kind {
+                MessageKind::Transfer { path: Some(path), .. } => self.open_file(Some(path)),
+                MessageKind::Transfer {
+                    tid,
+                    name,
+                    status: TransferStatus::AwaitingHost,
+                    ..
+                } => {
+                    let account_id = self.channel_account(channel_idx);
+                    let conversation_id = self.data.channels.items[channel_idx].id.clone();
+                    self.accept_transfer(&account_id, &conversation_id, tid, &name);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /**
+     * Insert pasted text in the input, or offer to send dropped files
+     * @param self
+     * @param text
+     */
+    pub fn on_paste(&mut self, text: &str) {
+        self.status = None;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if let Some(prompt) = &mut self.password_prompt {
+            prompt.on_paste(&text);
+            return;
+        }
+        if let Some(wizard) = &mut self.wizard {
+            wizard.on_paste(&text);
+            return;
+        }
+        let in_group = self
+            .data
+            .channels
+            .state
+            .selected()
+            .map(|idx| self.data.channels.items[idx].channel_type == ChannelType::Group)
+            .unwrap_or(false);
+        if in_group && self.data.input.is_empty() {
+            let files = input::pasted_files(&text);
+            if !files.is_empty() {
+                let names: Vec<&str> = files
+                    .iter()
+                    .map(|path| path.rsplit('/').next().unwrap_or(path))
+                    .collect();
+                self.status = Some(format!("Send {}? Enter to send, any other key to cancel", names.join(", ")));
+                self.dropped_files = files;
+                return;
+            }
+        }
+        editor::insert(&mut self.data.input, &mut self.data.input_cursor, &text);
+    }
+
+    /**
+     * Send the dropped files to the selected channel
+     * @param self
+     * @param files
+     */
+    fn send_dropped_files(&mut self, files: Vec<String>) {
+        let channel_idx = match self.data.channels.state.selected() {
+            Some(channel_idx) => channel_idx,
+            None => return,
+        };
+        let account_id = self.channel_account(channel_idx);
+        let conversation_id = self.data.channels.items[channel_idx].id.clone();
+        for path in files {
+            self.backend.send_file(&account_id, &conversation_id, &path);
+        }
+    }
+
+    /**
+     * Handle key events
+     * @param self
+     * @param key   key code and modifiers
+     */
+    pub fn on_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        if !matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            self.input_history_idx = None;
+        }
+        self.status = None;
+        if !self.dropped_files.is_empty() {
+            let files = std::mem::take(&mut self.dropped_files);
+            if key.code == KeyCode::Enter {
+                self.send_dropped_files(files);
+            }
+            return;
+        }
+        if self.focus == Focus::Messages && !ctrl && !alt && self.on_selection_key(key.code) {
+            return;
+        }
+        if self.focus == Focus::Transfers && !ctrl && !alt && self.on_transfers_key(key.code) {
+            return;
+        }
+        if self.focus == Focus::Devices && !ctrl && !alt && self.on_devices_key(key.code) {
+            return;
+        }
+        let input = &mut self.data.input;
+        let cursor = &mut self.data.input_cursor;
+        match key.code {
+            KeyCode::Char('a') if ctrl => *cursor = editor::line_start(input, *cursor),
+            KeyCode::Char('e') if ctrl => *cursor = editor::line_end(input, *cursor),
+            KeyCode::Char('w') if ctrl => {
+                let (start, end) = (editor::word_left(input, *cursor), *cursor);
+                editor::delete_range(input, cursor, start, end);
+            }
+            KeyCode::Char('u') if ctrl => {
+                let (start, end) = (editor::line_start(input, *cursor), *cursor);
+                editor::delete_range(input, cursor, start, end);
+            }
+            KeyCode::Char('k') if ctrl => {
+                let (start, end) = (*cursor, editor::line_end(input, *cursor));
+                editor::delete_range(input, cursor, start, end);
+            }
+            KeyCode::Char('b') if alt => *cursor = editor::word_left(input, *cursor),
+            KeyCode::Char('f') if alt => *cursor = editor::word_right(input, *cursor),
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => editor::insert(input, cursor, &c.to_string()),
+            KeyCode::Enter if shift || alt => editor::insert(input, cursor, "\n"),
+            KeyCode::Enter if !input.is_empty() => {
                 if let Some(idx) = self.data.channels.state.selected() {
                     self.send_input(idx)
                 }
             }
-            KeyCode::Backspace => {
-                if self.data.input_cursor > 0
-                    && self.data.input_cursor < self.data.input.width() + 1
-                {
-                    self.data.input_cursor = self.data.input_cursor.saturating_sub(1);
-                    let idx = self
-                        .data
-                        .input
-                        .chars()
-                        .take(self.data.input_cursor)
-                        .map(|c| c.len_utf8())
-                        .sum();
-                    self.data.input.remove(idx);
+            KeyCode::Enter => {
+                if let Some(idx) = self.data.channels.state.selected() {
+                    self.toggle_account(idx)
                 }
             }
+            KeyCode::Backspace if alt || ctrl => {
+                let (start, end) = (editor::word_left(input, *cursor), *cursor);
+                editor::delete_range(input, cursor, start, end);
+            }
+            KeyCode::Backspace => editor::backspace(input, cursor),
+            KeyCode::Delete => editor::delete(input, cursor),
+            KeyCode::Left if ctrl || alt => *cursor = editor::word_left(input, *cursor),
+            KeyCode::Right if ctrl || alt => *cursor = editor::word_right(input, *cursor),
+            KeyCode::Left => self.on_left(),
+            KeyCode::Right => self.on_right(),
+            KeyCode::Up => match self.focus {
+                Focus::Input => self.on_history_up(),
+                Focus::Channels => self.on_up(),
+                Focus::Messages => self.scroll_up(1),
+                Focus::Transfers => self.transfers.previous(),
+                Focus::Devices => self.devices.previous(),
+            },
+            KeyCode::Down => match self.focus {
+                Focus::Input => self.on_history_down(),
+                Focus::Channels => self.on_down(),
+                Focus::Messages => self.on_scroll_down(1),
+                Focus::Transfers => self.transfers.next(),
+                Focus::Devices => self.devices.next(),
+            },
+            KeyCode::Tab => self.on_tab(),
+            KeyCode::Esc => self.reply = None,
             _ => {}
         }
     }
 
+    /**
+     * Handle an action bound to a key
+     * @param self
+     * @param action
+     */
+    pub fn on_action(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::Search => self.on_search_key(),
+            Action::NextChannel => self.on_down(),
+            Action::PreviousChannel => self.on_up(),
+            Action::ScrollUp => self.on_page_up(),
+            Action::ScrollDown => self.on_page_down(),
+            Action::ScrollTop => self.on_home(),
+            Action::ScrollBottom => self.on_end(),
+            Action::FocusPane => self.focus = self.focus.next(),
+            Action::OpenFile => self.open_last_file(),
+            Action::Reply => self.on_reply(),
+            Action::SelectMessage => self.focus = Focus::Messages,
+            Action::Transfers => {
+                if self.focus == Focus::Transfers {
+                    self.focus = Focus::Input;
+                } else {
+                    self.refresh_transfers();
+                    self.focus = Focus::Transfers;
+                }
+            }
+        }
+    }
+
     /**
      * Handle user input
      * @param self
      * @param channel_idx       The channel which receives the input
      */
     fn send_input(&mut self, channel_idx: usize) {
-        let channel = &mut self.data.channels.items[channel_idx];
-
         let message: String = self.data.input.drain(..).collect();
         self.data.input_cursor = 0;
+        self.data.channels.items[channel_idx].push_input_history(&message);
+        let channel_type = self.data.channels.items[channel_idx].channel_type.clone();
 
-        if message == "/exit" {
-            self.should_quit = true;
-            return;
+        let show_msg = match commands::parse(&message, &channel_type) {
+            Ok(Some(command)) => self.run_command(channel_idx, &channel_type, command),
+            Ok(None) => self.send_text(channel_idx, &channel_type, &message),
+            Err(err) => {
+                self.data.channels.items[channel_idx]
+                    .messages
+                    .push(Message::info(err.to_string()));
+                Some(false)
+            }
+        };
+        // The channel list changed
+        let show_msg = match show_msg {
+            Some(show_msg) => show_msg,
+            None => return,
+        };
+
+        if show_msg {
+            let channel = &mut self.data.channels.items[channel_idx];
+            channel.messages.push(Message::new(
+                self.data.account.get_display_name(),
+                message.clone(),
+                Utc::now(),
+            ));
         }
 
-        let mut show_msg = true;
-        let mut is_invite = false;
-        let mut is_trust_request = false;
-        let mut from_request = String::new();
-        match channel.channel_type.clone() {
-            ChannelType::Invite => {
-                is_invite = true;
+        self.reset_unread_messages();
+        self.bubble_up_channel(channel_idx);
+    }
+
+    /**
+     * Handle a text which is not a command
+     * @param self
+     * @param channel_idx       The channel which receives the input
+     * @param channel_type
+     * @param message
+     * @return if the message must be shown
+     */
+    fn send_text(&mut self, channel_idx: usize, channel_type: &ChannelType, message: &String) -> Option<bool> {
+        let account_id = self.channel_account(channel_idx);
+        let channel = &mut self.data.channels.items[channel_idx];
+        match channel_type {
+            ChannelType::Generated => Some(true),
+            ChannelType::Group => {
+                let parent = match self.reply.take() {
+                    Some(reply) if reply.conversation_id == channel.id => reply.commit_id,
+                    _ => String::new(),
+                };
+                self.backend.send_conversation_message(&account_id, &channel.id, message, &parent);
+                Some(false)
             }
-            ChannelType::TrustRequest(contact) => {
-                is_trust_request = true;
-                from_request = contact;
+            _ => {
+                for line in commands::help(channel_type) {
+                    channel.messages.push(Message::info(line));
+                }
+                Some(false)
             }
-            _ => {}
         }
+    }
 
-        if message.starts_with("/msg ") {
-            let account_id = &self.data.account.id;
-            let mut member = String::from(message.strip_prefix("/msg ").unwrap());
-            if Jami::is_hash(&member) {
-                Jami::add_contact(&account_id, &member);
-                Jami::send_trust_request(&account_id, &member, Vec::new() /* TODO */);
-            } else {
-                let mut ns = String::new();
-                if member.find("@") != None {
-                    let member_cloned = member.clone();
-                    let split: Vec<&str> = member_cloned.split("@").collect();
-                    member = split[0].to_string();
-                    ns = split[1].to_string();
+    /**
+     * Execute a command
+     * @param self
+     * @param channel_idx       The channel which receives the input
+     * @param channel_type
+     * @param command
+     * @return if the command must be shown, None if the channel list changed
+     */
+    fn run_command(
+        &mut self,
+        channel_idx: usize,
+        channel_type: &ChannelType,
+        command: ParsedCommand,
+    ) -> Option<bool> {
+        let account_id = self.channel_account(channel_idx);
+        let channel = &mut self.data.channels.items[channel_idx];
+        match command.name {
+            "/exit" => {
+                self.should_quit = true;
+                None
+            }
+            "/help" => {
+                for line in commands::help(channel_type) {
+                    channel.messages.push(Message::info(line));
                 }
-                self.data.out_invite.push(OutgoingInvite {
-                    account: account_id.to_string(),
-                    channel: None,
-                    member: member.clone(),
-                });
-                show_msg = false;
-                Jami::lookup_name(&account_id, &ns, &member);
-            }
-        } else if channel.channel_type == ChannelType::Generated {
-            if message == "/new" {
-                Jami::start_conversation(&self.data.account.id);
-            } else if message == "/list" {
-                for account in Jami::get_account_list() {
+                Some(true)
+            }
+            "/search" => {
+                self.search(command.arg(0));
+                None
+            }
+            "/mentions" => {
+                self.show_mentions();
+                None
+            }
+            "/open" => {
+                let hit_idx = command.number(0) as usize;
+                if hit_idx > 0 && self.open_search_hit(hit_idx - 1) {
+                    return None;
+                }
+                let channel = &mut self.data.channels.items[channel_idx];
+                channel.messages.push(Message::info(format!("No result {}", hit_idx)));
+                Some(false)
+            }
+            "/msg" => {
+                let member = command.arg(0);
+                if Jami::is_hash(&member.to_string()) {
+                    self.backend.add_contact(&account_id, &member.to_string());
+                    self.backend.send_trust_request(&account_id, &member.to_string(), Vec::new() /* TODO */);
+                    Some(true)
+                } else {
+                    let (member, ns) = split_name_server(member);
+                    self.data.out_invite.push(OutgoingInvite {
+                        account: account_id.clone(),
+                        channel: None,
+                        member: member.clone(),
+                    });
+                    self.backend.lookup_name(&account_id, &ns, &member);
+                    Some(false)
+                }
+            }
+            "/new" => {
+                self.backend.start_conversation(&account_id);
+                Some(true)
+            }
+            "/list" => {
+                for account in self.backend.get_account_list() {
                     channel
                         .messages
                         .push(Message::info(String::from(format!("{}", account))));
                 }
-            } else if message == "/get" || message.starts_with("/get ") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let filter = parts.get(1).unwrap_or(&"").to_string();
-                for (key, value) in Jami::get_account_details(&self.data.account.id) {
-                    if filter.is_empty() || filter.to_lowercase() == key.to_lowercase() {
+                Some(true)
+            }
+            "/get" => {
+                let filter = command.arg(0).to_lowercase();
+                for (key, value) in self.backend.get_account_details(&account_id) {
+                    if filter.is_empty() || filter == key.to_lowercase() {
                         channel
                             .messages
                             .push(Message::info(String::from(format!("{}: {}", key, value))));
                     }
                 }
-                show_msg = false;
-            } else if message.starts_with("/set") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let key = parts.get(1).unwrap_or(&"").to_string();
-                let value = parts.get(2).unwrap_or(&"").to_string();
-                let mut details = Jami::get_account_details(&self.data.account.id);
-                let mut key_found = String::new();
-                for (key2, _) in &details {
-                    if key2.to_lowercase() == key.to_lowercase() {
-                        key_found = key2.to_string();
+                Some(false)
+            }
+            "/set" => {
+                let key = command.arg(0).to_lowercase();
+                let mut details = self.backend.get_account_details(&account_id);
+                let key_found = details.keys().find(|key2| key2.to_lowercase() == key).cloned();
+                match key_found {
+                    Some(key_found) => {
+                        details.insert(key_found, command.arg(1).to_string());
+                        self.backend.set_account_details(&account_id, details);
+                    }
+                    None => {
+                        channel
+                            .messages
+                            .push(Message::info(format!("Unknown key {}", command.arg(0))));
                     }
                 }
-                if !key_found.is_empty() {
-                    details.insert(key_found, value.to_string());
-                }
-                Jami::set_account_details(&self.data.account.id, details);
-                show_msg = false;
-            } else if message.starts_with("/switch ") {
-                let account_id = String::from(message.strip_prefix("/switch ").unwrap());
-                let account = Jami::get_account(&*account_id);
+                Some(false)
+            }
+            "/switch" => {
+                let account = self.backend.get_account(command.arg(0));
                 if account.id.is_empty() {
                     channel
                         .messages
                         .push(Message::info(String::from("Invalid account id.")));
-                } else {
-                    //  TODO avoid duplicate code
-                    self.untrack_current_conversation();
-                    self.data.account = account;
-                    self.data
-                        .profile_manager
-                        .load_from_account(&self.data.account.id);
-                    let channels = AppData::channels_for_account(&self.data.account);
-                    self.data.channels = StatefulList::with_items(channels);
-                    if !self.data.channels.items.is_empty() {
-                        self.data.channels.state.select(Some(0));
-                    }
-                    self.data.lookup_members();
+                    return Some(true);
                 }
-            } else if message == "/add" {
-                Jami::add_account("", "", ImportType::None);
-            } else if message.starts_with("/rm ") {
-                let account_id = String::from(message.strip_prefix("/rm ").unwrap());
-                Jami::rm_account(&*account_id);
-            } else if message.starts_with("/import ") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let file = parts.get(1).unwrap_or(&"").to_string();
-                let password = parts.get(2).unwrap_or(&"").to_string();
-                Jami::add_account(&file, &password, ImportType::BACKUP);
-            } else if message.starts_with("/link ") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let pin = parts.get(1).unwrap_or(&"").to_string();
-                let password = parts.get(2).unwrap_or(&"").to_string();
-                Jami::add_account(&pin, &password, ImportType::NETWORK);
-            } else if message == "/help" {
-                channel
-                    .messages
-                    .push(Message::info(String::from("/help: Show this help")));
-                channel.messages.push(Message::info(String::from(
-                    "/new: Start a new conversation",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/msg <id|username>: Start a conversation with someone",
-                )));
-                channel
-                    .messages
-                    .push(Message::info(String::from("/list: list accounts")));
-                channel.messages.push(Message::info(String::from(
-                    "/switch <id>: switch to an account",
-                )));
-                channel
-                    .messages
-                    .push(Message::info(String::from("/add: Add a new account")));
-                channel
-                    .messages
-                    .push(Message::info(String::from("/rm <id>: Remove an account")));
-                channel.messages.push(Message::info(String::from(
-                    "/link <pin> [password]: Link an account via a PIN",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/import <file> [password]: Import an account from a backup",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/get [key]: get account details (if key specified, only get key)",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/set <key> <value>: set account detail",
-                )));
+                self.switch_account(account);
+                None
+            }
+            "/unified" => {
+                self.set_unified(!self.data.unified);
+                None
+            }
+            "/add" => {
+                self.wizard = Some(AccountWizard::new(WizardKind::Create));
+                Some(false)
+            }
+            "/rm" => {
+                self.backend.rm_account(command.arg(0));
+                Some(true)
+            }
+            "/import" => {
+                let mut wizard = AccountWizard::new(WizardKind::Import);
+                wizard.set_value("archive", command.arg(0));
+                self.wizard = Some(wizard);
+                Some(false)
+            }
+            "/link" => {
+                self.wizard = Some(AccountWizard::new(WizardKind::Link));
+                Some(false)
+            }
+            "/devices" => {
+                self.show_devices(account_id);
+                Some(false)
+            }
+            "/device-name" => {
+                let mut details = self.backend.get_account_details(&account_id);
+                details.insert(String::from("Account.deviceName"), command.arg(0).to_string());
+                self.backend.set_account_details(&account_id, details);
                 channel
                     .messages
-                    .push(Message::info(String::from("/exit: quit")));
+                    .push(Message::info(format!("This device is now named {}", command.arg(0))));
+                Some(false)
             }
-        } else if channel.channel_type == ChannelType::Group {
-            let account_id = &self.data.account.id;
-            if message == "/leave" {
-                if Jami::rm_conversation(&account_id, &channel.id) {
-                    return;
+            "/revoke" => {
+                let device_id = command.arg(0).to_string();
+                let details = self.backend.get_account_details(&account_id);
+                if details.get("Account.deviceID") == Some(&device_id) {
+                    channel.messages.push(Message::info(String::from(
+                        "Cannot revoke this device, use /rm to remove the account",
+                    )));
                 } else {
+                    self.password_prompt = Some(PasswordPrompt::new(PasswordRequest::Revoke { account_id, device_id }));
+                }
+                Some(false)
+            }
+            "/export" => {
+                // The daemon doesn't run in our directory
+                let path = absolute_path(command.arg(0));
+                self.password_prompt = Some(PasswordPrompt::new(PasswordRequest::Export { account_id, path }));
+                Some(false)
+            }
+            "/pin" => {
+                self.password_prompt = Some(PasswordPrompt::new(PasswordRequest::Pin { account_id }));
+                Some(false)
+            }
+            "/leave" => match channel_type {
+                ChannelType::Group => {
+                    if self.backend.rm_conversation(&account_id, &channel.id) {
+                        return None;
+                    }
                     channel
                         .messages
                         .push(Message::info(String::from("Cannot remove conversation")));
+                    Some(true)
                 }
-            } else if message.starts_with("/invite") {
-                let mut member = String::from(message.strip_prefix("/invite ").unwrap());
-                if Jami::is_hash(&member) {
-                    Jami::add_conversation_member(&account_id, &channel.id, &member);
-                } else {
-                    let mut ns = String::new();
-                    if member.find("@") != None {
-                        let member_cloned = member.clone();
-                        let split: Vec<&str> = member_cloned.split("@").collect();
-                        member = split[0].to_string();
-                        ns = split[1].to_string();
+                _ => {
+                    match channel_type {
+                        ChannelType::TrustRequest(from) => {
+                            self.backend.discard_trust_request(&account_id, from)
+                        }
+                        _ => self.backend.decline_request(&account_id, &channel.id),
                     }
-                    self.data.out_invite.push(OutgoingInvite {
-                        account: account_id.to_string(),
-                        channel: Some(channel.id.clone()),
-                        member: member.clone(),
-                    });
-                    show_msg = false;
-                    Jami::lookup_name(&account_id, &ns, &member);
+                    self.data.channels.items.remove(channel_idx);
+                    if !self.data.channels.items.is_empty() {
+                        self.data.channels.state.select(Some(0));
+                    }
+                    None
                 }
-            } else if message.starts_with("/title") {
-                let title = String::from(message.strip_prefix("/title ").unwrap());
-                let mut infos = HashMap::new();
-                infos.insert(String::from("title"), title);
-                Jami::update_conversation_infos(&account_id, &channel.id, infos);
-                show_msg = false;
-            } else if message.starts_with("/description") {
-                let description = String::from(message.strip_prefix("/description ").unwrap());
-                let mut infos = HashMap::new();
-                infos.insert(String::from("description"), description);
-                Jami::update_conversation_infos(&account_id, &channel.id, infos);
-                show_msg = false;
-            } else if message.starts_with("/kick") {
-                let mut member = String::from(message.strip_prefix("/kick ").unwrap());
-                if Jami::is_hash(&member) {
-                    Jami::rm_conversation_member(&account_id, &channel.id, &member);
-                } else {
-                    let mut ns = String::new();
-                    if member.find("@") != None {
-                        let member_cloned = member.clone();
-                        let split: Vec<&str> = member_cloned.split("@").collect();
-                        member = split[0].to_string();
-                        ns = split[1].to_string();
+            },
+            "/join" => match channel_type {
+                ChannelType::TrustRequest(from) => {
+                    self.backend.accept_trust_request(&account_id, from);
+                    if !self.data.channels.items.is_empty() {
+                        self.data.channels.state.select(Some(0));
                     }
+                    self.data.channels.items.remove(channel_idx);
+                    None
+                }
+                _ => {
+                    self.backend.accept_request(&account_id, &channel.id);
+                    channel
+                        .messages
+                        .push(Message::info(String::from("Syncing… the view will update")));
+                    Some(true)
+                }
+            },
+            "/invite" => {
+                let member = command.arg(0);
+                if Jami::is_hash(&member.to_string()) {
+                    self.backend.add_conversation_member(&account_id, &channel.id, &member.to_string());
+                    Some(true)
+                } else {
+                    let (member, ns) = split_name_server(member);
+                    self.data.out_invite.push(OutgoingInvite {
+                        account: account_id.clone(),
+                        channel: Some(channel.id.clone()),
+                        member: member.clone(),
+                    });
+                    self.backend.lookup_name(&account_id, &ns, &member);
+                    Some(false)
+                }
+            }
+            "/kick" => {
+                let member = command.arg(0);
+                if Jami::is_hash(&member.to_string()) {
+                    self.backend.rm_conversation_member(&account_id, &channel.id, &member.to_string());
+                    Some(true)
+                } else {
+                    let (member, ns) = split_name_server(member);
                     self.data.pending_rm.push(PendingRm {
-                        account: account_id.to_string(),
+                        account: account_id.clone(),
                         channel: channel.id.clone(),
                         member: member.clone(),
                     });
-                    show_msg = false;
-                    Jami::lookup_name(&account_id, &ns, &member);
+                    self.backend.lookup_name(&account_id, &ns, &member);
+                    Some(false)
+                }
+            }
+            "/notify" => {
+                let text = match command.arg(0) {
+                    "" => format!(
+                        "Notifications: {}",
+                        self.config.notifications.mode(&channel.id, channel.notify).name()
+                    ),
+                    mode => match NotifyMode::parse(mode) {
+                        Ok(mode) => {
+                            channel.notify = Some(mode);
+                            self.cache_dirty = true;
+                            format!("Notifications: {}", mode.name())
+                        }
+                        Err(err) => err.to_string(),
+                    },
+                };
+                channel.messages.push(Message::info(text));
+                Some(false)
+            }
+            "/title" | "/description" => {
+                let mut infos = HashMap::new();
+                infos.insert(String::from(&command.name[1..]), command.arg(0).to_string());
+                self.backend.update_conversation_infos(&account_id, &channel.id, infos);
+                Some(false)
+            }
+            "/send" => {
+                self.backend.send_file(&account_id, &channel.id, &command.arg(0).to_string());
+                Some(false)
+            }
+            "/accept" => {
+                let tid = command.number(0);
+                let conversation_id = channel.id.clone();
+                let path = match command.arg(1) {
+                    "" => self.default_download_path(&account_id, &conversation_id, tid),
+                    path => Some(path.to_string()),
+                };
+                match path {
+                    Some(path) => self.backend.accept_file_transfer(&account_id, &conversation_id, tid, &path),
+                    None => {
+                        let channel = &mut self.data.channels.items[channel_idx];
+                        channel.messages.push(Message::info(String::from("Cannot accept file")));
+                    }
                 }
-            } else if message.starts_with("/send ") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let path = parts.get(1).unwrap_or(&"").to_string();
-                Jami::send_file(account_id.to_string(), channel.id.clone(), path);
-                show_msg = false;
-            } else if message.starts_with("/accept ") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let tid = parts.get(1).unwrap_or(&"").to_string().parse::<u64>().unwrap_or(0);
-                let mut path = parts.get(2).unwrap_or(&"").to_string();
-                if path.is_empty() {
-                    let default_download_dir = format!("{}/Jami", dirs::download_dir().unwrap().into_os_string().into_string().unwrap());
-                    let _ = create_dir(default_download_dir.clone());
-                    let info = Jami::data_transfer_info(account_id.clone(), channel.id.clone(), tid);
-                    if info.is_none() {
+                Some(false)
+            }
+            "/cancel" => {
+                self.backend.cancel_file_transfer(&account_id, &channel.id, command.number(0));
+                Some(false)
+            }
+            "/call" => {
+                let conversation_id = channel.id.clone();
+                let peer = match self.call_peer(channel_idx, command.arg(0)) {
+                    Some(peer) => peer,
+                    None => {
+                        let channel = &mut self.data.channels.items[channel_idx];
                         channel.messages.push(Message::info(String::from(
-                            "Cannot accept file",
+                            "Nobody to call, use /call <member>",
                         )));
-                    } else {
-                        let info = info.unwrap();
-                        let mut idx = 0;
-                        loop {
-                            let p = match idx {
-                                0 => format!("{}/{}", default_download_dir, info.display_name.clone()),
-                                i => format!("{}/{}_{}", default_download_dir, info.display_name.clone(), i),
-                            };
-                            if !Path::new(&p).exists() {
-                                path = p.clone();
-                                break;
-                            }
-                            idx += 1;
-                        } 
+                        return Some(false);
+                    }
+                };
+                match self.backend.place_call(&account_id, &peer) {
+                    Some(call_id) => {
+                        let mut call = Call::new(call_id, account_id, peer, CallState::Connecting);
+                        call.conversation_id = conversation_id;
+                        self.calls.push(call);
+                    }
+                    None => {
+                        let channel = &mut self.data.channels.items[channel_idx];
+                        channel.messages.push(Message::info(String::from("Cannot place the call")));
                     }
                 }
-                if !path.is_empty() {
-                    Jami::accept_file_transfer(&account_id, &channel.id, tid, &path);
+                Some(false)
+            }
+            "/accept-call" | "/refuse-call" => {
+                let incoming = self.calls.iter().rposition(|call| call.state == CallState::Incoming);
+                match incoming {
+                    Some(idx) if command.name == "/accept-call" => self.backend.accept_call(&self.calls[idx].id),
+                    Some(idx) => self.backend.refuse_call(&self.calls[idx].id),
+                    None => channel.messages.push(Message::info(String::from("No incoming call"))),
                 }
-                show_msg = false;
-            } else if message.starts_with("/cancel ") {
-                let parts: Vec<&str> = message.split(" ").collect();
-                let tid = parts.get(1).unwrap_or(&"").to_string().parse::<u64>().unwrap_or(0);
-                Jami::cancel_file_transfer(&account_id, &channel.id, tid);
-                show_msg = false;
-            } else if message == "/help" {
-                channel
-                    .messages
-                    .push(Message::info(String::from("/help: Show this help")));
-                channel.messages.push(Message::info(String::from(
-                    "/leave: Leave this conversation",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/invite [hash|username]: Invite somebody to the conversation",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/kick [hash|username]: Kick someone from the conversation",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/title [title]: Change the title of the room",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/description [description]: Change the description of the room",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/send [path]: Send a file to the conversation",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/accept [tid] <path>: Accept a file transfer",
-                )));
-                channel.messages.push(Message::info(String::from(
-                    "/cancel [tid]: Cancel a file transfer",
-                )));
-                channel
-                    .messages
-                    .push(Message::info(String::from("/exit: quit")));
-            } else {
-                show_msg = false;
-                Jami::send_conversation_message(&account_id, &channel.id, &message, &String::new());
-            }
-        } else if is_invite || is_trust_request {
-            let account_id = &self.data.account.id;
-            if message == "/leave" {
-                if is_invite {
-                    Jami::decline_request(&account_id, &channel.id);
-                } else {
-                    Jami::discard_trust_request(&account_id, &from_request);
+                Some(false)
+            }
+            "/hangup" | "/hold" | "/mute" => {
+                if command.name == "/hangup" {
+                    let conference = self.conferences.iter().find(|conf| conf.conversation_id == channel.id);
+                    if let Some(conference) = conference {
+                        self.backend.hang_up_conference(&conference.id);
+                        return Some(false);
+                    }
                 }
-                self.data.channels.items.remove(channel_idx);
-                if !self.data.channels.items.is_empty() {
-                    self.data.channels.state.select(Some(0));
+                // The call of the channel, else the last one
+                let last_call = self.calls.len().checked_sub(1);
+                let call_idx = self
+                    .calls
+                    .iter()
+                    .rposition(|call| call.conversation_id == channel.id)
+                    .or(last_call);
+                let call = match call_idx {
+                    Some(call_idx) => &mut self.calls[call_idx],
+                    None => {
+                        channel.messages.push(Message::info(String::from("No call in progress")));
+                        return Some(false);
+                    }
+                };
+                match command.name {
+                    "/hangup" => self.backend.hang_up(&call.id),
+                    "/hold" => self.backend.hold_call(&call.id, call.state != CallState::Hold),
+                    _ => {
+                        call.muted = !call.muted;
+                        self.backend.mute_call(&call.id, call.muted);
+                    }
                 }
-                show_msg = false;
-            } else if message == "/join" {
-                if is_invite {
-                    Jami::accept_request(&account_id, &channel.id);
-                    channel
-                        .messages
-                        .push(Message::info(String::from("Syncing… the view will update")));
+                Some(false)
+            }
+            "/conference" => {
+                let conversation_id = channel.id.clone();
+                let own_uri = self.own_uri(&account_id);
+                let channel = &mut self.data.channels.items[channel_idx];
+                let participants: Vec<String> = channel
+                    .members
+                    .iter()
+                    .filter(|m| m.hash != own_uri && !matches!(m.role, Role::Invited))
+                    .map(|m| m.hash.clone())
+                    .collect();
+                if self.conferences.iter().any(|conf| conf.conversation_id == conversation_id) {
+                    channel.messages.push(Message::info(String::from(
+                        "A conference is already in progress, use /conf-invite",
+                    )));
+                } else if participants.is_empty() {
+                    channel.messages.push(Message::info(String::from("Nobody to call")));
                 } else {
-                    Jami::accept_trust_request(&account_id, &from_request);
-                    if !self.data.channels.items.is_empty() {
-                        self.data.channels.state.select(Some(0));
+                    self.pending_conference = Some((account_id.clone(), conversation_id));
+                    self.backend.create_conference(&account_id, &participants);
+                }
+                Some(false)
+            }
+            "/conf-invite" => {
+                let conversation_id = channel.id.clone();
+                let conf_id = match self.conferences.iter().find(|conf| conf.conversation_id == conversation_id) {
+                    Some(conference) => conference.id.clone(),
+                    None => {
+                        channel.messages.push(Message::info(String::from(
+                            "No conference in progress, use /conference",
+                        )));
+                        return Some(false);
+                    }
+                };
+                let peer = self.call_peer(channel_idx, command.arg(0));
+                let call_id = peer.as_ref().and_then(|peer| self.backend.place_call(&account_id, peer));
+                match (peer, call_id) {
+                    (Some(peer), Some(call_id)) => {
+                        let mut call = Call::new(call_id, account_id, peer, CallState::Connecting);
+                        call.conversation_id = conversation_id;
+                        call.conference_id = conf_id;
+                        self.calls.push(call);
+                    }
+                    _ => {
+                        let channel = &mut self.data.channels.items[channel_idx];
+                        channel.messages.push(Message::info(format!("Cannot call {}", command.arg(0))));
                     }
-                    self.data.channels.items.remove(channel_idx);
-                    show_msg = false;
                 }
-            } else {
-                channel
-                    .messages
-                    .push(Message::info(String::from("/help: Show this help")));
-                channel
-                    .messages
-                    .push(Message::info(String::from("/leave: Decline this request")));
-                channel
-                    .messages
-                    .push(Message::info(String::from("/join: Accepts the request")));
+                Some(false)
             }
+            "/conf-kick" => {
+                let conversation_id = channel.id.clone();
+                let own_uri = self.own_uri(&account_id);
+                let peer = self.call_peer(channel_idx, command.arg(0)).unwrap_or_default();
+                let channel = &mut self.data.channels.items[channel_idx];
+                let is_admin = channel
+                    .members
+                    .iter()
+                    .any(|m| m.hash == own_uri && matches!(m.role, Role::Admin));
+                let conference = self.conferences.iter().find(|conf| conf.conversation_id == conversation_id);
+                let info = match conference {
+                    _ if !is_admin => String::from("Only admins can remove participants"),
+                    None => String::from("No conference in progress"),
+                    Some(conference) if conference.participants.iter().any(|p| p.uri == peer) => {
+                        self.backend.hang_up_participant(&conference.id, &peer);
+                        return Some(false);
+                    }
+                    Some(_) => format!("{} is not in the conference", command.arg(0)),
+                };
+                channel.messages.push(Message::info(info));
+                Some(false)
+            }
+            _ => Some(false),
         }
-
-        if show_msg {
-            let channel = &mut self.data.channels.items[channel_idx];
-            channel.messages.push(Message::new(
-                self.data.account.get_display_name(),
-                message.clone(),
-                Utc::now(),
-            ));
-        }
-
-        self.reset_unread_messages();
-        self.bubble_up_channel(channel_idx);
     }
 
     /**
@@ -456,9 +1130,12 @@ impl App {
         payloads: HashMap<String, String>,
     ) -> Option<()> {
         self.log(format!("incoming: {:?}", payloads));
-        if account_id == &*self.data.account.id {
+        // Incoming file to check against the auto-accept policy
+        let mut awaiting_transfer = None;
+        if self.data.shows_account(account_id) {
+            self.cache_dirty = true;
             for channel in &mut *self.data.channels.items {
-                if &*channel.id == conversation_id {
+                if channel.is(account_id, conversation_id) {
                     // Parse timestamp
                     let mut arrived_at = SystemTime::UNIX_EPOCH;
                     let tstr: String = payloads
@@ -477,150 +1154,210 @@ impl App {
                     // author
                     let author_str = payloads.get("author").unwrap_or(&String::new()).to_string();
                     let author = self.data.profile_manager.display_name(&author_str);
-                    // print message
-                    if payloads.get("type").unwrap() == "initial" {
-                        let mut initial_message = String::from("--> started the conversation");
-                        if payloads.get("mode").unwrap_or(&String::new()) == "0" {
-                            let uri = self
-                                .data
-                                .profile_manager
-                                .display_name(&payloads.get("invited").unwrap_or(&String::new()));
-                            initial_message = String::from(format!(
-                                "--> started a private conversation with {}",
-                                uri
-                            ));
-                        }
-                        channel
-                            .messages
-                            .push(Message::new(author, initial_message, arrived_at));
-                    } else if payloads.get("type").unwrap() == "text/plain" {
-                        channel.messages.push(Message::new(
-                            author,
-                            String::from(payloads.get("body").unwrap()),
-                            arrived_at,
-                        ));
-                    } else if payloads.get("type").unwrap() == "application/call-history+json" {
-                        let duration = payloads
-                            .get("duration")
-                            .unwrap_or(&String::from("0"))
-                            .clone();
-                        let duration = duration.parse::<i32>();
-                        if duration.is_err() {
-                            return Some(());
-                        }
-                        let duration = duration.unwrap() / 1000;
-                        let mut message = format!("📞 Call with duration: {} secs", duration);
-                        if duration == 0 {
-                            message = format!("❌ Call missed");
+                    let commit_id = payloads.get("id").unwrap_or(&String::new()).to_string();
+                    let commit_type = payloads.get("type").cloned().unwrap_or_default();
+                    let kind = match commit_type.as_str() {
+                        "initial" => Some(MessageKind::Initial {
+                            invited: match payloads.get("mode").map(|mode| mode.as_str()) {
+                                Some("0") => payloads.get("invited").cloned(),
+                                _ => None,
+                            },
+                        }),
+                        "text/plain" => Some(MessageKind::Text(payloads.get("body").cloned().unwrap_or_default())),
+                        "application/call-history+json" => {
+                            let duration = payloads.get("duration").map(|d| d.as_str()).unwrap_or("0");
+                            match duration.parse::<u64>() {
+                                Ok(duration) => Some(MessageKind::Call { duration_secs: duration / 1000 }),
+                                Err(_) => return Some(()),
+                            }
                         }
-                        channel.messages.push(Message::new(
-                            author,
-                            String::from(message),
-                            arrived_at,
-                        ));
-                    } else if payloads.get("type").unwrap() == "application/data-transfer+json" {
-                        let tid = payloads.get("tid").unwrap_or(&String::new()).clone();
-                        let display_name = payloads.get("displayName").unwrap_or(&String::new()).clone();
-
-                        let mut status = String::from("not downloaded");
-                        let info = Jami::data_transfer_info(account_id.clone(), conversation_id.clone(), tid.parse::<u64>().unwrap_or(0));
-                        if !info.is_none() {
-                            status = match info.unwrap().last_event {
-                                3  => String::from("awaiting peer"),
-                                4  => String::from("awaiting host"),
-                                5  => String::from("ongoing"),
-                                6  => String::from("finished"),
-                                7  => String::from("closed by host"),
-                                8  => String::from("closed by peer"),
-                                10 => String::from("unjoinable peer"),
-                                11 => String::from("timeout expired"),
-                                _  => String::from("not downaloaded"),
+                        "application/data-transfer+json" => {
+                            let tid = payloads.get("tid").cloned().unwrap_or_default();
+                            let info = self
+                                .backend
+                                .data_transfer_info(account_id, conversation_id, tid.parse::<u64>().unwrap_or(0));
+                            let path = self.data.transfer_manager.path(account_id.clone(), conversation_id.clone(), tid.clone());
+                            let status = match (&info, &path) {
+                                // Forgotten by the daemon, but the file was saved
+                                (None, Some(_)) => TransferStatus::Finished,
+                                _ => TransferStatus::from_code(info.as_ref().map_or(0, |info| info.last_event)),
                             };
+                            let info = info.unwrap_or_default();
+                            if status == TransferStatus::AwaitingHost {
+                                awaiting_transfer = Some((author_str.clone(), tid.parse::<u64>().unwrap_or(0)));
+                            }
+                            Some(MessageKind::Transfer {
+                                tid: tid.parse::<u64>().unwrap_or(0),
+                                name: payloads.get("displayName").cloned().unwrap_or_default(),
+                                status,
+                                path,
+                                bytes: info.bytes_progress,
+                                total: info.total_size,
+                                rate: 0,
+                            })
                         }
-    
-                        let message = match self.data.transfer_manager.path(account_id.clone(), conversation_id.clone(), tid.clone()) {
-                            None => format!("<New file transfer with id: {} - {} - {}>", tid, display_name, status),
-                            Some(path) => format!("<file://{}>", path),
-                        };
-                        channel.messages.push(Message::new(
-                            author,
-                            String::from(message),
-                            arrived_at,
-                        ));
-                    } else if payloads.get("type").unwrap() == "application/update-profile" {
-                        // Do not show update infos commits
-                        let new_infos = Jami::get_conversation_infos(account_id, conversation_id);
-                        channel.update_infos(new_infos);
-                    } else if payloads.get("type").unwrap() == "merge" {
-                        // Do not show merge commits
-                    } else if payloads.get("type").unwrap() == "member" {
-                        let action = payloads.get("action");
-                        let uri = payloads.get("uri");
-                        if action.is_none() || uri.is_none() {
-                            return Some(());
+                        "application/update-profile" => {
+                            // Do not show update infos commits
+                            let new_infos = self.backend.get_conversation_infos(account_id, conversation_id);
+                            channel.update_infos(new_infos);
+                            None
                         }
-                        let action = String::from(action.unwrap());
-                        let uri = String::from(uri.unwrap());
-                        let uri = self.data.profile_manager.display_name(&uri);
-                        if action == "add" {
-                            let msg = format!("--> | {} has been added", uri);
-                            channel.messages.push(Message::new(
-                                author,
-                                String::from(msg),
-                                arrived_at,
-                            ));
-                        } else if action == "join" {
-                            let msg = format!("--> | {} joins the conversation", uri);
-                            channel.messages.push(Message::new(
-                                author,
-                                String::from(msg),
-                                arrived_at,
-                            ));
-                        } else if action == "ban" {
-                            let msg = format!("<-- | {} was banned from the conversation", uri);
-                            channel.messages.push(Message::new(
-                                author,
-                                String::from(msg),
-                                arrived_at,
-                            ));
-                        } else if action == "remove" {
-                            let msg = format!("<-- | {} leaves the conversation", uri);
-                            channel.messages.push(Message::new(
-                                author,
-                                String::from(msg),
-                                arrived_at,
-                            ));
+                        // Do not show merge commits
+                        "merge" => None,
+                        "member" => {
+                            let action = payloads.get("action").map(|action| action.as_str()).and_then(MemberAction::parse);
+                            let uri = match payloads.get("uri") {
+                                Some(uri) => uri.clone(),
+                                None => return Some(()),
+                            };
+                            channel.members = AppData::get_conversations_members(&*self.backend, account_id, &conversation_id);
+                            for member in &*channel.members {
+                                self.backend.subscribe_presence(account_id, &member.hash, true);
+                            }
+                            action.map(|action| MessageKind::Member { action, uri })
                         }
-                        channel.members = AppData::get_conversations_members(account_id, &conversation_id);
-                        for member in &*channel.members {
-                            Jami::subscribe_presence(&self.data.account.id, &member.hash, true);
+                        _ => Some(MessageKind::Unknown(payloads.clone())),
+                    };
+                    if let Some(kind) = kind {
+                        let mut message = Message::with_kind(author, kind, arrived_at);
+                        message.id = commit_id;
+                        message.reply_to = payloads.get("reply-to").cloned().unwrap_or_default();
+                        message.author = author_str;
+                        message.payload = payloads.clone();
+                        if let (MessageKind::Text(_), Some(search_index)) = (&message.kind, &self.search_index) {
+                            let _ = search_index.add(account_id, conversation_id, &message);
                         }
-                    } else {
-                        channel.messages.push(Message::new(
-                            author,
-                            String::from(format!("{:?}", payloads)),
-                            arrived_at,
-                        ));
+                        channel.messages.push(message);
                     }
                 }
             }
         }
+        if let Some((author, tid)) = awaiting_transfer {
+            if !self.auto_accepted.contains(&tid) {
+                self.auto_accept_transfer(account_id, conversation_id, &author, tid);
+            }
+        }
         Some(())
     }
 
+    /**
+     * Count and notify messages received while their channel isn't selected
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param known             number of messages of the channel before the new ones
+     */
+    fn on_new_messages(&mut self, account_id: &String, conversation_id: &String, known: usize) {
+        if !self.data.shows_account(account_id) {
+            return;
+        }
+        let channel_idx = match self
+            .data
+            .channels
+            .items
+            .iter()
+            .position(|channel| channel.is(account_id, conversation_id))
+        {
+            Some(channel_idx) => channel_idx,
+            None => return,
+        };
+        if self.data.channels.state.selected() == Some(channel_idx) {
+            return;
+        }
+        let own_uri = self.own_uri(account_id);
+        let mention_names = self.mention_names(account_id);
+        let channel = &mut self.data.channels.items[channel_idx];
+        let new_messages: Vec<Message> = channel
+            .messages
+            .iter()
+            .skip(known)
+            .filter(|message| !message.author.is_empty() && message.author != own_uri)
+            .cloned()
+            .collect();
+        channel.unread_messages += new_messages.len();
+        let mode = self.config.notifications.mode(&channel.id, channel.notify);
+        let title = channel.bestname();
+        for message in new_messages {
+            let mentioned = notifications::mentions(message.text(), &mention_names);
+            if mentioned {
+                self.data.channels.items[channel_idx].unread_mentions += 1;
+            }
+            let notify = match mode {
+                NotifyMode::All => true,
+                NotifyMode::Mentions => mentioned,
+                NotifyMode::None => false,
+            };
+            if notify {
+                let body = ui::message_text(&self.data.profile_manager, &message.kind);
+                self.notify(&title, &format!("{}: {}", message.from, body));
+            }
+        }
+    }
+
+    /**
+     * Words highlighted in messages: uri, registered and display names of
+     * the user, and keywords of the config
+     * @param self
+     * @param account_id
+     */
+    pub fn mention_names(&mut self, account_id: &String) -> Vec<String> {
+        if let Some(names) = self.mention_names.get(account_id) {
+            return names.clone();
+        }
+        let details = self.backend.get_account_details(account_id);
+        let mut names = vec![
+            self.own_uri(account_id),
+            details.get("Account.registeredName").cloned().unwrap_or_default(),
+            self.backend
+                .get_account_list()
+                .iter()
+                .find(|account| &account.id == account_id)
+                .map(|account| account.get_display_name())
+                .unwrap_or_default(),
+        ];
+        names.extend(self.config.notifications.keywords.iter().cloned());
+        names.retain(|name| !name.is_empty());
+        self.mention_names.insert(account_id.clone(), names.clone());
+        names
+    }
+
+    /**
+     * Notify the user with the bell, the terminal and the command of the config
+     * @param self
+     * @param title
+     * @param body
+     */
+    fn notify(&mut self, title: &str, body: &str) {
+        let policy = &self.config.notifications;
+        self.terminal_output.extend(policy.terminal_output(title, body));
+        if let Err(err) = policy.run_command(title, body) {
+            self.log(format!("cannot run the notification command: {}", err));
+        }
+    }
+
     /**
      * When an account is registered
      * @param self
-     * @param _account_id
+     * @param account_id
      * @param registration_state        "REGISTERED" on ready
      */
     pub async fn on_registration_state_changed(
         &mut self,
-        _account_id: &String,
+        account_id: &String,
         registration_state: &String,
     ) {
+        // The registered name may have changed
+        self.mention_names.clear();
+        if let Some(wizard) = &mut self.wizard {
+            if &wizard.account_id == account_id && wizard.on_registration_state(registration_state) {
+                let (password, username) = (wizard.value("password").to_string(), wizard.value("username").to_string());
+                if !self.backend.register_name(account_id, &password, &username) {
+                    wizard.progress = Progress::Failed(format!("Cannot register {}", username));
+                }
+            }
+        }
         if registration_state == "REGISTERED" && self.data.account == Account::null() {
-            self.data.account = Jami::select_jami_account(false);
+            self.data.account = self.backend.select_jami_account(false);
         }
     }
 
@@ -628,7 +1365,7 @@ impl App {
      * On presence changed for a member
      */
     pub async fn on_member_presence_changed(&mut self, account_id: &String, uri: &String, flag: bool) {
-        if self.data.account.id == *account_id {
+        if self.data.shows_account(account_id) {
             self.data.tracked_presences.insert(uri.to_string(), flag);
         }
     }
@@ -637,16 +1374,14 @@ impl App {
      * Triggered when an account is deleted or added
      */
     pub async fn on_accounts_changed(&mut self) {
-        let mut still_there = false;
-        for account in Jami::get_account_list() {
-            if account.id == self.data.account.id {
-                still_there = true;
-                break;
-            }
-        }
+        let still_there = self
+            .backend
+            .get_account_list()
+            .iter()
+            .any(|account| account.id == self.data.account.id);
         if !still_there {
             // Reselect an account
-            self.data.account = Jami::select_jami_account(false);
+            self.data.account = self.backend.select_jami_account(false);
             if self.data.account.id.is_empty() {
                 self.data.channels.state.select(Some(0));
                 self.data
@@ -660,15 +1395,26 @@ impl App {
                     )));
                 return;
             }
-            self.data
-                .profile_manager
-                .load_from_account(&self.data.account.id);
-            let channels = AppData::channels_for_account(&self.data.account);
-            self.data.channels = StatefulList::with_items(channels);
-            if !self.data.channels.items.is_empty() {
-                self.data.channels.state.select(Some(0));
-            }
-            self.data.lookup_members();
+        }
+        // In unified mode, accounts added or removed change the shown channels too
+        let shown: Vec<String> = self
+            .data
+            .shown_accounts(&*self.backend)
+            .into_iter()
+            .map(|account| account.id)
+            .collect();
+        let headers: Vec<String> = self
+            .data
+            .channels
+            .items
+            .iter()
+            .filter(|channel| channel.channel_type == ChannelType::Generated)
+            .map(|channel| channel.account_id.clone())
+            .collect();
+        if !still_there || shown != headers {
+            self.needs_sync = false;
+            self.data.reload_channels(&*self.backend);
+            self.data.lookup_members(&*self.backend);
         }
     }
 
@@ -697,285 +1443,1694 @@ impl App {
         if result.is_err() {
             return;
         }
-        self.data.profile_manager.load_profile(&dest);
-        Jami::lookup_name(&account_id, &String::new(), &from);
+        self.data.profile_manager.load_profile(&dest);
+        self.backend.lookup_name(&account_id, &String::new(), &from);
+    }
+
+    /**
+     * When a conversation is loaded
+     * @param self
+     * @param _id
+     * @param account_id
+     * @param conversation_id
+     * @param messages
+     * @return if ok
+     */
+    pub async fn on_conversation_loaded(
+        &mut self,
+        id: u32,
+        account_id: String,
+        conversation_id: String,
+        messages: Vec<HashMap<String, String>>,
+    ) -> Option<()> {
+        if self.backfill.is_page(id) {
+            let search_index = self.search_index.as_ref()?;
+            if let Some(request) = self.backfill.on_page(search_index, &messages, &self.data.profile_manager) {
+                self.request_backfill_page(request);
+            }
+            return Some(());
+        }
+        // Messages are sent from the newest to the oldest
+        let oldest_commit = messages
+            .last()
+            .and_then(|msg| msg.get("id"))
+            .cloned()
+            .unwrap_or_default();
+        let batch_len = messages.len();
+
+        // The batch is parsed alone, then merged with the already loaded messages
+        let mut loaded_messages = Vec::new();
+        let mut loading_history = false;
+        if self.data.shows_account(&account_id) {
+            for channel in &mut *self.data.channels.items {
+                if channel.is(&account_id, &conversation_id) {
+                    loading_history = channel.loading_history;
+                    loaded_messages = std::mem::take(&mut channel.messages);
+                }
+            }
+        }
+
+        let messages: Vec<_> = messages.into_iter().rev().collect();
+        for msg in messages {
+            let _ = self.on_message(&account_id, &conversation_id, msg).await;
+        }
+
+        if self.data.shows_account(&account_id) {
+            for channel in &mut *self.data.channels.items {
+                if !channel.is(&account_id, &conversation_id) {
+                    continue;
+                }
+                if loading_history {
+                    // Older messages go first. The "from" commit is sent again by the daemon, so drop duplicates
+                    let known_ids: Vec<&String> = loaded_messages
+                        .iter()
+                        .filter(|msg| !msg.id.is_empty())
+                        .map(|msg| &msg.id)
+                        .collect();
+                    channel
+                        .messages
+                        .retain(|msg| msg.id.is_empty() || !known_ids.contains(&&msg.id));
+                    channel.messages.append(&mut loaded_messages);
+                } else if channel.messages.is_empty() {
+                    // Nothing new to show, keep cached messages
+                    channel.messages.append(&mut loaded_messages);
+                }
+                if !oldest_commit.is_empty() {
+                    channel.oldest_commit = oldest_commit.clone();
+                }
+                channel.loading_history = false;
+                channel.history_complete = batch_len < HISTORY_BATCH_SIZE as usize;
+            }
+            self.continue_jump(&account_id, &conversation_id);
+        }
+        Some(())
+    }
+
+    pub async fn on_data_transfer_event(
+        &mut self,
+        account_id: String,
+        conversation_id: String,
+        tid: u64,
+        status: i32
+    ) -> Option<()> {
+        let info = self.backend.data_transfer_info(&account_id, &conversation_id, tid);
+        if !info.is_none() {
+            let info = info.unwrap();
+            match self.data.transfer_manager.path(account_id.clone(), conversation_id.clone(), tid.to_string()) {
+                None => {
+                    if info.flags == 0 /* outgoing */ {
+                        self.data.transfer_manager.set_file_path(account_id.clone(), conversation_id.clone(), tid.to_string(), info.path.clone());
+                    } else if status == 6 /* Finished */ {
+                        self.data.transfer_manager.set_file_path(account_id.clone(), conversation_id.clone(), tid.to_string(), info.path.clone());
+                    }
+                },
+                _ => {},
+            };
+
+            if self.data.shows_account(&account_id) {
+                self.update_transfer(&account_id, &conversation_id, tid, TransferStatus::from_code(status), &info);
+                if self.focus == Focus::Transfers {
+                    self.refresh_transfers();
+                }
+            }
+        }
+        Some(())
+    }
+
+    /**
+     * Free path in the download directory for a transfer
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param tid
+     * @return None if the transfer is unknown
+     */
+    fn default_download_path(&self, account_id: &String, conversation_id: &String, tid: u64) -> Option<String> {
+        let info = self.backend.data_transfer_info(account_id, conversation_id, tid)?;
+        let conversation_name = self
+            .data
+            .channels
+            .items
+            .iter()
+            .find(|channel| channel.is(account_id, conversation_id))
+            .map(|channel| channel.bestname())
+            .unwrap_or_default();
+        // The name comes from the sender
+        let name = safe_file_name(&info.display_name)?;
+        let dir = self.config.transfers.download_dir(conversation_id, &conversation_name)?;
+        let _ = create_dir_all(&dir);
+        free_path(&dir, name).into_os_string().into_string().ok()
+    }
+
+    /**
+     * Accept an incoming file if the policy of the config allows it
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param author            uri of the sender
+     * @param tid
+     */
+    fn auto_accept_transfer(&mut self, account_id: &String, conversation_id: &String, author: &String, tid: u64) {
+        let info = match self.backend.data_transfer_info(account_id, conversation_id, tid) {
+            Some(info) => info,
+            None => return,
+        };
+        let policy = &self.config.transfers;
+        let from_contact = policy.from_contacts && self.backend.get_contacts(account_id).contains(author);
+        if !policy.should_accept(conversation_id, from_contact, &info.display_name, info.total_size) {
+            return;
+        }
+        if let Some(path) = self.default_download_path(account_id, conversation_id, tid) {
+            self.auto_accepted.insert(tid);
+            self.backend.accept_file_transfer(account_id, conversation_id, tid, &path);
+        }
+    }
+
+    /**
+     * Refresh what changes with time, like the progress of transfers.
+     * The daemon doesn't send events while a transfer is ongoing.
+     * @param self
+     */
+    pub fn on_tick(&mut self) {
+        if let Some(wizard) = &mut self.wizard {
+            if let Some(name) = wizard.tick(Instant::now()) {
+                // No account is needed to look up a name
+                self.backend.lookup_name(&String::new(), &String::new(), &name);
+            }
+        }
+        let mut ongoing = Vec::new();
+        for channel in &self.data.channels.items {
+            for message in &channel.messages {
+                if let MessageKind::Transfer {
+                    tid,
+                    status: TransferStatus::Ongoing,
+                    ..
+                } = message.kind
+                {
+                    ongoing.push((self.data.account_of(channel), channel.id.clone(), tid));
+                }
+            }
+        }
+        for (account_id, conversation_id, tid) in ongoing {
+            if let Some(info) = self.backend.data_transfer_info(&account_id, &conversation_id, tid) {
+                let status = TransferStatus::from_code(info.last_event);
+                self.update_transfer(&account_id, &conversation_id, tid, status, &info);
+            }
+        }
+        if self.focus == Focus::Transfers {
+            self.refresh_transfers();
+        }
+        self.continue_backfill();
+    }
+
+    /**
+     * Index the history of the next conversation, if no page is being loaded
+     * @param self
+     */
+    fn continue_backfill(&mut self) {
+        // Wait for the channels of the daemon
+        if self.needs_sync {
+            return;
+        }
+        let search_index = match &self.search_index {
+            Some(search_index) => search_index,
+            None => return,
+        };
+        let conversations: Vec<(String, String)> = self
+            .data
+            .channels
+            .items
+            .iter()
+            .filter(|channel| channel.channel_type == ChannelType::Group)
+            .map(|channel| (self.data.account_of(channel), channel.id.clone()))
+            .collect();
+        if let Some(request) = self.backfill.start(search_index, &conversations, Instant::now()) {
+            self.request_backfill_page(request);
+        }
+    }
+
+    /**
+     * Ask a page of history to index to the daemon
+     * @param self
+     * @param request
+     */
+    fn request_backfill_page(&mut self, request: PageRequest) {
+        let request_id = self.backend.load_conversation(
+            &request.account_id,
+            &request.conversation_id,
+            &request.from,
+            HISTORY_BATCH_SIZE,
+        );
+        self.backfill.on_requested(request_id, Instant::now());
+    }
+
+    /**
+     * List transfers of the loaded conversations, with their state from the daemon
+     * @param self
+     */
+    fn refresh_transfers(&mut self) {
+        let mut transfers = Vec::new();
+        for channel in &self.data.channels.items {
+            let account_id = self.data.account_of(channel);
+            for message in &channel.messages {
+                if let MessageKind::Transfer {
+                    tid,
+                    name,
+                    status,
+                    path,
+                    bytes,
+                    total,
+                    ..
+                } = &message.kind
+                {
+                    let mut transfer = TransferEntry {
+                        account_id: account_id.clone(),
+                        conversation_id: channel.id.clone(),
+                        tid: *tid,
+                        name: name.clone(),
+                        status: *status,
+                        path: path.clone(),
+                        bytes: *bytes,
+                        total: *total,
+                    };
+                    if let Some(info) = self.backend.data_transfer_info(&account_id, &channel.id, *tid) {
+                        transfer.status = TransferStatus::from_code(info.last_event);
+                        transfer.bytes = info.bytes_progress;
+                        transfer.total = info.total_size;
+                    }
+                    transfers.push(transfer);
+                }
+            }
+        }
+        transfers.reverse();
+        let selected = match self.transfers.state.selected() {
+            _ if transfers.is_empty() => None,
+            Some(idx) => Some(idx.min(transfers.len() - 1)),
+            None => Some(0),
+        };
+        self.transfers.items = transfers;
+        self.transfers.state.select(selected);
+    }
+
+    /**
+     * Update the message of a transfer in place
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param tid
+     * @param new_status
+     * @param info              progress of the transfer
+     */
+    fn update_transfer(
+        &mut self,
+        account_id: &String,
+        conversation_id: &String,
+        tid: u64,
+        new_status: TransferStatus,
+        info: &TransferInfo,
+    ) {
+        let new_path = self.data.transfer_manager.path(
+            account_id.clone(),
+            conversation_id.clone(),
+            tid.to_string(),
+        );
+        let now = Instant::now();
+        let new_rate = match new_status {
+            TransferStatus::Ongoing => match self.transfer_samples.insert(tid, (now, info.bytes_progress)) {
+                Some((sampled_at, bytes)) if now > sampled_at => {
+                    let elapsed = now.duration_since(sampled_at).as_secs_f64();
+                    ((info.bytes_progress - bytes).max(0) as f64 / elapsed) as u64
+                }
+                _ => 0,
+            },
+            _ => {
+                self.transfer_samples.remove(&tid);
+                0
+            }
+        };
+        let channels = self
+            .data
+            .channels
+            .items
+            .iter_mut()
+            .filter(|channel| channel.is(account_id, conversation_id));
+        for channel in channels {
+            for message in &mut channel.messages {
+                if let MessageKind::Transfer {
+                    tid: message_tid,
+                    status,
+                    path,
+                    bytes,
+                    total,
+                    rate,
+                    ..
+                } = &mut message.kind
+                {
+                    if *message_tid == tid {
+                        *status = new_status;
+                        *path = new_path.clone();
+                        *bytes = info.bytes_progress;
+                        *total = info.total_size;
+                        *rate = new_rate;
+                        self.cache_dirty = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * When a new conversation is ready
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @return if ok
+     */
+    pub async fn on_conversation_ready(
+        &mut self,
+        account_id: String,
+        conversation_id: String,
+    ) -> Option<()> {
+        if self.data.shows_account(&account_id) {
+            self.cache_dirty = true;
+            self.data.channels.state.select(Some(0));
+            self.data
+                .channels
+                .items
+                .retain(|channel| !channel.is(&account_id, &conversation_id));
+            self.add_channel(&account_id, Channel::new(&conversation_id, ChannelType::Group));
+            self.data.channels.state.select(Some(0));
+        }
+        Some(())
+    }
+
+    /**
+     * When a conversation is removed
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @return if ok
+     */
+    pub async fn on_conversation_removed(
+        &mut self,
+        account_id: String,
+        conversation_id: String,
+    ) -> Option<()> {
+        if self.data.shows_account(&account_id) {
+            self.cache_dirty = true;
+            if let Some(idx) = self.data.channels.state.selected() {
+                let channel = &mut self.data.channels.items[idx];
+                if channel.is(&account_id, &conversation_id) {
+                    self.untrack_current_conversation();
+                    self.data.channels.state.select(Some(0));
+                }
+            }
+            self.data
+                .channels
+                .items
+                .retain(|channel| !channel.is(&account_id, &conversation_id));
+        }
+        Some(())
+    }
+
+    /**
+     * When receiving a new conversation request (not a trust request)
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @todo other parameters?
+     * @return if ok
+     */
+    pub async fn on_conversation_request(
+        &mut self,
+        account_id: String,
+        conversation_id: String,
+    ) -> Option<()> {
+        if self.data.shows_account(&account_id) {
+            self.add_channel(&account_id, Channel::new(&conversation_id, ChannelType::Invite));
+            self.data.channels.state.select(Some(0));
+            if self.config.notifications.mode(&conversation_id, None) != NotifyMode::None {
+                self.notify("Jami", "New conversation request");
+            }
+        }
+        Some(())
+    }
+
+    /**
+     * When receiving a new trust request
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @todo other parameters?
+     * @return if ok
+     */
+    pub async fn on_incoming_trust_request(
+        &mut self,
+        account_id: &String,
+        from: &String,
+        _payloads: Vec<u8>,
+        _receive_time: u64,
+    ) -> Option<()> {
+        if self.data.shows_account(account_id) {
+            self.add_channel(account_id, Channel::new(&from, ChannelType::TrustRequest(from.clone())));
+            self.data.channels.state.select(Some(0));
+            // The channel of a trust request is named after the sender
+            if self.config.notifications.mode(from, None) != NotifyMode::None {
+                let name = self.data.profile_manager.display_name(from);
+                self.notify("Jami", &format!("Trust request from {}", name));
+            }
+        }
+        Some(())
+    }
+
+    /**
+     * When a name is found, refresh UI if necessary and profile manager
+     * @param self
+     * @param account_id
+     * @param status        0 for success
+     * @param address       uri related
+     * @param name          name related
+     */
+    pub async fn on_registered_name_found(
+        &mut self,
+        account_id: String,
+        status: u64,
+        address: String,
+        name: String,
+    ) -> Option<()> {
+        self.data.profile_manager.username_found(&address, &name);
+        if let Some(wizard) = &mut self.wizard {
+            wizard.on_name_found(status, &name);
+        }
+        // pending invite
+        for i in 0..self.data.out_invite.len() {
+            let out_invite = &self.data.out_invite[i];
+            if out_invite.account == account_id && out_invite.member == name {
+                if status == 0 {
+                    if out_invite.channel.as_ref().is_none() {
+                        self.backend.add_contact(&out_invite.account, &address);
+                        self.backend.send_trust_request(
+                            &out_invite.account,
+                            &address,
+                            Vec::new(), /* TODO */
+                        );
+                    } else {
+                        let conversation = out_invite.channel.clone().unwrap();
+                        self.backend.add_conversation_member(&out_invite.account, &conversation, &address);
+                    }
+                } else {
+                    let channels = &mut self.data.channels.items;
+                    for channel in &mut *channels {
+                        if channel.is(&account_id, &out_invite.channel.clone().unwrap_or(String::new())) {
+                            channel
+                                .messages
+                                .push(Message::info(String::from("Cannot invite member")));
+                        }
+                    }
+                }
+                self.data.out_invite.remove(i);
+                break;
+            }
+        }
+
+        // pending remove
+        for i in 0..self.data.pending_rm.len() {
+            let pending_rm = &self.data.pending_rm[i];
+            if pending_rm.account == account_id && pending_rm.member == name {
+                if status == 0 {
+                    self.backend.rm_conversation_member(
+                        &pending_rm.account,
+                        &pending_rm.channel,
+                        &address,
+                    );
+                }
+                self.data.pending_rm.remove(i);
+                break;
+            }
+        }
+
+        Some(())
+    }
+
+    /**
+     * Handle account signals of the daemon
+     * @param self
+     * @param event
+     */
+    pub fn handle_account_event(&mut self, event: AccountEvent) {
+        match event {
+            AccountEvent::PinExported {
+                account_id,
+                status,
+                pin,
+            } => {
+                let info = match status {
+                    0 => {
+                        let expiry = Local::now() + chrono::Duration::from_std(PIN_VALIDITY).unwrap();
+                        format!("PIN: {} (valid until {})", pin, expiry.format("%H:%M"))
+                    }
+                    1 => String::from("Cannot generate a PIN: wrong password"),
+                    _ => String::from("Cannot generate a PIN: network error"),
+                };
+                self.push_generated_info(&account_id, info);
+            }
+            AccountEvent::NameRegistered {
+                account_id,
+                status,
+                name,
+            } => {
+                // The registered name is mentioned
+                self.mention_names.remove(&account_id);
+                if let Some(wizard) = &mut self.wizard {
+                    if wizard.account_id == account_id {
+                        wizard.on_name_registered(status, &name);
+                    }
+                }
+            }
+            AccountEvent::KnownDevicesChanged { account_id, devices } => {
+                if account_id == self.devices_account {
+                    self.set_devices(devices);
+                }
+            }
+            AccountEvent::DeviceRevoked {
+                account_id,
+                device_id,
+                status,
+            } => {
+                let info = match status {
+                    0 => format!("Device {} revoked", device_id),
+                    1 => format!("Cannot revoke device {}: wrong password", device_id),
+                    _ => format!("Cannot revoke device {}: unknown device", device_id),
+                };
+                self.push_generated_info(&account_id, info);
+            }
+        }
+    }
+
+    /**
+     * Open the devices panel
+     * @param self
+     * @param account_id    Account of the listed devices
+     */
+    fn show_devices(&mut self, account_id: String) {
+        self.devices_account = account_id;
+        let devices = self.backend.get_known_devices(&self.devices_account);
+        self.set_devices(devices);
+        self.focus = Focus::Devices;
+    }
+
+    /**
+     * Replace the devices of the panel, keeping the selection
+     * @param self
+     * @param devices       Name of each device id
+     */
+    fn set_devices(&mut self, devices: HashMap<String, String>) {
+        let current = self
+            .backend
+            .get_account_details(&self.devices_account)
+            .get("Account.deviceID")
+            .cloned()
+            .unwrap_or_default();
+        let mut devices: Vec<DeviceEntry> = devices
+            .into_iter()
+            .map(|(id, name)| DeviceEntry {
+                current: id == current,
+                id,
+                name,
+            })
+            .collect();
+        devices.sort_by(|a, b| b.current.cmp(&a.current).then_with(|| a.name.cmp(&b.name)));
+        let selected = match self.devices.state.selected() {
+            _ if devices.is_empty() => None,
+            Some(idx) => Some(idx.min(devices.len() - 1)),
+            None => Some(0),
+        };
+        self.devices.items = devices;
+        self.devices.state.select(selected);
+    }
+
+    /**
+     * Show an information in the generated channel of an account
+     * @param self
+     * @param account_id
+     * @param info
+     */
+    fn push_generated_info(&mut self, account_id: &String, info: String) {
+        let header = self.data.channels.items.iter().position(|channel| {
+            channel.channel_type == ChannelType::Generated && self.data.account_of(channel) == *account_id
+        });
+        if let Some(idx) = header {
+            self.data.channels.items[idx].messages.push(Message::info(info));
+        }
+    }
+
+    /**
+     * Handle call signals of the daemon
+     * @param self
+     * @param event
+     */
+    pub fn handle_call_event(&mut self, event: CallEvent) {
+        match event {
+            CallEvent::Incoming {
+                account_id,
+                call_id,
+                from,
+            } => {
+                let peer = normalize_uri(&from);
+                let own_uri = self.own_uri(&account_id);
+                let mut call = Call::new(call_id, account_id, peer.clone(), CallState::Incoming);
+                // The conversation of the call is the 1:1 conversation with the peer
+                if let Some(channel) = self.data.channels.items.iter().find(|channel| {
+                    channel.channel_type == ChannelType::Group
+                        && self.data.account_of(channel) == call.account_id
+                        && channel.members.len() == 2
+                        && channel.members.iter().all(|m| m.hash == peer || m.hash == own_uri)
+                }) {
+                    call.conversation_id = channel.id.clone();
+                }
+                self.calls.push(call);
+            }
+            CallEvent::StateChanged { call_id, state, .. } => {
+                let call_idx = match self.calls.iter().position(|call| call.id == call_id) {
+                    Some(call_idx) => call_idx,
+                    None => return,
+                };
+                let state = CallState::parse(&state);
+                if state.is_finished() {
+                    let call = self.calls.remove(call_idx);
+                    let peer = self.data.profile_manager.display_name(&call.peer);
+                    let info = match call.started_at {
+                        Some(_) => format!("Call with {} ended ({})", peer, format_duration(call.duration())),
+                        None => format!("Call with {} ended", peer),
+                    };
+                    self.push_call_info(&call.conversation_id, info);
+                    return;
+                }
+                let call = &mut self.calls[call_idx];
+                if state == CallState::Current && call.started_at.is_none() {
+                    call.started_at = Some(Instant::now());
+                    if !call.conference_id.is_empty() {
+                        self.backend.add_to_conference(&call.id, &call.conference_id);
+                    }
+                }
+                call.state = state;
+            }
+            CallEvent::ConferenceCreated { conf_id } => {
+                let (account_id, conversation_id) = self
+                    .pending_conference
+                    .take()
+                    .unwrap_or_else(|| (self.data.account.id.clone(), String::new()));
+                self.conferences.push(Conference {
+                    id: conf_id,
+                    account_id,
+                    conversation_id,
+                    participants: Vec::new(),
+                });
+            }
+            CallEvent::ConferenceInfos { conf_id, infos } => {
+                if let Some(conference) = self.conferences.iter_mut().find(|conf| conf.id == conf_id) {
+                    conference.participants = infos.iter().map(Participant::from_infos).collect();
+                }
+            }
+            CallEvent::ConferenceRemoved { conf_id } => {
+                if let Some(idx) = self.conferences.iter().position(|conf| conf.id == conf_id) {
+                    let conference = self.conferences.remove(idx);
+                    self.push_call_info(&conference.conversation_id, String::from("Conference ended"));
+                }
+            }
+        }
+    }
+
+    /**
+     * Show an info about a call in its conversation, or in the selected channel
+     * @param self
+     * @param conversation_id
+     * @param info
+     */
+    fn push_call_info(&mut self, conversation_id: &String, info: String) {
+        let channel_idx = self
+            .data
+            .channels
+            .items
+            .iter()
+            .position(|channel| &channel.id == conversation_id)
+            .or_else(|| self.data.channels.state.selected());
+        if let Some(channel) = channel_idx.and_then(|idx| self.data.channels.items.get_mut(idx)) {
+            channel.messages.push(Message::info(info));
+        }
+    }
+
+    /**
+     * Uri of an account
+     * @param self
+     * @param account_id
+     */
+    fn own_uri(&self, account_id: &String) -> String {
+        let details = self.backend.get_account_details(account_id);
+        normalize_uri(details.get("Account.username").map(|u| u.as_str()).unwrap_or(""))
+    }
+
+    /**
+     * Account owning a channel
+     * @param self
+     * @param channel_idx
+     */
+    fn channel_account(&self, channel_idx: usize) -> String {
+        self.data.account_of(&self.data.channels.items[channel_idx])
+    }
+
+    /**
+     * Find who to call from a channel
+     * @param self
+     * @param channel_idx
+     * @param member        hash or display name, or empty for the peer of a 1:1 conversation
+     * @return the uri of the peer
+     */
+    fn call_peer(&self, channel_idx: usize, member: &str) -> Option<String> {
+        if Jami::is_hash(&member.to_string()) {
+            return Some(member.to_string());
+        }
+        let own_uri = self.own_uri(&self.channel_account(channel_idx));
+        let mut others = self.data.channels.items[channel_idx]
+            .members
+            .iter()
+            .filter(|m| m.hash != own_uri);
+        if member.is_empty() {
+            let peer = others.next()?;
+            return match others.next() {
+                Some(_) => None,
+                None => Some(peer.hash.clone()),
+            };
+        }
+        others
+            .find(|m| self.data.profile_manager.display_name(&m.hash) == member)
+            .map(|m| m.hash.clone())
+    }
+
+    // direct interactions
+
+    fn untrack_current_conversation(&mut self) {
+        if let Some(idx) = self.data.channels.state.selected() {
+            let account_id = self.channel_account(idx);
+            let channel = &mut self.data.channels.items[idx];
+            for member in &*channel.members {
+                self.backend.subscribe_presence(&account_id, &member.hash, false);
+            }
+        }
+    }
+
+    fn change_conversation(&mut self, next: bool) {
+        self.reset_unread_messages();
+        self.untrack_current_conversation();
+
+        // Channels of collapsed accounts are skipped, their headers are always shown
+        for _ in 0..self.data.channels.items.len() {
+            if next {
+                self.data.channels.next();
+            } else {
+                self.data.channels.previous();
+            }
+            match self.data.channels.state.selected() {
+                Some(idx) if self.is_hidden(idx) => {}
+                _ => break,
+            }
+        }
+
+        self.load_selected_conversation();
+    }
+
+    /**
+     * Make an account the current one and show its channels
+     * @param self
+     * @param account
+     */
+    fn switch_account(&mut self, account: Account) {
+        self.untrack_current_conversation();
+        // In unified mode, the channels of the account are already shown
+        let header_idx = self.data.channels.items.iter().position(|channel| {
+            channel.channel_type == ChannelType::Generated && channel.account_id == account.id
+        });
+        if let (true, Some(header_idx)) = (self.data.unified, header_idx) {
+            self.data.account = account;
+            self.data.channels.state.select(Some(header_idx));
+            return;
+        }
+        self.save_cache();
+        self.needs_sync = false;
+        self.data.account = account;
+        self.data.reload_channels(&*self.backend);
+        self.data.lookup_members(&*self.backend);
+    }
+
+    /**
+     * Handle a key while the account wizard is shown
+     * @param self
+     * @param key
+     */
+    fn on_wizard_key(&mut self, key: KeyEvent) {
+        let wizard = match &mut self.wizard {
+            Some(wizard) => wizard,
+            None => return,
+        };
+        match wizard.on_key(key) {
+            WizardAction::None => {}
+            WizardAction::Close => {
+                let wizard = self.wizard.take().unwrap();
+                if let Some(account_id) = wizard.broken_account() {
+                    self.backend.rm_account(account_id);
+                }
+                if let Progress::Done(_) = wizard.progress {
+                    let account = self.backend.get_account(&wizard.account_id);
+                    if !account.id.is_empty() {
+                        self.switch_account(account);
+                    }
+                }
+            }
+            WizardAction::Submit => self.create_account(),
+            WizardAction::Retry => {
+                // Else each try would add an account
+                if !wizard.account_id.is_empty() {
+                    self.backend.rm_account(&wizard.account_id);
+                }
+                wizard.retry();
+            }
+        }
+    }
+
+    /**
+     * Handle a key while the password prompt is shown
+     * @param self
+     * @param key
+     */
+    fn on_prompt_key(&mut self, key: KeyEvent) {
+        let prompt = match &mut self.password_prompt {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        match prompt.on_key(key) {
+            PromptAction::None => {}
+            PromptAction::Cancel => self.password_prompt = None,
+            PromptAction::Submit => {
+                let prompt = self.password_prompt.take().unwrap();
+                self.run_password_request(prompt.request, &prompt.field.value);
+            }
+        }
+    }
+
+    /**
+     * Run an account command once its password is typed
+     * @param self
+     * @param request
+     * @param password
+     */
+    fn run_password_request(&mut self, request: PasswordRequest, password: &String) {
+        match request {
+            PasswordRequest::Export { account_id, path } => {
+                let info = if self.backend.export_to_file(&account_id, &path, password) {
+                    format!("Account backup written to {}", path)
+                } else {
+                    String::from("Cannot export the account (wrong password?)")
+                };
+                self.push_generated_info(&account_id, info);
+            }
+            PasswordRequest::Pin { account_id } => {
+                let info = if self.backend.export_on_ring(&account_id, password) {
+                    "Generating a PIN…"
+                } else {
+                    "Cannot generate a PIN"
+                };
+                self.push_generated_info(&account_id, String::from(info));
+            }
+            PasswordRequest::Revoke { account_id, device_id } => {
+                let info = if self.backend.revoke_device(&account_id, password, &device_id) {
+                    format!("Revoking device {}…", device_id)
+                } else {
+                    format!("Cannot revoke device {}", device_id)
+                };
+                self.push_generated_info(&account_id, info);
+            }
+        }
+    }
+
+    /**
+     * Create the account filled in the wizard, its registration is then
+     * followed with the events of the daemon
+     * @param self
+     */
+    fn create_account(&mut self) {
+        let wizard = match &mut self.wizard {
+            Some(wizard) => wizard,
+            None => return,
+        };
+        let account_id = match self.backend.create_account(wizard.details()) {
+            Some(account_id) => account_id,
+            None => {
+                wizard.progress = Progress::Failed(String::from("The daemon refused the account"));
+                return;
+            }
+        };
+        wizard.account_id = account_id.clone();
+        let (display_name, avatar) = (wizard.value("display_name").to_string(), wizard.value("avatar").to_string());
+        if display_name.is_empty() && avatar.is_empty() {
+            return;
+        }
+        // The daemon sends the profile of the account to its contacts, it doesn't run in our directory
+        let avatar = if avatar.is_empty() { avatar } else { absolute_path(&avatar) };
+        if !self.backend.update_profile(&account_id, &display_name, &avatar) {
+            self.log(format!("cannot update the profile of {}", account_id));
+        }
+    }
+
+    /**
+     * If a channel is folded under the collapsed header of its account
+     * @param self
+     * @param channel_idx
+     */
+    pub fn is_hidden(&self, channel_idx: usize) -> bool {
+        let channel = &self.data.channels.items[channel_idx];
+        self.data.unified
+            && channel.channel_type != ChannelType::Generated
+            && self.collapsed_accounts.contains(&channel.account_id)
+    }
+
+    /**
+     * Collapse or expand the channels of an account, if the channel is its header
+     * @param self
+     * @param channel_idx
+     */
+    fn toggle_account(&mut self, channel_idx: usize) {
+        let channel = &self.data.channels.items[channel_idx];
+        if !self.data.unified || channel.channel_type != ChannelType::Generated {
+            return;
+        }
+        let account_id = channel.account_id.clone();
+        if !self.collapsed_accounts.remove(&account_id) {
+            self.collapsed_accounts.insert(account_id);
+        }
+    }
+
+    /**
+     * Show the channels of every enabled account, or only the ones of the current account
+     * @param self
+     * @param unified
+     */
+    fn set_unified(&mut self, unified: bool) {
+        self.untrack_current_conversation();
+        self.save_cache();
+        self.data.unified = unified;
+        self.collapsed_accounts.clear();
+        self.needs_sync = false;
+        self.data.reload_channels(&*self.backend);
+        self.data.lookup_members(&*self.backend);
+        self.load_selected_conversation();
+    }
+
+    fn select_channel(&mut self, channel_idx: usize) {
+        self.reset_unread_messages();
+        self.untrack_current_conversation();
+        self.data.channels.state.select(Some(channel_idx));
+        self.load_selected_conversation();
+    }
+
+    fn load_selected_conversation(&mut self) {
+        self.input_history_idx = None;
+        self.reply = None;
+        self.highlighted_member = None;
+        if let Some(idx) = self.data.channels.state.selected() {
+            let account_id = self.channel_account(idx);
+            let channel = &mut self.data.channels.items[idx];
+            if channel.channel_type == ChannelType::Group {
+                channel.reset_history();
+                self.backend.load_conversation(
+                    &account_id,
+                    &channel.id,
+                    &String::new(),
+                    HISTORY_BATCH_SIZE,
+                );
+            } else {
+                channel.scroll = 0;
+            }
+            for member in &*channel.members {
+                self.backend.subscribe_presence(&account_id, &member.hash, true);
+            }
+        }
+    }
+
+    /**
+     * Search messages of the shown accounts and show hits in the search channel
+     * @param self
+     * @param query     text to search
+     */
+    fn search(&mut self, query: &str) {
+        let hits = match &self.search_index {
+            Some(search_index) => self
+                .data
+                .shown_accounts(&*self.backend)
+                .iter()
+                .map(|account| search_index.search(&account.id, query, SEARCH_LIMIT))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map(|hits| {
+                    let mut hits: Vec<SearchHit> = hits.into_iter().flatten().collect();
+                    hits.sort_by(|a, b| b.arrived_at.cmp(&a.arrived_at));
+                    hits.truncate(SEARCH_LIMIT as usize);
+                    hits
+                }),
+            None => Err(anyhow::anyhow!("no search index")),
+        };
+        self.show_search_results(
+            format!("{}: {}", SEARCH_CHANNEL_ID, query),
+            format!("No message found for \"{}\"", query),
+            hits,
+        );
+    }
+
+    /**
+     * Show the recent messages mentioning the user, in the search channel
+     * @param self
+     */
+    fn show_mentions(&mut self) {
+        let account_id = self.data.account.id.clone();
+        let names = self.mention_names(&account_id);
+        let own_name = self.data.account.get_display_name();
+        let hits = match &self.search_index {
+            Some(search_index) => names
+                .iter()
+                .map(|name| search_index.search(&account_id, name, SEARCH_LIMIT))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map(|hits| {
+                    let mut hits: Vec<SearchHit> = hits
+                        .into_iter()
+                        .flatten()
+                        .filter(|hit| hit.author != own_name && notifications::mentions(&hit.body, &names))
+                        .collect();
+                    hits.sort_by(|a, b| {
+                        (b.arrived_at, &b.conversation_id, &b.id).cmp(&(a.arrived_at, &a.conversation_id, &a.id))
+                    });
+                    hits.dedup_by(|a, b| a.conversation_id == b.conversation_id && a.id == b.id);
+                    hits.truncate(SEARCH_LIMIT as usize);
+                    hits
+                }),
+            None => Err(anyhow::anyhow!("no search index")),
+        };
+        self.show_search_results(
+            format!("{}: mentions", SEARCH_CHANNEL_ID),
+            String::from("No message mentions you"),
+            hits,
+        );
+    }
+
+    /**
+     * Replace the search channel with results
+     * @param self
+     * @param title
+     * @param empty_text    shown if there is no result
+     * @param hits
+     */
+    fn show_search_results(&mut self, title: String, empty_text: String, hits: anyhow::Result<Vec<SearchHit>>) {
+        let mut channel = Channel::new(&String::from(SEARCH_CHANNEL_ID), ChannelType::Search);
+        channel.title = title;
+        channel.history_complete = true;
+        match hits {
+            Ok(hits) => {
+                if hits.is_empty() {
+                    channel.messages.push(Message::info(empty_text));
+                }
+                // Newest hit at the bottom
+                for (idx, hit) in hits.iter().enumerate().rev() {
+                    let channel_name = self
+                        .data
+                        .channels
+                        .items
+                        .iter()
+                        .find(|channel| channel.is(&hit.account_id, &hit.conversation_id))
+                        .map(|channel| channel.bestname())
+                        .unwrap_or_else(|| hit.conversation_id.clone());
+                    let date = hit.arrived_at.with_timezone(&chrono::Local).format("%Y-%m-%d");
+                    channel.messages.push(Message::new(
+                        hit.author.clone(),
+                        format!("[{}] {} ({}): {}", idx + 1, channel_name, date, hit.body),
+                        hit.arrived_at,
+                    ));
+                }
+                channel.messages.push(Message::info(String::from(
+                    "/open <n>: Go to the message in its conversation",
+                )));
+                self.search_results = hits;
+            }
+            Err(err) => {
+                channel.messages.push(Message::info(format!("Cannot search: {}", err)));
+            }
+        }
+
+        self.reset_unread_messages();
+        self.untrack_current_conversation();
+        self.data
+            .channels
+            .items
+            .retain(|channel| channel.channel_type != ChannelType::Search);
+        let idx = self.data.channels.items.len().min(1);
+        self.data.channels.items.insert(idx, channel);
+        self.data.channels.state.select(Some(idx));
+    }
+
+    /**
+     * Go to a search hit in its conversation
+     * @param self
+     * @param hit_idx       index in the last search results
+     * @return if the conversation exists
+     */
+    fn open_search_hit(&mut self, hit_idx: usize) -> bool {
+        let hit = match self.search_results.get(hit_idx) {
+            Some(hit) => hit.clone(),
+            None => return false,
+        };
+        let channel_idx = match self
+            .data
+            .channels
+            .items
+            .iter()
+            .position(|channel| channel.is(&hit.account_id, &hit.conversation_id))
+        {
+            Some(channel_idx) => channel_idx,
+            None => return false,
+        };
+        self.pending_jump = Some((hit.account_id, hit.conversation_id, hit.id));
+        self.select_channel(channel_idx);
+        true
+    }
+
+    /**
+     * Scroll to the message searched if loaded, else load older messages
+     * @param self
+     * @param account_id
+     * @param conversation_id       conversation just loaded
+     */
+    fn continue_jump(&mut self, account_id: &String, conversation_id: &String) {
+        let commit_id = match &self.pending_jump {
+            Some((jump_account, jump_conversation, commit_id))
+                if jump_account == account_id && jump_conversation == conversation_id =>
+            {
+                commit_id.clone()
+            }
+            _ => return,
+        };
+        let channel = match self
+            .data
+            .channels
+            .items
+            .iter_mut()
+            .find(|channel| channel.is(account_id, conversation_id))
+        {
+            Some(channel) => channel,
+            None => {
+                self.pending_jump = None;
+                return;
+            }
+        };
+        match channel.messages.iter().position(|msg| msg.id == commit_id) {
+            Some(pos) => {
+                channel.scroll = channel.messages.len() - 1 - pos;
+                self.pending_jump = None;
+            }
+            None if !channel.history_complete && !channel.oldest_commit.is_empty() => {
+                channel.loading_history = true;
+                self.backend.load_conversation(
+                    account_id,
+                    &channel.id,
+                    &channel.oldest_commit,
+                    HISTORY_BATCH_SIZE,
+                );
+            }
+            None => self.pending_jump = None,
+        }
+    }
+
+    /**
+     * On Tab, complete the word before the cursor or go to the next candidate
+     * @param self
+     */
+    pub fn on_tab(&mut self) {
+        if let Some(completion) = &mut self.completion {
+            if completion.is_continued_by(&self.data.input, self.data.input_cursor) {
+                completion.next();
+                self.data.input_cursor = completion.apply(&mut self.data.input);
+                return;
+            }
+        }
+
+        let start = completion::word_start(&self.data.input, self.data.input_cursor);
+        let before: String = self.data.input.chars().take(start).collect();
+        let word: String = self
+            .data
+            .input
+            .chars()
+            .skip(start)
+            .take(self.data.input_cursor - start)
+            .collect();
+        let candidates = self.completion_candidates(&before, &word);
+        if candidates.is_empty() {
+            self.completion = None;
+            return;
+        }
+        let mut completion = Completion::new(candidates, start, word.chars().count());
+        self.data.input_cursor = completion.apply(&mut self.data.input);
+        self.completion = Some(completion);
+    }
+
+    /**
+     * Get possible completions for a word
+     * @param self
+     * @param before    input before the word
+     * @param word      word to complete
+     * @return the candidates
+     */
+    fn completion_candidates(&self, before: &str, word: &str) -> Vec<String> {
+        let channel = self
+            .data
+            .channels
+            .state
+            .selected()
+            .and_then(|idx| self.data.channels.items.get(idx));
+        let channel_type = channel
+            .map(|channel| channel.channel_type.clone())
+            .unwrap_or(ChannelType::Generated);
+        let profile_manager = &self.data.profile_manager;
+
+        // Mentions
+        if let Some(name) = word.strip_prefix('@') {
+            let members = channel.map(|channel| &channel.members[..]).unwrap_or(&[]);
+            return completion::matching(
+                name,
+                members
+                    .iter()
+                    .map(|member| profile_manager.display_name(&member.hash)),
+            )
+            .into_iter()
+            .map(|name| format!("@{}", name))
+            .collect();
+        }
+
+        // Escaped spaces don't separate arguments
+        let args = commands::split_args(before)
+            .unwrap_or_else(|_| before.split_whitespace().map(String::from).collect());
+        if args.is_empty() {
+            if word.starts_with('/') {
+                return completion::matching(
+                    word,
+                    commands::commands_for(&channel_type).map(|command| command.name.to_string()),
+                );
+            }
+            return Vec::new();
+        }
+
+        // Members are completed by their hash, but can be searched by display name
+        let members_matching = |members: Vec<&Member>| -> Vec<String> {
+            let word = word.to_lowercase();
+            let mut hashes: Vec<String> = members
+                .into_iter()
+                .filter(|member| {
+                    member.hash.starts_with(&word)
+                        || profile_manager
+                            .display_name(&member.hash)
+                            .to_lowercase()
+                            .starts_with(&word)
+                })
+                .map(|member| member.hash.clone())
+                .collect();
+            hashes.sort();
+            hashes.dedup();
+            hashes
+        };
+        let current_members: Vec<&Member> = channel
+            .map(|channel| channel.members.iter().collect())
+            .unwrap_or_default();
+        let all_members: Vec<&Member> = self
+            .data
+            .channels
+            .items
+            .iter()
+            .flat_map(|channel| channel.members.iter())
+            .filter(|member| member.hash != self.data.account.id)
+            .collect();
+
+        let kind = commands::find(&args[0], &channel_type)
+            .and_then(|command| command.args.get(args.len() - 1))
+            .map(|arg| arg.kind);
+        match kind {
+            Some(ArgKind::Member) => members_matching(current_members),
+            Some(ArgKind::Contact) => members_matching(all_members),
+            Some(ArgKind::Account) => completion::matching(
+                word,
+                self.backend
+                    .get_account_list()
+                    .into_iter()
+                    .map(|account| account.id),
+            ),
+            Some(ArgKind::Path) => completion::path_candidates(word),
+            _ => Vec::new(),
+        }
+    }
+
+    /**
+     * On Ctrl+F, prepare a search
+     */
+    pub fn on_search_key(&mut self) {
+        self.data.input = String::from("/search ");
+        self.data.input_cursor = self.data.input.chars().count();
+    }
+
+    /**
+     * Go to the previous channel
+     */
+    pub fn on_up(&mut self) {
+        self.change_conversation(false);
+    }
+
+    /**
+     * Go to the next channel
+     */
+    pub fn on_down(&mut self) {
+        self.change_conversation(true);
+    }
+
+    /**
+     * Scroll the current conversation and load older messages when the oldest
+     * loaded message is reached
+     * @param self
+     * @param count     number of messages to scroll
+     */
+    fn scroll_up(&mut self, count: usize) {
+        if let Some(idx) = self.data.channels.state.selected() {
+            let account_id = self.channel_account(idx);
+            let channel = &mut self.data.channels.items[idx];
+            if !channel.scroll_up(count)
+                || channel.channel_type != ChannelType::Group
+                || channel.loading_history
+                || channel.history_complete
+                || channel.oldest_commit.is_empty()
+            {
+                return;
+            }
+            channel.loading_history = true;
+            self.backend.load_conversation(
+                &account_id,
+                &channel.id,
+                &channel.oldest_commit,
+                HISTORY_BATCH_SIZE,
+            );
+        }
     }
 
     /**
-     * When a conversation is loaded
-     * @param self
-     * @param _id
-     * @param account_id
-     * @param conversation_id
-     * @param messages
-     * @return if ok
+     * On key page up
      */
-    pub async fn on_conversation_loaded(
-        &mut self,
-        _id: u32,
-        account_id: String,
-        conversation_id: String,
-        messages: Vec<HashMap<String, String>>,
-    ) -> Option<()> {
-        let messages: Vec<_> = messages.into_iter().rev().collect();
-        for msg in messages {
-            let _ = self.on_message(&account_id, &conversation_id, msg).await;
-        }
-        Some(())
+    pub fn on_page_up(&mut self) {
+        self.scroll_up(SCROLL_STEP);
     }
 
-    pub async fn on_data_transfer_event(
-        &mut self,
-        account_id: String,
-        conversation_id: String,
-        tid: u64,
-        status: i32
-    ) -> Option<()> {
-        let info = Jami::data_transfer_info(account_id.clone(), conversation_id.clone(), tid);
-        if !info.is_none() {
-            let info = info.unwrap();
-            match self.data.transfer_manager.path(account_id.clone(), conversation_id.clone(), tid.to_string()) {
-                None => {
-                    if info.flags == 0 /* outgoing */ {
-                        self.data.transfer_manager.set_file_path(account_id.clone(), conversation_id.clone(), tid.to_string(), info.path);
-                    } else if status == 6 /* Finished */ {
-                        self.data.transfer_manager.set_file_path(account_id.clone(), conversation_id.clone(), tid.to_string(), info.path);
-                    }
-                },
-                _ => {},
-            };
+    /**
+     * On key page down
+     */
+    pub fn on_page_down(&mut self) {
+        self.on_scroll_down(SCROLL_STEP);
+    }
 
-            // Note: bad perf there but for now I don't care, will fix this when necessary
-            if account_id == &*self.data.account.id {
-                if let Some(idx) = self.data.channels.state.selected() {
-                    let channel = &mut self.data.channels.items[idx];
-                    if channel.id == conversation_id {
-                        channel.messages.clear();
-                        Jami::load_conversation(&self.data.account.id, &channel.id, &String::new(), 0);
-                    }
-                }
-            }
+    fn on_scroll_down(&mut self, count: usize) {
+        if let Some(idx) = self.data.channels.state.selected() {
+            self.data.channels.items[idx].scroll_down(count);
         }
-        Some(())
     }
 
     /**
-     * When a new conversation is ready
+     * Reply to the message at the bottom of the view
      * @param self
-     * @param account_id
-     * @param conversation_id
-     * @return if ok
      */
-    pub async fn on_conversation_ready(
-        &mut self,
-        account_id: String,
-        conversation_id: String,
-    ) -> Option<()> {
-        if account_id == self.data.account.id {
-            self.data.channels.state.select(Some(0));
-            self.data
-                .channels
-                .items
-                .retain(|channel| channel.id != conversation_id);
-            self.data
-                .channels
-                .items
-                .push(Channel::new(&conversation_id, ChannelType::Group));
-            self.bubble_up_channel(self.data.channels.items.len() - 1);
-            self.data.channels.state.select(Some(0));
+    fn on_reply(&mut self) {
+        let idx = match self.data.channels.state.selected() {
+            Some(idx) => idx,
+            None => return,
+        };
+        let channel = &mut self.data.channels.items[idx];
+        let target = channel.bottom_message().map(|msg_idx| &channel.messages[msg_idx]);
+        match target {
+            Some(msg) if channel.channel_type == ChannelType::Group && !msg.id.is_empty() => {
+                self.reply = Some(Reply {
+                    conversation_id: channel.id.clone(),
+                    commit_id: msg.id.clone(),
+                    author: msg.from.clone(),
+                    body: ui::message_text(&self.data.profile_manager, &msg.kind),
+                });
+                self.focus = Focus::Input;
+            }
+            _ => self.status = Some(String::from("No message to reply to")),
         }
-        Some(())
     }
 
     /**
-     * When a conversation is removed
+     * Open the last file received or sent in the current conversation,
+     * from the bottom of the view
      * @param self
-     * @param account_id
-     * @param conversation_id
-     * @return if ok
      */
-    pub async fn on_conversation_removed(
-        &mut self,
-        account_id: String,
-        conversation_id: String,
-    ) -> Option<()> {
-        if account_id == self.data.account.id {
-            if let Some(idx) = self.data.channels.state.selected() {
-                let channel = &mut self.data.channels.items[idx];
-                if channel.id == conversation_id {
-                    self.untrack_current_conversation();
-                    self.data.channels.state.select(Some(0));
-                }
-            }
-            self.data
-                .channels
-                .items
-                .retain(|channel| channel.id != conversation_id);
-        }
-        Some(())
+    fn open_last_file(&mut self) {
+        let idx = match self.data.channels.state.selected() {
+            Some(idx) => idx,
+            None => return,
+        };
+        let channel = &self.data.channels.items[idx];
+        let visible = channel.messages.len().saturating_sub(channel.scroll);
+        let path = channel.messages[..visible]
+            .iter()
+            .rev()
+            .find_map(|msg| msg.file_path())
+            .map(String::from);
+        self.open_file(path);
+    }
+
+    fn open_file(&mut self, path: Option<String>) {
+        self.status = match path {
+            None => Some(String::from("No file to open")),
+            Some(path) => match open_path(&path) {
+                Ok(()) => None,
+                Err(err) => Some(format!("Can't open {}: {}", path, err)),
+            },
+        };
     }
 
     /**
-     * When receiving a new conversation request (not a trust request)
+     * Accept a file transfer to the default download directory
      * @param self
      * @param account_id
      * @param conversation_id
-     * @todo other parameters?
-     * @return if ok
+     * @param tid
+     * @param name      name of the file, for the status
      */
-    pub async fn on_conversation_request(
-        &mut self,
-        account_id: String,
-        conversation_id: String,
-    ) -> Option<()> {
-        if account_id == self.data.account.id {
-            self.data
-                .channels
-                .items
-                .push(Channel::new(&conversation_id, ChannelType::Invite));
-            self.bubble_up_channel(self.data.channels.items.len() - 1);
-            self.data.channels.state.select(Some(0));
+    fn accept_transfer(&mut self, account_id: &String, conversation_id: &String, tid: u64, name: &str) {
+        match self.default_download_path(account_id, conversation_id, tid) {
+            Some(path) => {
+                self.backend
+                    .accept_file_transfer(account_id, conversation_id, tid, &path);
+                self.status = Some(format!("Downloading {} to {}", name, path));
+            }
+            None => self.status = Some(String::from("Cannot accept file")),
         }
-        Some(())
     }
 
     /**
-     * When receiving a new trust request
+     * Highlight a member in the member list and show their profile
      * @param self
-     * @param account_id
-     * @param conversation_id
-     * @todo other parameters?
-     * @return if ok
+     * @param channel_idx
+     * @param hash
      */
-    pub async fn on_incoming_trust_request(
-        &mut self,
-        account_id: &String,
-        from: &String,
-        _payloads: Vec<u8>,
-        _receive_time: u64,
-    ) -> Option<()> {
-        if account_id == &self.data.account.id {
-            self.data
-                .channels
-                .items
-                .push(Channel::new(&from, ChannelType::TrustRequest(from.clone())));
-            self.bubble_up_channel(self.data.channels.items.len() - 1);
-            self.data.channels.state.select(Some(0));
-        }
-        Some(())
+    fn show_member(&mut self, channel_idx: usize, hash: String) {
+        let channel = &self.data.channels.items[channel_idx];
+        let status = match channel.members.iter().find(|member| member.hash == hash) {
+            Some(member) => format!(
+                "{} ({}) - {:?}",
+                self.data.profile_manager.display_name(&member.hash),
+                member.hash,
+                member.role
+            ),
+            None if hash.is_empty() => String::from("Unknown author"),
+            None => format!("{} is not a member anymore", hash),
+        };
+        self.highlighted_member = Some(hash);
+        self.status = Some(status);
     }
 
     /**
-     * When a name is found, refresh UI if necessary and profile manager
+     * Handle a key in the transfers panel
      * @param self
-     * @param account_id
-     * @param status        0 for success
-     * @param address       uri related
-     * @param name          name related
+     * @param key
+     * @return if the key was handled
      */
-    pub async fn on_registered_name_found(
-        &mut self,
-        account_id: String,
-        status: u64,
-        address: String,
-        name: String,
-    ) -> Option<()> {
-        self.data.profile_manager.username_found(&address, &name);
-        // pending invite
-        for i in 0..self.data.out_invite.len() {
-            let out_invite = &self.data.out_invite[i];
-            if out_invite.account == account_id && out_invite.member == name {
-                if status == 0 {
-                    if out_invite.channel.as_ref().is_none() {
-                        Jami::add_contact(&self.data.account.id, &address);
-                        Jami::send_trust_request(
-                            &out_invite.account,
-                            &address,
-                            Vec::new(), /* TODO */
-                        );
-                    } else {
-                        let conversation = out_invite.channel.clone().unwrap();
-                        Jami::add_conversation_member(&out_invite.account, &conversation, &address);
-                    }
-                } else {
-                    let channels = &mut self.data.channels.items;
-                    for channel in &mut *channels {
-                        if channel.id == out_invite.channel.clone().unwrap_or(String::new()) {
-                            channel
-                                .messages
-                                .push(Message::info(String::from("Cannot invite member")));
-                        }
+    fn on_transfers_key(&mut self, key: KeyCode) -> bool {
+        let transfer = self
+            .transfers
+            .state
+            .selected()
+            .and_then(|idx| self.transfers.items.get(idx))
+            .cloned();
+        match (key, transfer) {
+            (KeyCode::Up, _) | (KeyCode::Char('k'), _) => self.transfers.previous(),
+            (KeyCode::Down, _) | (KeyCode::Char('j'), _) => self.transfers.next(),
+            (KeyCode::Esc, _) | (KeyCode::Char('q'), _) => self.focus = Focus::Input,
+            (KeyCode::Char('a'), Some(transfer)) => {
+                self.accept_transfer(&transfer.account_id, &transfer.conversation_id, transfer.tid, &transfer.name);
+            }
+            (KeyCode::Char('d'), Some(transfer)) => {
+                // Let the user edit the path of /accept in the conversation of the transfer
+                let channel_idx = self
+                    .data
+                    .channels
+                    .items
+                    .iter()
+                    .position(|channel| channel.is(&transfer.account_id, &transfer.conversation_id));
+                if let Some(channel_idx) = channel_idx {
+                    if self.data.channels.state.selected() != Some(channel_idx) {
+                        self.select_channel(channel_idx);
                     }
+                    let path = self
+                        .default_download_path(&transfer.account_id, &transfer.conversation_id, transfer.tid)
+                        .unwrap_or_default();
+                    let path = if path.contains(char::is_whitespace) {
+                        format!("\"{}\"", path)
+                    } else {
+                        path
+                    };
+                    self.data.input = format!("/accept {} {}", transfer.tid, path);
+                    self.data.input_cursor = self.data.input.chars().count();
+                    self.focus = Focus::Input;
                 }
-                self.data.out_invite.remove(i);
-                break;
             }
-        }
-
-        // pending remove
-        for i in 0..self.data.pending_rm.len() {
-            let pending_rm = &self.data.pending_rm[i];
-            if pending_rm.account == account_id && pending_rm.member == name {
-                if status == 0 {
-                    Jami::rm_conversation_member(
-                        &pending_rm.account,
-                        &pending_rm.channel,
-                        &address,
-                    );
-                }
-                self.data.pending_rm.remove(i);
-                break;
+            (KeyCode::Char('c'), Some(transfer)) => {
+                self.backend
+                    .cancel_file_transfer(&transfer.account_id, &transfer.conversation_id, transfer.tid);
             }
+            (KeyCode::Char('o'), Some(transfer)) | (KeyCode::Enter, Some(transfer)) => {
+                self.open_file(transfer.path);
+            }
+            (KeyCode::Char(_), _) | (KeyCode::Enter, _) => {}
+            _ => return false,
         }
-
-        Some(())
+        true
     }
 
-    // direct interactions
-
-    fn untrack_current_conversation(&mut self) {
-        if let Some(idx) = self.data.channels.state.selected() {
-            let channel = &mut self.data.channels.items[idx];
-            for member in &*channel.members {
-                Jami::subscribe_presence(&self.data.account.id, &member.hash, false);
+    /**
+     * Handle a key in the devices panel
+     * @param self
+     * @param key
+     * @return if the key was handled
+     */
+    fn on_devices_key(&mut self, key: KeyCode) -> bool {
+        let device = self
+            .devices
+            .state
+            .selected()
+            .and_then(|idx| self.devices.items.get(idx))
+            .cloned();
+        match (key, device) {
+            (KeyCode::Up, _) | (KeyCode::Char('k'), _) => self.devices.previous(),
+            (KeyCode::Down, _) | (KeyCode::Char('j'), _) => self.devices.next(),
+            (KeyCode::Esc, _) | (KeyCode::Char('q'), _) => self.focus = Focus::Input,
+            (KeyCode::Char('n'), Some(device)) if device.current => {
+                self.edit_account_command(format!("/device-name {}", device.name));
             }
+            (KeyCode::Char('r'), Some(device)) if !device.current => {
+                self.password_prompt = Some(PasswordPrompt::new(PasswordRequest::Revoke {
+                    account_id: self.devices_account.clone(),
+                    device_id: device.id,
+                }));
+            }
+            (KeyCode::Char(_), _) | (KeyCode::Enter, _) => {}
+            _ => return false,
         }
+        true
     }
 
-    fn change_conversation(&mut self, next: bool) {
-        self.reset_unread_messages();
-        self.untrack_current_conversation();
-
-        if next {
-            self.data.channels.next();
-        } else {
-            self.data.channels.previous();
+    /**
+     * Let the user edit a command in the generated channel of the account of the devices panel
+     * @param self
+     * @param input
+     */
+    fn edit_account_command(&mut self, input: String) {
+        let header = self.data.channels.items.iter().position(|channel| {
+            channel.channel_type == ChannelType::Generated && self.data.account_of(channel) == self.devices_account
+        });
+        if let Some(header) = header {
+            if self.data.channels.state.selected() != Some(header) {
+                self.select_channel(header);
+            }
+            self.data.input_cursor = input.chars().count();
+            self.data.input = input;
+            self.focus = Focus::Input;
         }
+    }
 
-        if let Some(idx) = self.data.channels.state.selected() {
-            let channel = &mut self.data.channels.items[idx];
-            if channel.channel_type == ChannelType::Group {
-                channel.messages.clear();
-                Jami::load_conversation(&self.data.account.id, &channel.id, &String::new(), 0);
+    /**
+     * Handle a key in the messages pane, where the message at the bottom
+     * of the view is selected
+     * @param self
+     * @param key
+     * @return if the key was handled
+     */
+    fn on_selection_key(&mut self, key: KeyCode) -> bool {
+        let idx = match self.data.channels.state.selected() {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let channel = &self.data.channels.items[idx];
+        let message = channel
+            .bottom_message()
+            .map(|msg_idx| channel.messages[msg_idx].clone());
+        match (key, message) {
+            (KeyCode::Up, _) | (KeyCode::Char('k'), _) => self.scroll_up(1),
+            (KeyCode::Down, _) | (KeyCode::Char('j'), _) => self.on_scroll_down(1),
+            (KeyCode::Esc, _) | (KeyCode::Char('q'), _) => self.focus = Focus::Input,
+            (KeyCode::Char('r'), _) => self.on_reply(),
+            (KeyCode::Char('y'), Some(message)) => {
+                let text = ui::message_text(&self.data.profile_manager, &message.kind);
+                self.terminal_output.push(osc52_copy(&text));
+                self.status = Some(String::from("Message copied to the clipboard"));
             }
-            for member in &*channel.members {
-                Jami::subscribe_presence(&self.data.account.id, &member.hash, true);
+            (KeyCode::Char('o'), Some(message)) | (KeyCode::Enter, Some(message)) => {
+                self.open_file(message.file_path().map(String::from));
+            }
+            (KeyCode::Char('p'), Some(message)) => {
+                if message.payload.is_empty() {
+                    self.status = Some(String::from("No commit for this message"));
+                    return true;
+                }
+                // Shown after the last message, so leave the selection
+                let channel = &mut self.data.channels.items[idx];
+                let mut payload: Vec<_> = message.payload.iter().collect();
+                payload.sort();
+                for (key, value) in payload {
+                    channel.messages.push(Message::info(format!("{}: {}", key, value)));
+                }
+                channel.scroll = 0;
+                self.focus = Focus::Input;
             }
+            (KeyCode::Char('a'), Some(message)) => self.show_member(idx, message.author),
+            (KeyCode::Char(_), _) | (KeyCode::Enter, _) => {}
+            _ => return false,
         }
+        true
     }
 
     /**
-     * On key up
+     * On key home, go to the oldest loaded message
      */
-    pub fn on_up(&mut self) {
-        self.change_conversation(false);
+    pub fn on_home(&mut self) {
+        self.scroll_up(usize::MAX);
     }
 
     /**
-     * On key down
+     * On key end, go back to the last message
      */
-    pub fn on_down(&mut self) {
-        self.change_conversation(true);
+    pub fn on_end(&mut self) {
+        if let Some(idx) = self.data.channels.state.selected() {
+            self.data.channels.items[idx].scroll = 0;
+        }
     }
 
     /**
@@ -989,11 +3144,63 @@ impl App {
      * On key right
      */
     pub fn on_right(&mut self) {
-        if self.data.input_cursor < self.data.input.width() {
+        if self.data.input_cursor < self.data.input.chars().count() {
             self.data.input_cursor += 1;
         }
     }
 
+    /**
+     * On key up, go to the previous line of the input, or recall the previous
+     * input sent in this channel
+     */
+    pub fn on_history_up(&mut self) {
+        if let Some(pos) = editor::line_up(&self.data.input, self.data.input_cursor) {
+            self.data.input_cursor = pos;
+            return;
+        }
+        let idx = match self.data.channels.state.selected() {
+            Some(idx) => idx,
+            None => return,
+        };
+        let history = &self.data.channels.items[idx].input_history;
+        let history_idx = match self.input_history_idx {
+            None if history.is_empty() => return,
+            None => {
+                self.input_draft = self.data.input.clone();
+                history.len() - 1
+            }
+            Some(0) => return,
+            Some(history_idx) => history_idx - 1,
+        };
+        self.input_history_idx = Some(history_idx);
+        self.data.input = history[history_idx].clone();
+        self.data.input_cursor = self.data.input.chars().count();
+    }
+
+    /**
+     * On key down, go to the next line of the input, or recall the next input
+     * sent in this channel, then the input being written
+     */
+    pub fn on_history_down(&mut self) {
+        if let Some(pos) = editor::line_down(&self.data.input, self.data.input_cursor) {
+            self.data.input_cursor = pos;
+            return;
+        }
+        let (idx, history_idx) = match (self.data.channels.state.selected(), self.input_history_idx) {
+            (Some(idx), Some(history_idx)) => (idx, history_idx),
+            _ => return,
+        };
+        let history = &self.data.channels.items[idx].input_history;
+        if history_idx + 1 < history.len() {
+            self.input_history_idx = Some(history_idx + 1);
+            self.data.input = history[history_idx + 1].clone();
+        } else {
+            self.input_history_idx = None;
+            self.data.input = std::mem::take(&mut self.input_draft);
+        }
+        self.data.input_cursor = self.data.input.chars().count();
+    }
+
     /**
      * Clear messages
      */
@@ -1001,6 +3208,7 @@ impl App {
         if let Some(selected_idx) = self.data.channels.state.selected() {
             if self.data.channels.items[selected_idx].unread_messages > 0 {
                 self.data.channels.items[selected_idx].unread_messages = 0;
+                self.data.channels.items[selected_idx].unread_mentions = 0;
                 return true;
             }
         }
@@ -1022,18 +3230,66 @@ impl App {
      * @param channel_idx        Id of the channel to move
      */
     fn bubble_up_channel(&mut self, channel_idx: usize) {
-        // bubble up channel to the beginning of the list
+        // bubble up channel to the beginning of the list, or of its account in unified mode
+        let account_id = self.channel_account(channel_idx);
         let channels = &mut self.data.channels;
-        for (prev, next) in (1..channel_idx).zip(2..channel_idx + 1).rev() {
-            channels.items.swap(prev, next);
+        if channels.items[channel_idx].channel_type == ChannelType::Generated {
+            return;
+        }
+        let first = channels.items[..channel_idx]
+            .iter()
+            .rposition(|channel| channel.channel_type == ChannelType::Generated && channel.account_id == account_id)
+            .unwrap_or(0)
+            + 1;
+        if channel_idx < first {
+            return;
         }
+        channels.items[first..channel_idx + 1].rotate_right(1);
         match channels.state.selected() {
-            Some(0) if 0 == channel_idx => channels.state.select(Some(0)),
-            Some(selected_idx) if selected_idx == channel_idx => channels.state.select(Some(1)),
-            Some(selected_idx) if selected_idx < channel_idx => {
+            Some(selected_idx) if selected_idx == channel_idx => channels.state.select(Some(first)),
+            Some(selected_idx) if selected_idx >= first && selected_idx < channel_idx => {
                 channels.state.select(Some(selected_idx + 1));
             }
             _ => {}
         };
     }
+
+    /**
+     * Add a channel of an account at the top of its channels
+     * @param self
+     * @param account_id
+     * @param channel
+     */
+    fn add_channel(&mut self, account_id: &String, mut channel: Channel) {
+        channel.account_id = account_id.clone();
+        self.data.channels.items.push(channel);
+        self.bubble_up_channel(self.data.channels.items.len() - 1);
+    }
+}
+
+/**
+ * Make a path typed by the user absolute, "~/" is supported
+ * @param path
+ */
+fn absolute_path(path: &str) -> String {
+    let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    };
+    match std::env::current_dir() {
+        Ok(dir) => dir.join(path).to_string_lossy().into_owned(),
+        Err(_) => path.to_string_lossy().into_owned(),
+    }
+}
+
+/**
+ * Split "name@nameserver" (the name server is optional)
+ * @param member
+ * @return the name and the name server
+ */
+fn split_name_server(member: &str) -> (String, String) {
+    match member.find('@') {
+        Some(pos) => (member[..pos].to_string(), member[pos + 1..].to_string()),
+        None => (member.to_string(), String::new()),
+    }
 }
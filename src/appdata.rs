@@ -1,13 +1,32 @@
+use crate::backend::Backend;
 use crate::util::*;
 
+use app_dirs::{app_dir, get_app_dir, AppDataType, AppInfo};
 use chrono::Utc;
 use jami_rs::account::Account;
-use jami_rs::{Jami, ProfileManager, TransferManager};
+use jami_rs::{ProfileManager, TransferManager};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
 
+pub const APP_INFO: AppInfo = AppInfo {
+    name: "jami-cli",
+    author: "SFL",
+};
+/// File next to the cached accounts containing the id of the last used account
+const LAST_ACCOUNT_FILE: &str = "last_account";
+
+/**
+ * Account and channels read from the daemon to refresh the cache
+ */
+pub struct JamiSnapshot {
+    pub account: Account,
+    /// Shown accounts, the current one first
+    pub accounts: Vec<Account>,
+    pub channels: Vec<Channel>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct AppData {
     pub channels: StatefulList<Channel>,
@@ -19,30 +38,117 @@ pub struct AppData {
     pub out_invite: Vec<OutgoingInvite>,
     #[serde(skip)]
     pub pending_rm: Vec<PendingRm>,
+    /// Not cached, it may hold anything typed
+    #[serde(skip)]
     pub input: String,
     #[serde(skip)]
     pub input_cursor: usize,
+    /// Channels of every enabled account are loaded, grouped by account
+    #[serde(skip)]
+    pub unified: bool,
 }
 
 impl AppData {
     /**
      * Lookup on nameserver all members
      */
-    pub fn lookup_members(&mut self) {
+    pub fn lookup_members(&mut self, backend: &dyn Backend) {
         // Refresh titles for channel
-        for channel in &mut *self.channels.items {
+        for channel in &*self.channels.items {
+            let account_id = self.account_of(channel);
             for member in &*channel.members {
-                Jami::lookup_address(&self.account.id, &String::new(), &member.hash);
+                backend.lookup_address(&account_id, &String::new(), &member.hash);
             }
         }
     }
 
+    /**
+     * Account owning a channel, the current one for channels without account
+     * @param self
+     * @param channel
+     */
+    pub fn account_of(&self, channel: &Channel) -> String {
+        match channel.account_id.as_str() {
+            "" => self.account.id.clone(),
+            account_id => account_id.to_string(),
+        }
+    }
+
+    /**
+     * If the channels of an account are shown
+     * @param self
+     * @param account_id
+     */
+    pub fn shows_account(&self, account_id: &str) -> bool {
+        account_id == self.account.id
+            || (self.unified && self.channels.items.iter().any(|channel| channel.account_id == account_id))
+    }
+
+    /**
+     * Accounts whose channels are shown: every enabled Jami account in
+     * unified mode, else the current one
+     * @param self
+     * @param backend
+     */
+    pub fn shown_accounts(&self, backend: &dyn Backend) -> Vec<Account> {
+        AppData::accounts_to_show(backend, &self.account, self.unified)
+    }
+
+    /**
+     * Accounts shown with an account as the current one
+     * @param backend
+     * @param current
+     * @param unified   if every enabled account is shown
+     */
+    fn accounts_to_show(backend: &dyn Backend, current: &Account, unified: bool) -> Vec<Account> {
+        if !unified {
+            return vec![current.clone()];
+        }
+        let mut accounts: Vec<Account> = backend
+            .get_account_list()
+            .into_iter()
+            .filter(|account| {
+                let details = backend.get_account_details(&account.id);
+                let enabled = details.get("Account.enable").map(|e| e != "false").unwrap_or(true);
+                let jami = details.get("Account.type").map(|t| t == "RING").unwrap_or(true);
+                enabled && jami
+            })
+            .collect();
+        // The current account first
+        if let Some(idx) = accounts.iter().position(|account| account.id == current.id) {
+            let account = accounts.remove(idx);
+            accounts.insert(0, account);
+        }
+        accounts
+    }
+
+    /**
+     * Get channels of several accounts, each one starting with its generated
+     * channel, named after the account
+     * @param backend
+     * @param accounts
+     * @return the channels
+     */
+    pub fn channels_for_accounts(backend: &dyn Backend, accounts: &[Account]) -> Vec<Channel> {
+        if accounts.len() == 1 {
+            return AppData::channels_for_account(backend, &accounts[0]);
+        }
+        let mut channels = Vec::new();
+        for account in accounts {
+            let mut account_channels = AppData::channels_for_account(backend, account);
+            account_channels[0].title = format!("⚙️ {}", account.get_display_name());
+            channels.append(&mut account_channels);
+        }
+        channels
+    }
+
     /**
      * Get channel for account
+     * @param backend
      * @param account   
      * @return the channels
      */
-    pub fn channels_for_account(account: &Account) -> Vec<Channel> {
+    pub fn channels_for_account(backend: &dyn Backend, account: &Account) -> Vec<Channel> {
         let mut channels = Vec::new();
         let mut messages = Vec::new();
 
@@ -60,16 +166,24 @@ impl AppData {
 
         channels.push(Channel {
             id: String::from("⚙️ Jami-cli"),
+            account_id: account.id.clone(),
             title: String::from("⚙️ Jami-cli"),
             description: String::new(),
             members: Vec::new(),
             channel_type: ChannelType::Generated,
             messages,
             unread_messages: 0,
+            unread_mentions: 0,
+            notify: None,
+            scroll: 0,
+            oldest_commit: String::new(),
+            loading_history: false,
+            history_complete: true,
+            input_history: Vec::new(),
         });
 
         // Get trust requests
-        for request in Jami::get_trust_requests(&account.id) {
+        for request in backend.get_trust_requests(&account.id) {
             channels.push(Channel::new(
                 &request,
                 ChannelType::TrustRequest(request.clone()),
@@ -77,7 +191,7 @@ impl AppData {
         }
 
         // Get requests
-        for request in Jami::get_conversations_requests(&account.id) {
+        for request in backend.get_conversations_requests(&account.id) {
             channels.push(Channel::new(
                 &request.get("id").unwrap().clone(),
                 ChannelType::Invite,
@@ -85,19 +199,27 @@ impl AppData {
         }
 
         // Get conversations
-        for conversation in Jami::get_conversations(&account.id) {
+        for conversation in backend.get_conversations(&account.id) {
             let mut channel = Channel::new(&conversation, ChannelType::Group);
-            let new_infos = Jami::get_conversation_infos(&account.id, &conversation);
-            channel.members = AppData::get_conversations_members(&account.id, &conversation);
+            let new_infos = backend.get_conversation_infos(&account.id, &conversation);
+            channel.members =
+                AppData::get_conversations_members(backend, &account.id, &conversation);
             channel.update_infos(new_infos);
             channels.push(channel);
         }
+        for channel in &mut channels {
+            channel.account_id = account.id.clone();
+        }
         channels
     }
 
     // Retrieve conversation members
-    pub fn get_conversations_members(account_id: &String, conversation: &String) -> Vec<Member> {
-        let members_from_daemon = Jami::get_members(account_id, &conversation);
+    pub fn get_conversations_members(
+        backend: &dyn Backend,
+        account_id: &String,
+        conversation: &String,
+    ) -> Vec<Member> {
+        let members_from_daemon = backend.get_members(account_id, &conversation);
         let mut members = Vec::new();
         for member in members_from_daemon {
             let role: Role;
@@ -114,32 +236,147 @@ impl AppData {
         members
     }
 
-    // Init self
-    pub fn init_from_jami() -> anyhow::Result<Self> {
-        let account = Jami::select_jami_account(true);
-        let mut channels = Vec::new();
-        let mut profile_manager = ProfileManager::new();
-        let transfer_manager = TransferManager::new();
-        if !account.id.is_empty() {
-            profile_manager.load_from_account(&account.id);
-            channels = AppData::channels_for_account(&account);
+    /**
+     * Load the cached data of the last used account
+     * @return the cached data if any
+     */
+    pub fn load_from_cache() -> Option<Self> {
+        let dir = get_app_dir(AppDataType::UserData, &APP_INFO, "accounts").ok()?;
+        let account_id = fs::read_to_string(dir.join(LAST_ACCOUNT_FILE)).ok()?;
+        let file = File::open(dir.join(format!("{}.json", account_id.trim()))).ok()?;
+        let mut data: AppData = serde_json::from_reader(io::BufReader::new(file)).ok()?;
+        // Presences will be received again from the daemon
+        data.tracked_presences.clear();
+        data.input_cursor = data.input.chars().count();
+        if !data.channels.items.is_empty() {
+            data.channels.state.select(Some(0));
         }
+        Some(data)
+    }
 
-        let mut channels = StatefulList::with_items(channels);
-        if !channels.items.is_empty() {
-            channels.state.select(Some(0));
+    /**
+     * Write data of the current account into the cache
+     * @param self
+     */
+    pub fn save_to_cache(&self) -> anyhow::Result<()> {
+        if self.account.id.is_empty() {
+            return Ok(());
         }
+        let dir = app_dir(AppDataType::UserData, &APP_INFO, "accounts")?;
+        let file = File::create(dir.join(format!("{}.json", self.account.id)))?;
+        serde_json::to_writer(io::BufWriter::new(file), self)?;
+        fs::write(dir.join(LAST_ACCOUNT_FILE), &self.account.id)?;
+        Ok(())
+    }
 
-        Ok(AppData {
+    /**
+     * Read from the daemon what refreshes the cache. Blocking, it runs on
+     * another thread while the cached data is shown.
+     * @param backend
+     * @param account_id    cached account
+     * @param unified       if every enabled account is shown
+     * @return None if the daemon has no account, the cache is then kept
+     */
+    pub fn fetch_from_jami(backend: &dyn Backend, account_id: &str, unified: bool) -> Option<JamiSnapshot> {
+        let mut account = backend.get_account(account_id);
+        if account.id.is_empty() {
+            account = backend.select_jami_account(true);
+        }
+        if account.id.is_empty() {
+            return None;
+        }
+        let accounts = AppData::accounts_to_show(backend, &account, unified);
+        let channels = AppData::channels_for_accounts(backend, &accounts);
+        Some(JamiSnapshot {
+            account,
+            accounts,
             channels,
+        })
+    }
+
+    /**
+     * Refresh cached data with what the daemon sent. Cached messages are
+     * kept until conversations are reloaded.
+     * @param self
+     * @param snapshot
+     */
+    pub fn merge_from_jami(&mut self, snapshot: JamiSnapshot) {
+        let JamiSnapshot {
+            account,
+            accounts,
+            mut channels,
+        } = snapshot;
+        // In unified mode, the user may have switched to another shown account meanwhile
+        let account = match accounts.iter().find(|shown| self.unified && shown.id == self.account.id) {
+            Some(current) => current.clone(),
+            None => account,
+        };
+        let cached_channels = std::mem::take(&mut self.channels.items);
+        let same_account = account.id == self.account.id;
+        self.account = account;
+        if same_account || self.unified {
+            for channel in &mut channels {
+                let cached = cached_channels
+                    .iter()
+                    .find(|c| c.is(&channel.account_id, &channel.id) && (same_account || !c.account_id.is_empty()));
+                if let Some(cached) = cached {
+                    channel.input_history = cached.input_history.clone();
+                    channel.notify = cached.notify;
+                    if channel.channel_type == ChannelType::Generated {
+                        continue;
+                    }
+                    channel.messages = cached.messages.clone();
+                    channel.unread_messages = cached.unread_messages;
+                    channel.unread_mentions = cached.unread_mentions;
+                }
+            }
+        }
+
+        for account in &accounts {
+            self.profile_manager.load_from_account(&account.id);
+        }
+        self.channels = StatefulList::with_items(channels);
+        if !self.channels.items.is_empty() {
+            self.channels.state.select(Some(0));
+        }
+    }
+
+    // Init self
+    pub fn init_from_jami(backend: &dyn Backend, unified: bool) -> anyhow::Result<Self> {
+        let account = backend.select_jami_account(true);
+        let mut data = AppData {
+            channels: StatefulList::with_items(Vec::new()),
             input: String::new(),
-            profile_manager,
-            transfer_manager,
+            profile_manager: ProfileManager::new(),
+            transfer_manager: TransferManager::new(),
             out_invite: Vec::new(),
             pending_rm: Vec::new(),
             input_cursor: 0,
             tracked_presences: HashMap::new(),
             account,
-        })
+            unified,
+        };
+        data.reload_channels(backend);
+        Ok(data)
+    }
+
+    /**
+     * Replace the channels by the ones of the shown accounts
+     * @param self
+     * @param backend
+     */
+    pub fn reload_channels(&mut self, backend: &dyn Backend) {
+        let mut channels = Vec::new();
+        if !self.account.id.is_empty() {
+            let accounts = self.shown_accounts(backend);
+            for account in &accounts {
+                self.profile_manager.load_from_account(&account.id);
+            }
+            channels = AppData::channels_for_accounts(backend, &accounts);
+        }
+        self.channels = StatefulList::with_items(channels);
+        if !self.channels.items.is_empty() {
+            self.channels.state.select(Some(0));
+        }
     }
 }
@@ -0,0 +1,451 @@
+use crate::calls::call_manager;
+
+use dbus::arg::{AppendAll, ReadAll};
+use dbus::blocking::Connection;
+use jami_rs::account::Account;
+use jami_rs::Jami;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Signals of the daemon, with the keys of the user as input
+pub type Event = jami_rs::Event<crossterm::event::KeyEvent>;
+
+/**
+ * Informations about a data transfer
+ */
+#[derive(Clone, Debug, Default)]
+pub struct TransferInfo {
+    pub display_name: String,
+    pub path: String,
+    pub last_event: i32,
+    pub flags: u32,
+    pub total_size: i64,
+    pub bytes_progress: i64,
+}
+
+/**
+ * Calls made by the client to the daemon. This allows to run the app
+ * without a running dring (see FakeBackend).
+ */
+pub trait Backend {
+    // Accounts
+    fn select_jami_account(&self, create_if_not: bool) -> Account;
+    fn get_account(&self, account_id: &str) -> Account;
+    fn get_account_list(&self) -> Vec<Account>;
+    fn get_account_details(&self, account_id: &String) -> HashMap<String, String>;
+    fn set_account_details(&self, account_id: &String, details: HashMap<String, String>);
+    /**
+     * @param details   like Account.archivePin to link an account
+     * @return the id of the account, None if the daemon refused it
+     */
+    fn create_account(&self, details: HashMap<String, String>) -> Option<String>;
+    fn rm_account(&self, account_id: &str);
+    /**
+     * Change the profile the daemon sends to the contacts of an account
+     * @param avatar    absolute path of an image, or empty
+     * @return if the daemon got the profile
+     */
+    fn update_profile(&self, account_id: &String, display_name: &String, avatar: &String) -> bool;
+    /**
+     * Write an archive of the account
+     * @param password  of the archive, can be empty
+     * @return if the archive was written
+     */
+    fn export_to_file(&self, account_id: &String, path: &String, password: &String) -> bool;
+    /**
+     * Publish the account to link another device. The PIN comes with AccountEvent::PinExported
+     * @return if the export started
+     */
+    fn export_on_ring(&self, account_id: &String, password: &String) -> bool;
+    /**
+     * @return the name of each device linked to the account
+     */
+    fn get_known_devices(&self, account_id: &String) -> HashMap<String, String>;
+    /**
+     * Revoke a device of the account. The result comes with AccountEvent::DeviceRevoked
+     * @return if the revocation started
+     */
+    fn revoke_device(&self, account_id: &String, password: &String, device_id: &String) -> bool;
+
+    // Name server
+    /**
+     * @return if the registration started, the name is known once it ends
+     */
+    fn register_name(&self, account_id: &String, password: &String, name: &String) -> bool;
+    fn lookup_name(&self, account_id: &String, name_service: &String, name: &String);
+    fn lookup_address(&self, account_id: &String, name_service: &String, address: &String);
+
+    // Contacts and trust requests
+    fn add_contact(&self, account_id: &String, uri: &String);
+    /**
+     * @return uris of the contacts of the account
+     */
+    fn get_contacts(&self, account_id: &String) -> Vec<String>;
+    fn subscribe_presence(&self, account_id: &String, uri: &String, flag: bool);
+    fn get_trust_requests(&self, account_id: &String) -> Vec<String>;
+    fn send_trust_request(&self, account_id: &String, to: &String, payloads: Vec<u8>);
+    fn accept_trust_request(&self, account_id: &String, from: &String);
+    fn discard_trust_request(&self, account_id: &String, from: &String);
+
+    // Conversations
+    fn get_conversations(&self, account_id: &String) -> Vec<String>;
+    fn get_conversations_requests(&self, account_id: &String) -> Vec<HashMap<String, String>>;
+    fn get_conversation_infos(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+    ) -> HashMap<String, String>;
+    fn update_conversation_infos(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        infos: HashMap<String, String>,
+    );
+    fn start_conversation(&self, account_id: &String);
+    fn rm_conversation(&self, account_id: &String, conversation_id: &String) -> bool;
+    fn accept_request(&self, account_id: &String, conversation_id: &String);
+    fn decline_request(&self, account_id: &String, conversation_id: &String);
+
+    // Members
+    fn get_members(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+    ) -> Vec<HashMap<String, String>>;
+    fn add_conversation_member(&self, account_id: &String, conversation_id: &String, uri: &String);
+    fn rm_conversation_member(&self, account_id: &String, conversation_id: &String, uri: &String);
+
+    // Messages
+    fn send_conversation_message(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        message: &String,
+        parent: &String,
+    );
+    /**
+     * Load messages of a conversation, sent with Event::ConversationLoaded
+     * @param from      commit to start from, empty for the newest one
+     * @return the id of the load, sent with the messages, 0 on failure
+     */
+    fn load_conversation(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        from: &String,
+        size: u32,
+    ) -> u32;
+
+    // Transfers
+    fn send_file(&self, account_id: &String, conversation_id: &String, path: &String);
+    fn data_transfer_info(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        tid: u64,
+    ) -> Option<TransferInfo>;
+    fn accept_file_transfer(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        tid: u64,
+        path: &String,
+    );
+    fn cancel_file_transfer(&self, account_id: &String, conversation_id: &String, tid: u64);
+
+    // Calls
+    /**
+     * @return the id of the call, None if the daemon refused it
+     */
+    fn place_call(&self, account_id: &String, to: &String) -> Option<String>;
+    fn accept_call(&self, call_id: &String);
+    fn refuse_call(&self, call_id: &String);
+    fn hang_up(&self, call_id: &String);
+    fn hold_call(&self, call_id: &String, hold: bool);
+    fn mute_call(&self, call_id: &String, mute: bool);
+
+    // Conferences
+    /**
+     * Call every participant, the id of the conference comes with conferenceCreated
+     */
+    fn create_conference(&self, account_id: &String, participants: &Vec<String>);
+    fn add_to_conference(&self, call_id: &String, conf_id: &String);
+    fn hang_up_participant(&self, conf_id: &String, uri: &String);
+    fn hang_up_conference(&self, conf_id: &String);
+}
+
+/**
+ * Call a method of the ConfigurationManager which isn't exposed by jami-rs
+ * @param method
+ * @param args
+ */
+fn configuration_manager<A: AppendAll, R: ReadAll + 'static>(method: &str, args: A) -> Result<R, dbus::Error> {
+    let conn = Connection::new_session()?;
+    let proxy = conn.with_proxy("cx.ring.Ring", "/cx/ring/Ring/ConfigurationManager", Duration::from_secs(5));
+    proxy.method_call("cx.ring.Ring.ConfigurationManager", method, args)
+}
+
+/**
+ * Backend talking to dring via jami-rs
+ */
+pub struct JamiBackend;
+
+impl Backend for JamiBackend {
+    fn select_jami_account(&self, create_if_not: bool) -> Account {
+        Jami::select_jami_account(create_if_not)
+    }
+
+    fn get_account(&self, account_id: &str) -> Account {
+        Jami::get_account(account_id)
+    }
+
+    fn get_account_list(&self) -> Vec<Account> {
+        Jami::get_account_list()
+    }
+
+    fn get_account_details(&self, account_id: &String) -> HashMap<String, String> {
+        Jami::get_account_details(account_id)
+    }
+
+    fn set_account_details(&self, account_id: &String, details: HashMap<String, String>) {
+        Jami::set_account_details(account_id, details);
+    }
+
+    fn create_account(&self, details: HashMap<String, String>) -> Option<String> {
+        let account: Result<(String,), _> = configuration_manager("addAccount", (details,));
+        account.ok().map(|(account_id,)| account_id).filter(|account_id| !account_id.is_empty())
+    }
+
+    fn rm_account(&self, account_id: &str) {
+        Jami::rm_account(account_id);
+    }
+
+    fn update_profile(&self, account_id: &String, display_name: &String, avatar: &String) -> bool {
+        // Flag 0: the avatar is the path of a file, read by the daemon
+        let updated: Result<(), _> = configuration_manager("updateProfile", (account_id, display_name, avatar, 0u64));
+        updated.is_ok()
+    }
+
+    fn export_to_file(&self, account_id: &String, path: &String, password: &String) -> bool {
+        let exported: Result<(bool,), _> = configuration_manager("exportToFile", (account_id, path, password));
+        exported.map(|(exported,)| exported).unwrap_or(false)
+    }
+
+    fn export_on_ring(&self, account_id: &String, password: &String) -> bool {
+        let exported: Result<(bool,), _> = configuration_manager("exportOnRing", (account_id, password));
+        exported.map(|(exported,)| exported).unwrap_or(false)
+    }
+
+    fn get_known_devices(&self, account_id: &String) -> HashMap<String, String> {
+        let devices: Result<(HashMap<String, String>,), _> = configuration_manager("getKnownRingDevices", (account_id,));
+        devices.map(|(devices,)| devices).unwrap_or_default()
+    }
+
+    fn revoke_device(&self, account_id: &String, password: &String, device_id: &String) -> bool {
+        let revoked: Result<(bool,), _> = configuration_manager("revokeDevice", (account_id, password, device_id));
+        revoked.map(|(revoked,)| revoked).unwrap_or(false)
+    }
+
+    fn register_name(&self, account_id: &String, password: &String, name: &String) -> bool {
+        let registered: Result<(bool,), _> = configuration_manager("registerName", (account_id, password, name));
+        registered.map(|(registered,)| registered).unwrap_or(false)
+    }
+
+    fn lookup_name(&self, account_id: &String, name_service: &String, name: &String) {
+        Jami::lookup_name(account_id, name_service, name);
+    }
+
+    fn lookup_address(&self, account_id: &String, name_service: &String, address: &String) {
+        Jami::lookup_address(account_id, name_service, address);
+    }
+
+    fn add_contact(&self, account_id: &String, uri: &String) {
+        Jami::add_contact(account_id, uri);
+    }
+
+    fn get_contacts(&self, account_id: &String) -> Vec<String> {
+        let contacts: Result<(Vec<HashMap<String, String>>,), _> =
+            configuration_manager("getContacts", (account_id,));
+        contacts
+            .map(|(contacts,)| contacts.into_iter().filter_map(|mut contact| contact.remove("id")).collect())
+            .unwrap_or_default()
+    }
+
+    fn subscribe_presence(&self, account_id: &String, uri: &String, flag: bool) {
+        Jami::subscribe_presence(account_id, uri, flag);
+    }
+
+    fn get_trust_requests(&self, account_id: &String) -> Vec<String> {
+        Jami::get_trust_requests(account_id)
+    }
+
+    fn send_trust_request(&self, account_id: &String, to: &String, payloads: Vec<u8>) {
+        Jami::send_trust_request(account_id, to, payloads);
+    }
+
+    fn accept_trust_request(&self, account_id: &String, from: &String) {
+        Jami::accept_trust_request(account_id, from);
+    }
+
+    fn discard_trust_request(&self, account_id: &String, from: &String) {
+        Jami::discard_trust_request(account_id, from);
+    }
+
+    fn get_conversations(&self, account_id: &String) -> Vec<String> {
+        Jami::get_conversations(account_id)
+    }
+
+    fn get_conversations_requests(&self, account_id: &String) -> Vec<HashMap<String, String>> {
+        Jami::get_conversations_requests(account_id)
+    }
+
+    fn get_conversation_infos(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+    ) -> HashMap<String, String> {
+        Jami::get_conversation_infos(account_id, conversation_id)
+    }
+
+    fn update_conversation_infos(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        infos: HashMap<String, String>,
+    ) {
+        Jami::update_conversation_infos(account_id, conversation_id, infos);
+    }
+
+    fn start_conversation(&self, account_id: &String) {
+        Jami::start_conversation(account_id);
+    }
+
+    fn rm_conversation(&self, account_id: &String, conversation_id: &String) -> bool {
+        Jami::rm_conversation(account_id, conversation_id)
+    }
+
+    fn accept_request(&self, account_id: &String, conversation_id: &String) {
+        Jami::accept_request(account_id, conversation_id);
+    }
+
+    fn decline_request(&self, account_id: &String, conversation_id: &String) {
+        Jami::decline_request(account_id, conversation_id);
+    }
+
+    fn get_members(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+    ) -> Vec<HashMap<String, String>> {
+        Jami::get_members(account_id, conversation_id)
+    }
+
+    fn add_conversation_member(&self, account_id: &String, conversation_id: &String, uri: &String) {
+        Jami::add_conversation_member(account_id, conversation_id, uri);
+    }
+
+    fn rm_conversation_member(&self, account_id: &String, conversation_id: &String, uri: &String) {
+        Jami::rm_conversation_member(account_id, conversation_id, uri);
+    }
+
+    fn send_conversation_message(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        message: &String,
+        parent: &String,
+    ) {
+        Jami::send_conversation_message(account_id, conversation_id, message, parent);
+    }
+
+    fn load_conversation(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        from: &String,
+        size: u32,
+    ) -> u32 {
+        Jami::load_conversation(account_id, conversation_id, from, size)
+    }
+
+    fn send_file(&self, account_id: &String, conversation_id: &String, path: &String) {
+        Jami::send_file(account_id.clone(), conversation_id.clone(), path.clone());
+    }
+
+    fn data_transfer_info(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        tid: u64,
+    ) -> Option<TransferInfo> {
+        let info = Jami::data_transfer_info(account_id.clone(), conversation_id.clone(), tid)?;
+        Some(TransferInfo {
+            display_name: info.display_name,
+            path: info.path,
+            last_event: info.last_event as i32,
+            flags: info.flags as u32,
+            total_size: info.total as i64,
+            bytes_progress: info.bytes_progress as i64,
+        })
+    }
+
+    fn accept_file_transfer(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        tid: u64,
+        path: &String,
+    ) {
+        Jami::accept_file_transfer(account_id, conversation_id, tid, path);
+    }
+
+    fn cancel_file_transfer(&self, account_id: &String, conversation_id: &String, tid: u64) {
+        Jami::cancel_file_transfer(account_id, conversation_id, tid);
+    }
+
+    fn place_call(&self, account_id: &String, to: &String) -> Option<String> {
+        let (call_id,): (String,) = call_manager("placeCall", (account_id, to)).ok()?;
+        if call_id.is_empty() {
+            return None;
+        }
+        Some(call_id)
+    }
+
+    fn accept_call(&self, call_id: &String) {
+        let _: Result<(), _> = call_manager("accept", (call_id,));
+    }
+
+    fn refuse_call(&self, call_id: &String) {
+        let _: Result<(bool,), _> = call_manager("refuse", (call_id,));
+    }
+
+    fn hang_up(&self, call_id: &String) {
+        let _: Result<(bool,), _> = call_manager("hangUp", (call_id,));
+    }
+
+    fn hold_call(&self, call_id: &String, hold: bool) {
+        let method = if hold { "hold" } else { "unhold" };
+        let _: Result<(bool,), _> = call_manager(method, (call_id,));
+    }
+
+    fn mute_call(&self, call_id: &String, mute: bool) {
+        let _: Result<(), _> = call_manager("muteLocalMedia", (call_id, "MEDIA_TYPE_AUDIO", mute));
+    }
+
+    fn create_conference(&self, account_id: &String, participants: &Vec<String>) {
+        let _: Result<(), _> = call_manager("createConfFromParticipantList", (account_id, participants));
+    }
+
+    fn add_to_conference(&self, call_id: &String, conf_id: &String) {
+        let _: Result<(bool,), _> = call_manager("addParticipant", (call_id, conf_id));
+    }
+
+    fn hang_up_participant(&self, conf_id: &String, uri: &String) {
+        let _: Result<(), _> = call_manager("hangupParticipant", (conf_id, uri));
+    }
+
+    fn hang_up_conference(&self, conf_id: &String) {
+        let _: Result<(bool,), _> = call_manager("hangUpConference", (conf_id,));
+    }
+}
@@ -0,0 +1,223 @@
+//! Indexing of the history of the conversations, so that /search finds
+//! messages never shown by the client. Histories are read from the daemon
+//! page by page, one conversation at a time, while the app runs.
+
+use crate::app::HISTORY_BATCH_SIZE;
+use crate::search::{BackfillMark, SearchIndex};
+use crate::util::{Message, MessageKind};
+
+use chrono::{TimeZone, Utc};
+use jami_rs::ProfileManager;
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Delay after which a page the daemon didn't send is given up, with its conversation
+pub const PAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/**
+ * Page of history to load from the daemon
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageRequest {
+    pub account_id: String,
+    pub conversation_id: String,
+    /// Commit starting the page, empty for the newest one
+    pub from: String,
+}
+
+/**
+ * Conversation being indexed
+ */
+struct Job {
+    request: PageRequest,
+    /// Id of the load sent to the daemon, 0 until sent
+    request_id: u32,
+    requested_at: Instant,
+    mark: BackfillMark,
+    /// Going from the newest commit down to the high-water mark of the last run
+    catching_up: bool,
+    /// Newest commit seen in this run, the next high-water mark
+    newest: String,
+}
+
+/**
+ * Backfill of the search index
+ */
+#[derive(Default)]
+pub struct Backfill {
+    /// Conversations indexed, or being indexed, since the app started
+    started: HashSet<(String, String)>,
+    job: Option<Job>,
+}
+
+impl Backfill {
+    /**
+     * Start indexing the first conversation not indexed yet, if no page is being loaded
+     * @param self
+     * @param index
+     * @param conversations     account and id of the shown conversations
+     * @param now
+     * @return the first page to load
+     */
+    pub fn start(
+        &mut self,
+        index: &SearchIndex,
+        conversations: &[(String, String)],
+        now: Instant,
+    ) -> Option<PageRequest> {
+        if let Some(job) = &self.job {
+            if now < job.requested_at + PAGE_TIMEOUT {
+                return None;
+            }
+            self.job = None;
+        }
+        let (account_id, conversation_id) = conversations
+            .iter()
+            .find(|conversation| !self.started.contains(conversation))?
+            .clone();
+        self.started
+            .insert((account_id.clone(), conversation_id.clone()));
+        let mark = index.backfill_mark(&account_id, &conversation_id).ok()?;
+        let job = Job {
+            request: PageRequest {
+                account_id,
+                conversation_id,
+                from: String::new(),
+            },
+            request_id: 0,
+            requested_at: now,
+            catching_up: !mark.newest.is_empty(),
+            mark,
+            newest: String::new(),
+        };
+        let request = job.request.clone();
+        self.job = Some(job);
+        Some(request)
+    }
+
+    /**
+     * The page asked by start() or on_page() was sent to the daemon
+     * @param self
+     * @param request_id    id of the load, 0 if the daemon refused it
+     * @param now
+     */
+    pub fn on_requested(&mut self, request_id: u32, now: Instant) {
+        if request_id == 0 {
+            self.job = None;
+        } else if let Some(job) = &mut self.job {
+            job.request_id = request_id;
+            job.requested_at = now;
+        }
+    }
+
+    /**
+     * If a loaded conversation is a page of the backfill, not for the chat
+     * @param self
+     * @param request_id
+     */
+    pub fn is_page(&self, request_id: u32) -> bool {
+        matches!(&self.job, Some(job) if request_id != 0 && job.request_id == request_id)
+    }
+
+    /**
+     * Index a page loaded from the daemon
+     * @param self
+     * @param index
+     * @param payloads          commits, from the newest to the oldest
+     * @param profile_manager   to name the authors
+     * @return the next page to load, if the conversation isn't indexed yet
+     */
+    pub fn on_page(
+        &mut self,
+        index: &SearchIndex,
+        payloads: &[HashMap<String, String>],
+        profile_manager: &ProfileManager,
+    ) -> Option<PageRequest> {
+        let mut job = self.job.take()?;
+        let account_id = job.request.account_id.clone();
+        let conversation_id = job.request.conversation_id.clone();
+        let messages: Vec<Message> = payloads
+            .iter()
+            .filter_map(|payloads| text_message(payloads, profile_manager))
+            .collect();
+        index
+            .add_all(&account_id, &conversation_id, &messages)
+            .ok()?;
+
+        let ids: Vec<&String> = payloads
+            .iter()
+            .filter_map(|payloads| payloads.get("id"))
+            .collect();
+        if ids.is_empty() {
+            // Nothing to remember, the conversation is read again next time
+            return None;
+        }
+        // The first page of a run starts from the newest commit
+        if job.newest.is_empty() {
+            job.newest = ids[0].clone();
+        }
+        let oldest = ids[ids.len() - 1].clone();
+        let end = payloads.len() < HISTORY_BATCH_SIZE as usize;
+        if job.catching_up {
+            if !end && !ids.contains(&&job.mark.newest) {
+                job.request.from = oldest;
+                return self.continue_job(job);
+            }
+            // The high-water mark only moves once the gap before it is indexed
+            job.catching_up = false;
+            job.mark.newest = job.newest.clone();
+            if end {
+                job.mark.oldest = oldest;
+                job.mark.complete = true;
+            }
+        } else {
+            if job.mark.newest.is_empty() {
+                job.mark.newest = job.newest.clone();
+            }
+            job.mark.oldest = oldest;
+            job.mark.complete = end;
+        }
+        index
+            .set_backfill_mark(&account_id, &conversation_id, &job.mark)
+            .ok()?;
+        if job.mark.complete {
+            return None;
+        }
+        job.request.from = job.mark.oldest.clone();
+        self.continue_job(job)
+    }
+
+    fn continue_job(&mut self, mut job: Job) -> Option<PageRequest> {
+        job.request_id = 0;
+        let request = job.request.clone();
+        self.job = Some(job);
+        Some(request)
+    }
+}
+
+/**
+ * Text message of a commit, as indexed
+ * @param payloads
+ * @param profile_manager   to name the author
+ */
+fn text_message(
+    payloads: &HashMap<String, String>,
+    profile_manager: &ProfileManager,
+) -> Option<Message> {
+    if payloads.get("type").map(|commit_type| commit_type.as_str()) != Some("text/plain") {
+        return None;
+    }
+    let timestamp = payloads
+        .get("timestamp")
+        .and_then(|timestamp| timestamp.parse::<i64>().ok())
+        .unwrap_or(0);
+    let author = payloads.get("author").cloned().unwrap_or_default();
+    let mut message = Message::with_kind(
+        profile_manager.display_name(&author),
+        MessageKind::Text(payloads.get("body").cloned().unwrap_or_default()),
+        Utc.timestamp(timestamp, 0),
+    );
+    message.id = payloads.get("id").cloned().unwrap_or_default();
+    Some(message)
+}
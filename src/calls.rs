@@ -0,0 +1,264 @@
+//! Calls are not handled by jami-rs, so the CallManager of the daemon is
+//! used directly through dbus.
+
+use dbus::arg::{AppendAll, ReadAll};
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+use dbus::Message;
+use tokio::sync::mpsc::UnboundedSender;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const DAEMON_NAME: &str = "cx.ring.Ring";
+const CALL_MANAGER_PATH: &str = "/cx/ring/Ring/CallManager";
+const CALL_MANAGER_IFACE: &str = "cx.ring.Ring.CallManager";
+
+/**
+ * Call signals of the daemon
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallEvent {
+    Incoming {
+        account_id: String,
+        call_id: String,
+        from: String,
+    },
+    StateChanged {
+        call_id: String,
+        state: String,
+        code: i32,
+    },
+    ConferenceCreated {
+        conf_id: String,
+    },
+    /// Participants of a conference changed
+    ConferenceInfos {
+        conf_id: String,
+        infos: Vec<HashMap<String, String>>,
+    },
+    ConferenceRemoved {
+        conf_id: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallState {
+    Incoming,
+    Connecting,
+    Ringing,
+    Current,
+    Hold,
+    Busy,
+    Failure,
+    Over,
+    Other(String),
+}
+
+impl CallState {
+    /**
+     * Parse a state sent by callStateChanged
+     * @param state
+     */
+    pub fn parse(state: &str) -> Self {
+        match state {
+            "INCOMING" => CallState::Incoming,
+            "CONNECTING" => CallState::Connecting,
+            "RINGING" => CallState::Ringing,
+            "CURRENT" => CallState::Current,
+            "HOLD" => CallState::Hold,
+            "BUSY" => CallState::Busy,
+            "FAILURE" => CallState::Failure,
+            "OVER" | "HUNGUP" => CallState::Over,
+            other => CallState::Other(other.to_string()),
+        }
+    }
+
+    /**
+     * If the call doesn't exist anymore for the daemon
+     */
+    pub fn is_finished(&self) -> bool {
+        matches!(self, CallState::Busy | CallState::Failure | CallState::Over)
+    }
+}
+
+/**
+ * A call in progress
+ */
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub id: String,
+    pub account_id: String,
+    /// Uri of the peer
+    pub peer: String,
+    /// Conversation with the peer, if known
+    pub conversation_id: String,
+    pub state: CallState,
+    /// When the call was answered
+    pub started_at: Option<Instant>,
+    pub muted: bool,
+    /// Conference to add the call to once answered
+    pub conference_id: String,
+}
+
+impl Call {
+    pub fn new(id: String, account_id: String, peer: String, state: CallState) -> Self {
+        Self {
+            id,
+            account_id,
+            peer,
+            conversation_id: String::new(),
+            state,
+            started_at: None,
+            muted: false,
+            conference_id: String::new(),
+        }
+    }
+
+    /**
+     * Duration since the call was answered
+     */
+    pub fn duration(&self) -> Duration {
+        self.started_at
+            .map(|started_at| started_at.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+/**
+ * Participant of a conference
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Participant {
+    /// Empty for the user hosting the conference
+    pub uri: String,
+    pub muted: bool,
+    pub moderator: bool,
+}
+
+impl Participant {
+    /**
+     * Read a participant sent by onConferenceInfosUpdated
+     * @param infos
+     */
+    pub fn from_infos(infos: &HashMap<String, String>) -> Self {
+        let flag = |key: &str| infos.get(key).map(|v| v == "true").unwrap_or(false);
+        Self {
+            uri: normalize_uri(infos.get("uri").map(|u| u.as_str()).unwrap_or("")),
+            muted: flag("audioLocalMuted") || flag("audioModeratorMuted"),
+            moderator: flag("isModerator"),
+        }
+    }
+}
+
+/**
+ * Conference hosted by the user
+ */
+#[derive(Clone, Debug)]
+pub struct Conference {
+    pub id: String,
+    pub account_id: String,
+    /// Swarm the conference was started from
+    pub conversation_id: String,
+    pub participants: Vec<Participant>,
+}
+
+/**
+ * Get the hash from an uri like "ring:hash" or "<jami:hash@ring.dht>"
+ * @param uri
+ */
+pub fn normalize_uri(uri: &str) -> String {
+    let uri = uri.trim_start_matches('<').trim_end_matches('>');
+    let uri = uri
+        .strip_prefix("ring:")
+        .or_else(|| uri.strip_prefix("jami:"))
+        .unwrap_or(uri);
+    uri.trim_end_matches("@ring.dht").to_string()
+}
+
+/**
+ * Format a duration like 1:02:03 or 02:03
+ * @param duration
+ */
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs / 3600 {
+        0 => format!("{:02}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+/**
+ * Call a method of the CallManager
+ * @param method
+ * @param args
+ */
+pub fn call_manager<A: AppendAll, R: ReadAll + 'static>(method: &str, args: A) -> Result<R, dbus::Error> {
+    let conn = Connection::new_session()?;
+    let proxy = conn.with_proxy(DAEMON_NAME, CALL_MANAGER_PATH, Duration::from_secs(5));
+    proxy.method_call(CALL_MANAGER_IFACE, method, args)
+}
+
+/**
+ * Forward call signals of the daemon until stopped. Blocking.
+ * @param tx        where to send events
+ * @param stop      set to true to stop listening
+ */
+pub fn listen_signals(tx: UnboundedSender<CallEvent>, stop: Arc<AtomicBool>) -> Result<(), dbus::Error> {
+    let conn = Connection::new_session()?;
+
+    let incoming_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CALL_MANAGER_IFACE, "incomingCall"),
+        move |(account_id, call_id, from): (String, String, String), _: &Connection, _: &Message| {
+            let _ = incoming_tx.send(CallEvent::Incoming {
+                account_id,
+                call_id,
+                from,
+            });
+            true
+        },
+    )?;
+    let state_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CALL_MANAGER_IFACE, "callStateChanged"),
+        move |(call_id, state, code): (String, String, i32), _: &Connection, _: &Message| {
+            let _ = state_tx.send(CallEvent::StateChanged {
+                call_id,
+                state,
+                code,
+            });
+            true
+        },
+    )?;
+    let created_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CALL_MANAGER_IFACE, "conferenceCreated"),
+        move |(conf_id,): (String,), _: &Connection, _: &Message| {
+            let _ = created_tx.send(CallEvent::ConferenceCreated { conf_id });
+            true
+        },
+    )?;
+    let infos_tx = tx.clone();
+    conn.add_match(
+        MatchRule::new_signal(CALL_MANAGER_IFACE, "onConferenceInfosUpdated"),
+        move |(conf_id, infos): (String, Vec<HashMap<String, String>>), _: &Connection, _: &Message| {
+            let _ = infos_tx.send(CallEvent::ConferenceInfos { conf_id, infos });
+            true
+        },
+    )?;
+    conn.add_match(
+        MatchRule::new_signal(CALL_MANAGER_IFACE, "conferenceRemoved"),
+        move |(conf_id,): (String,), _: &Connection, _: &Message| {
+            let _ = tx.send(CallEvent::ConferenceRemoved { conf_id });
+            true
+        },
+    )?;
+
+    while !stop.load(Ordering::Relaxed) {
+        conn.process(Duration::from_millis(500))?;
+    }
+    Ok(())
+}
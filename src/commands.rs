@@ -0,0 +1,524 @@
+use crate::util::ChannelType;
+
+use thiserror::Error;
+
+/**
+ * Channels where a command can be used
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    Any,
+    Generated,
+    Group,
+    /// Conversation requests and trust requests
+    Request,
+    Search,
+}
+
+impl Scope {
+    pub fn contains(&self, channel_type: &ChannelType) -> bool {
+        matches!(
+            (self, channel_type),
+            (Scope::Any, _)
+                | (Scope::Generated, ChannelType::Generated)
+                | (Scope::Group, ChannelType::Group)
+                | (Scope::Request, ChannelType::Invite)
+                | (Scope::Request, ChannelType::TrustRequest(_))
+                | (Scope::Search, ChannelType::Search)
+        )
+    }
+}
+
+/**
+ * Type of an argument, used for validation and completion
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    Word,
+    /// All remaining words
+    Rest,
+    Number,
+    Path,
+    /// Member of the current conversation
+    Member,
+    /// Someone who is not necessarily in the current conversation
+    Contact,
+    Account,
+}
+
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+const fn arg(name: &'static str, kind: ArgKind) -> Arg {
+    Arg {
+        name,
+        kind,
+        optional: false,
+    }
+}
+
+const fn opt(name: &'static str, kind: ArgKind) -> Arg {
+    Arg {
+        name,
+        kind,
+        optional: true,
+    }
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub scopes: &'static [Scope],
+    pub args: &'static [Arg],
+    pub help: &'static str,
+}
+
+impl Command {
+    /**
+     * Get usage, like "/accept <tid> [path]"
+     * @param self
+     */
+    pub fn usage(&self) -> String {
+        let mut usage = String::from(self.name);
+        for arg in self.args {
+            if arg.optional {
+                usage += &format!(" [{}]", arg.name);
+            } else {
+                usage += &format!(" <{}>", arg.name);
+            }
+        }
+        usage
+    }
+
+    pub fn applies_to(&self, channel_type: &ChannelType) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(channel_type))
+    }
+}
+
+/**
+ * Every command of the client. Commands with the same name but different
+ * scopes are allowed, the first one matching the channel is used.
+ */
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "/help",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Show this help",
+    },
+    Command {
+        name: "/new",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "Start a new conversation",
+    },
+    Command {
+        name: "/msg",
+        scopes: &[Scope::Any],
+        args: &[arg("id|username", ArgKind::Contact)],
+        help: "Start a conversation with someone",
+    },
+    Command {
+        name: "/list",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "List accounts",
+    },
+    Command {
+        name: "/switch",
+        scopes: &[Scope::Generated],
+        args: &[arg("id", ArgKind::Account)],
+        help: "Switch to an account",
+    },
+    Command {
+        name: "/unified",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "Show the channels of every account at once, or only the current one",
+    },
+    Command {
+        name: "/add",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "Create a new account, with a guided form",
+    },
+    Command {
+        name: "/rm",
+        scopes: &[Scope::Generated],
+        args: &[arg("id", ArgKind::Account)],
+        help: "Remove an account",
+    },
+    Command {
+        name: "/link",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "Link an account of another device via a PIN",
+    },
+    Command {
+        name: "/import",
+        scopes: &[Scope::Generated],
+        args: &[opt("file", ArgKind::Path)],
+        help: "Import an account from a backup",
+    },
+    Command {
+        name: "/devices",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "List the devices linked to the account",
+    },
+    Command {
+        name: "/device-name",
+        scopes: &[Scope::Generated],
+        args: &[arg("name", ArgKind::Rest)],
+        help: "Rename this device",
+    },
+    Command {
+        name: "/revoke",
+        scopes: &[Scope::Generated],
+        args: &[arg("device", ArgKind::Word)],
+        help: "Revoke another device of the account, the password is then asked",
+    },
+    Command {
+        name: "/export",
+        scopes: &[Scope::Generated],
+        args: &[arg("path", ArgKind::Path)],
+        help: "Write a backup of the account, the password is then asked",
+    },
+    Command {
+        name: "/pin",
+        scopes: &[Scope::Generated],
+        args: &[],
+        help: "Generate a PIN to link the account on another device, the password is then asked",
+    },
+    Command {
+        name: "/get",
+        scopes: &[Scope::Generated],
+        args: &[opt("key", ArgKind::Word)],
+        help: "Get account details (if key specified, only get key)",
+    },
+    Command {
+        name: "/set",
+        scopes: &[Scope::Generated],
+        args: &[arg("key", ArgKind::Word), arg("value", ArgKind::Rest)],
+        help: "Set account detail",
+    },
+    Command {
+        name: "/leave",
+        scopes: &[Scope::Group],
+        args: &[],
+        help: "Leave this conversation",
+    },
+    Command {
+        name: "/leave",
+        scopes: &[Scope::Request],
+        args: &[],
+        help: "Decline this request",
+    },
+    Command {
+        name: "/join",
+        scopes: &[Scope::Request],
+        args: &[],
+        help: "Accept the request",
+    },
+    Command {
+        name: "/invite",
+        scopes: &[Scope::Group],
+        args: &[arg("hash|username", ArgKind::Contact)],
+        help: "Invite somebody to the conversation",
+    },
+    Command {
+        name: "/kick",
+        scopes: &[Scope::Group],
+        args: &[arg("hash|username", ArgKind::Member)],
+        help: "Kick someone from the conversation",
+    },
+    Command {
+        name: "/title",
+        scopes: &[Scope::Group],
+        args: &[arg("title", ArgKind::Rest)],
+        help: "Change the title of the room",
+    },
+    Command {
+        name: "/description",
+        scopes: &[Scope::Group],
+        args: &[arg("description", ArgKind::Rest)],
+        help: "Change the description of the room",
+    },
+    Command {
+        name: "/notify",
+        scopes: &[Scope::Group],
+        args: &[opt("all|mentions|none", ArgKind::Word)],
+        help: "Show or change which messages of the room notify you",
+    },
+    Command {
+        name: "/send",
+        scopes: &[Scope::Group],
+        args: &[arg("path", ArgKind::Path)],
+        help: "Send a file to the conversation",
+    },
+    Command {
+        name: "/accept",
+        scopes: &[Scope::Group],
+        args: &[arg("tid", ArgKind::Number), opt("path", ArgKind::Path)],
+        help: "Accept a file transfer",
+    },
+    Command {
+        name: "/cancel",
+        scopes: &[Scope::Group],
+        args: &[arg("tid", ArgKind::Number)],
+        help: "Cancel a file transfer",
+    },
+    Command {
+        name: "/call",
+        scopes: &[Scope::Group],
+        args: &[opt("member", ArgKind::Member)],
+        help: "Call the peer of a 1:1 conversation, or a member",
+    },
+    Command {
+        name: "/accept-call",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Answer the incoming call",
+    },
+    Command {
+        name: "/refuse-call",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Refuse the incoming call",
+    },
+    Command {
+        name: "/hangup",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Hang up the current call",
+    },
+    Command {
+        name: "/hold",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Put the current call on hold, or resume it",
+    },
+    Command {
+        name: "/mute",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Mute or unmute the microphone in the current call",
+    },
+    Command {
+        name: "/conference",
+        scopes: &[Scope::Group],
+        args: &[],
+        help: "Start a conference with the members of the conversation",
+    },
+    Command {
+        name: "/conf-invite",
+        scopes: &[Scope::Group],
+        args: &[arg("member", ArgKind::Member)],
+        help: "Invite someone into the conference of the conversation",
+    },
+    Command {
+        name: "/conf-kick",
+        scopes: &[Scope::Group],
+        args: &[arg("member", ArgKind::Member)],
+        help: "Remove a participant from the conference (admins only)",
+    },
+    Command {
+        name: "/search",
+        scopes: &[Scope::Any],
+        args: &[arg("text", ArgKind::Rest)],
+        help: "Search messages in all conversations (Ctrl+F)",
+    },
+    Command {
+        name: "/mentions",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "List the recent messages mentioning you in all conversations",
+    },
+    Command {
+        name: "/open",
+        scopes: &[Scope::Search],
+        args: &[arg("n", ArgKind::Number)],
+        help: "Go to the message in its conversation",
+    },
+    Command {
+        name: "/exit",
+        scopes: &[Scope::Any],
+        args: &[],
+        help: "Quit",
+    },
+];
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CommandError {
+    #[error("Unknown command {0}, see /help")]
+    Unknown(String),
+    #[error("Usage: {0}")]
+    Usage(String),
+    #[error("Unterminated quote")]
+    UnterminatedQuote,
+}
+
+/**
+ * A command typed by the user, with validated arguments
+ */
+#[derive(Debug)]
+pub struct ParsedCommand {
+    pub name: &'static str,
+    pub args: Vec<String>,
+}
+
+impl ParsedCommand {
+    /**
+     * Get an argument, or an empty string for a missing optional argument
+     */
+    pub fn arg(&self, idx: usize) -> &str {
+        self.args.get(idx).map(|arg| &arg[..]).unwrap_or("")
+    }
+
+    /**
+     * Get a number argument (already validated)
+     */
+    pub fn number(&self, idx: usize) -> u64 {
+        self.arg(idx).parse::<u64>().unwrap_or(0)
+    }
+}
+
+/**
+ * Commands usable in a channel
+ * @param channel_type
+ */
+pub fn commands_for(channel_type: &ChannelType) -> impl Iterator<Item = &'static Command> + '_ {
+    COMMANDS
+        .iter()
+        .filter(move |command| command.applies_to(channel_type))
+}
+
+/**
+ * Find a command usable in a channel
+ * @param name          with the leading '/'
+ * @param channel_type
+ */
+pub fn find(name: &str, channel_type: &ChannelType) -> Option<&'static Command> {
+    commands_for(channel_type).find(|command| command.name == name)
+}
+
+/**
+ * Generate help for a channel
+ * @param channel_type
+ * @return one line per command
+ */
+pub fn help(channel_type: &ChannelType) -> Vec<String> {
+    commands_for(channel_type)
+        .map(|command| format!("{}: {}", command.usage(), command.help))
+        .collect()
+}
+
+/**
+ * Split a line like a shell: words are separated by spaces, quotes
+ * group words and '\' escapes the next char.
+ * @param line
+ */
+pub fn split_args(line: &str) -> Result<Vec<String>, CommandError> {
+    split_words(line, usize::MAX).map(|(args, _)| args)
+}
+
+/**
+ * Split the first words of a line like split_args
+ * @param line
+ * @param max_words     number of words to split
+ * @return the words and the text after them, as typed
+ */
+fn split_words(line: &str, max_words: usize) -> Result<(Vec<String>, &str), CommandError> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        if args.len() == max_words {
+            return Ok((args, &line[pos..]));
+        }
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), c) => current.push(c),
+            (Some(_), '"') => quote = None,
+            (_, '\\') => {
+                if let Some((_, escaped)) = chars.next() {
+                    current.push(escaped);
+                }
+                in_word = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(CommandError::UnterminatedQuote);
+    }
+    if in_word {
+        args.push(current);
+    }
+    Ok((args, ""))
+}
+
+/**
+ * Parse a line typed in a channel
+ * @param line
+ * @param channel_type
+ * @return None if the line is not a command
+ */
+pub fn parse(line: &str, channel_type: &ChannelType) -> Result<Option<ParsedCommand>, CommandError> {
+    if !line.starts_with('/') {
+        return Ok(None);
+    }
+    let (mut name, line) = split_words(line, 1)?;
+    let name = name.remove(0);
+    let command = find(&name, channel_type).ok_or(CommandError::Unknown(name))?;
+
+    let usage = || CommandError::Usage(command.usage());
+    let takes_rest = command
+        .args
+        .last()
+        .map(|arg| arg.kind == ArgKind::Rest)
+        .unwrap_or(false);
+    // The last argument is kept as typed, with its quotes and spaces
+    let words = if takes_rest {
+        let (mut words, rest) = split_words(line, command.args.len() - 1)?;
+        let rest = rest.trim();
+        if !rest.is_empty() {
+            words.push(rest.to_string());
+        }
+        words
+    } else {
+        split_args(line)?
+    };
+    let required = command.args.iter().filter(|arg| !arg.optional).count();
+    if words.len() < required || words.len() > command.args.len() {
+        return Err(usage());
+    }
+    for (word, arg) in words.iter().zip(command.args) {
+        if arg.kind == ArgKind::Number && word.parse::<u64>().is_err() {
+            return Err(usage());
+        }
+    }
+
+    Ok(Some(ParsedCommand {
+        name: command.name,
+        args: words,
+    }))
+}
@@ -0,0 +1,168 @@
+use std::fs;
+
+/**
+ * Current Tab completion, to cycle through candidates
+ */
+pub struct Completion {
+    candidates: Vec<String>,
+    idx: usize,
+    /// Char index of the completed word in the input
+    start: usize,
+    /// Number of chars of the word currently inserted
+    len: usize,
+    /// Input after the last completion, to detect edits
+    input: String,
+}
+
+impl Completion {
+    pub fn new(candidates: Vec<String>, start: usize, len: usize) -> Self {
+        Self {
+            candidates,
+            idx: 0,
+            start,
+            len,
+            input: String::new(),
+        }
+    }
+
+    /**
+     * If the input didn't change since the last completion
+     * @param self
+     * @param input
+     * @param cursor    char index of the cursor
+     */
+    pub fn is_continued_by(&self, input: &str, cursor: usize) -> bool {
+        self.input == input && self.start + self.len == cursor
+    }
+
+    /**
+     * Go to the next candidate
+     * @param self
+     */
+    pub fn next(&mut self) {
+        self.idx = (self.idx + 1) % self.candidates.len();
+    }
+
+    /**
+     * Replace the completed word by the current candidate
+     * @param self
+     * @param input
+     * @return the new cursor position
+     */
+    pub fn apply(&mut self, input: &mut String) -> usize {
+        let candidate = &self.candidates[self.idx];
+        let before: String = input.chars().take(self.start).collect();
+        let after: String = input.chars().skip(self.start + self.len).collect();
+        *input = format!("{}{}{}", before, candidate, after);
+        self.len = candidate.chars().count();
+        self.input = input.clone();
+        self.start + self.len
+    }
+}
+
+/**
+ * Char index of the start of the word before the cursor
+ * @param input
+ * @param cursor    char index of the cursor
+ */
+pub fn word_start(input: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = input.chars().take(cursor).collect();
+    (0..chars.len())
+        .rev()
+        // Escaped spaces are part of the word
+        .find(|&idx| chars[idx].is_whitespace() && (idx == 0 || chars[idx - 1] != '\\'))
+        .map(|idx| idx + 1)
+        .unwrap_or(0)
+}
+
+/**
+ * Keep candidates starting with a prefix (case insensitive), sorted and without duplicates
+ * @param prefix
+ * @param candidates
+ */
+pub fn matching<I>(prefix: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let prefix = prefix.to_lowercase();
+    let mut result: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&prefix))
+        .collect();
+    result.sort();
+    result.dedup();
+    result
+}
+
+/**
+ * Escape a word for the command parser: spaces, quotes and backslashes
+ * are prefixed by a backslash
+ * @param word
+ */
+pub fn escape(word: &str) -> String {
+    let mut escaped = String::new();
+    for c in word.chars() {
+        if c.is_whitespace() || c == '"' || c == '\'' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/**
+ * Word being typed as the command parser will read it, even with an
+ * unterminated quote
+ * @param word
+ */
+pub fn unescape(word: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            '"' | '\'' => {}
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/**
+ * Files and directories starting with a path prefix. "~/" is supported
+ * and kept in candidates.
+ * @param prefix    path typed by the user, escaped like candidates
+ */
+pub fn path_candidates(prefix: &str) -> Vec<String> {
+    let prefix = unescape(prefix);
+    let (dir, file_prefix) = match prefix.rfind('/') {
+        Some(pos) => (&prefix[..=pos], &prefix[pos + 1..]),
+        None => ("", &prefix[..]),
+    };
+    let dir_to_read = match dir.strip_prefix("~/") {
+        Some(rest) => match dirs::home_dir() {
+            Some(home) => home.join(rest),
+            None => return Vec::new(),
+        },
+        None if dir.is_empty() => ".".into(),
+        None => dir.into(),
+    };
+    let entries = match fs::read_dir(dir_to_read) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files only if asked
+            if !name.starts_with(file_prefix) || (name.starts_with('.') && !file_prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            Some(escape(&format!("{}{}{}", dir, name, if is_dir { "/" } else { "" })))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
@@ -0,0 +1,126 @@
+use crate::appdata::APP_INFO;
+use crate::keys::{Chords, KeyBindings, KeyError};
+use crate::notifications::{NotificationError, NotificationPolicy, NotificationsConfig};
+use crate::theme::{Theme, ThemeConfig, ThemeError};
+use crate::transfers::{TransferError, TransferPolicy, TransfersConfig};
+
+use app_dirs::{get_app_root, AppDataType};
+use serde::Deserialize;
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Can't read {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("Invalid config {0}: {1}")]
+    Toml(PathBuf, toml::de::Error),
+    #[error("Invalid [keys] in {0}: {1}")]
+    Keys(PathBuf, KeyError),
+    #[error("Invalid [theme] in {0}: {1}")]
+    Theme(PathBuf, ThemeError),
+    #[error("Invalid [transfers] in {0}: {1}")]
+    Transfers(PathBuf, TransferError),
+    #[error("Invalid [notifications] in {0}: {1}")]
+    Notifications(PathBuf, NotificationError),
+}
+
+/**
+ * [accounts] section of the config
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountsConfig {
+    /// Show the channels of every enabled account at once
+    pub unified: bool,
+}
+
+/**
+ * Content of config.toml, as written by the user
+ */
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    keys: HashMap<String, Chords>,
+    theme: ThemeConfig,
+    transfers: TransfersConfig,
+    notifications: NotificationsConfig,
+    accounts: AccountsConfig,
+}
+
+/**
+ * User configuration
+ */
+#[derive(Debug, Default)]
+pub struct Config {
+    pub keys: KeyBindings,
+    pub theme: Theme,
+    pub transfers: TransferPolicy,
+    pub notifications: NotificationPolicy,
+    pub accounts: AccountsConfig,
+}
+
+impl Config {
+    /**
+     * Path of the config file, in the user config directory
+     */
+    pub fn path() -> Option<PathBuf> {
+        get_app_root(AppDataType::UserConfig, &APP_INFO)
+            .ok()
+            .map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /**
+     * Load the config of the user. A missing file gives the default config.
+     */
+    pub fn load() -> Result<Self, ConfigError> {
+        match Config::path() {
+            Some(path) if path.exists() => Config::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /**
+     * Load a config file
+     * @param path
+     */
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.into(), e))?;
+        Config::parse(&content).map_err(|e| match e {
+            ConfigError::Toml(_, e) => ConfigError::Toml(path.into(), e),
+            ConfigError::Keys(_, e) => ConfigError::Keys(path.into(), e),
+            ConfigError::Theme(_, e) => ConfigError::Theme(path.into(), e),
+            ConfigError::Transfers(_, e) => ConfigError::Transfers(path.into(), e),
+            ConfigError::Notifications(_, e) => ConfigError::Notifications(path.into(), e),
+            e => e,
+        })
+    }
+
+    /**
+     * Parse the content of a config file
+     * @param content
+     */
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        let raw: RawConfig =
+            toml::from_str(content).map_err(|e| ConfigError::Toml(CONFIG_FILE.into(), e))?;
+        let keys = KeyBindings::new(&raw.keys).map_err(|e| ConfigError::Keys(CONFIG_FILE.into(), e))?;
+        let theme = Theme::new(&raw.theme).map_err(|e| ConfigError::Theme(CONFIG_FILE.into(), e))?;
+        let transfers = TransferPolicy::new(&raw.transfers)
+            .map_err(|e| ConfigError::Transfers(CONFIG_FILE.into(), e))?;
+        let notifications = NotificationPolicy::new(&raw.notifications)
+            .map_err(|e| ConfigError::Notifications(CONFIG_FILE.into(), e))?;
+        Ok(Config {
+            keys,
+            theme,
+            transfers,
+            notifications,
+            accounts: raw.accounts,
+        })
+    }
+}
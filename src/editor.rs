@@ -0,0 +1,186 @@
+//! Editing functions for the input box. The cursor is always a char index
+//! in the input, display widths are only used for the layout.
+
+use unicode_width::UnicodeWidthChar;
+
+fn byte_idx(input: &str, cursor: usize) -> usize {
+    input
+        .char_indices()
+        .nth(cursor)
+        .map(|(idx, _)| idx)
+        .unwrap_or_else(|| input.len())
+}
+
+fn chars(input: &str) -> Vec<char> {
+    input.chars().collect()
+}
+
+/**
+ * Insert text at the cursor and move the cursor after it
+ * @param input
+ * @param cursor
+ * @param text
+ */
+pub fn insert(input: &mut String, cursor: &mut usize, text: &str) {
+    input.insert_str(byte_idx(input, *cursor), text);
+    *cursor += text.chars().count();
+}
+
+/**
+ * Remove chars between two positions and put the cursor at the start
+ * @param input
+ * @param cursor
+ * @param start     first char removed
+ * @param end       first char kept
+ */
+pub fn delete_range(input: &mut String, cursor: &mut usize, start: usize, end: usize) {
+    if start >= end {
+        return;
+    }
+    let range = byte_idx(input, start)..byte_idx(input, end);
+    input.replace_range(range, "");
+    *cursor = start;
+}
+
+/**
+ * Remove the char before the cursor
+ */
+pub fn backspace(input: &mut String, cursor: &mut usize) {
+    let pos = *cursor;
+    if pos > 0 {
+        delete_range(input, cursor, pos - 1, pos);
+    }
+}
+
+/**
+ * Remove the char under the cursor
+ */
+pub fn delete(input: &mut String, cursor: &mut usize) {
+    let pos = *cursor;
+    if pos < input.chars().count() {
+        delete_range(input, cursor, pos, pos + 1);
+    }
+}
+
+/**
+ * Start of the word before the cursor
+ */
+pub fn word_left(input: &str, cursor: usize) -> usize {
+    let chars = chars(input);
+    let mut pos = cursor.min(chars.len());
+    while pos > 0 && !chars[pos - 1].is_alphanumeric() {
+        pos -= 1;
+    }
+    while pos > 0 && chars[pos - 1].is_alphanumeric() {
+        pos -= 1;
+    }
+    pos
+}
+
+/**
+ * End of the word after the cursor
+ */
+pub fn word_right(input: &str, cursor: usize) -> usize {
+    let chars = chars(input);
+    let mut pos = cursor.min(chars.len());
+    while pos < chars.len() && !chars[pos].is_alphanumeric() {
+        pos += 1;
+    }
+    while pos < chars.len() && chars[pos].is_alphanumeric() {
+        pos += 1;
+    }
+    pos
+}
+
+/**
+ * Start of the line of the cursor
+ */
+pub fn line_start(input: &str, cursor: usize) -> usize {
+    let chars = chars(input);
+    let mut pos = cursor.min(chars.len());
+    while pos > 0 && chars[pos - 1] != '\n' {
+        pos -= 1;
+    }
+    pos
+}
+
+/**
+ * End of the line of the cursor
+ */
+pub fn line_end(input: &str, cursor: usize) -> usize {
+    let chars = chars(input);
+    let mut pos = cursor.min(chars.len());
+    while pos < chars.len() && chars[pos] != '\n' {
+        pos += 1;
+    }
+    pos
+}
+
+/**
+ * Same column on the previous line
+ * @return None if the cursor is on the first line
+ */
+pub fn line_up(input: &str, cursor: usize) -> Option<usize> {
+    let start = line_start(input, cursor);
+    if start == 0 {
+        return None;
+    }
+    let column = cursor - start;
+    let previous_start = line_start(input, start - 1);
+    Some((previous_start + column).min(start - 1))
+}
+
+/**
+ * Same column on the next line
+ * @return None if the cursor is on the last line
+ */
+pub fn line_down(input: &str, cursor: usize) -> Option<usize> {
+    let end = line_end(input, cursor);
+    if end >= input.chars().count() {
+        return None;
+    }
+    let column = cursor - line_start(input, cursor);
+    let next_end = line_end(input, end + 1);
+    Some((end + 1 + column).min(next_end))
+}
+
+/**
+ * Wrap the input for a given width, using the display width of chars
+ * @param input
+ * @param cursor
+ * @param width     available columns
+ * @return the lines and the position (column, line) of the cursor
+ */
+pub fn layout(input: &str, cursor: usize, width: usize) -> (Vec<String>, (usize, usize)) {
+    let width = width.max(1);
+    let mut lines = vec![String::new()];
+    let mut line_width = 0;
+    let mut cursor_pos = None;
+    for (idx, c) in input.chars().enumerate() {
+        let c_width = c.width().unwrap_or(0);
+        if c != '\n' && line_width + c_width > width {
+            lines.push(String::new());
+            line_width = 0;
+        }
+        if idx == cursor {
+            cursor_pos = Some((line_width, lines.len() - 1));
+        }
+        if c == '\n' {
+            lines.push(String::new());
+            line_width = 0;
+        } else {
+            lines.last_mut().unwrap().push(c);
+            line_width += c_width;
+        }
+    }
+    let cursor_pos = match cursor_pos {
+        Some(pos) => pos,
+        // The cursor is after the last char, on a new line if the last one is full
+        None if line_width >= width => {
+            lines.push(String::new());
+            (0, lines.len() - 1)
+        }
+        None => (line_width, lines.len() - 1),
+    };
+    (lines, cursor_pos)
+}
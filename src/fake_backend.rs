@@ -0,0 +1,938 @@
+use crate::accounts::AccountEvent;
+use crate::backend::{Backend, Event, TransferInfo};
+use crate::calls::CallEvent;
+
+use jami_rs::account::Account;
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/**
+ * A conversation known by the fake daemon
+ */
+#[derive(Default)]
+pub struct FakeConversation {
+    pub id: String,
+    pub infos: HashMap<String, String>,
+    pub members: Vec<HashMap<String, String>>,
+    /// Commits from the oldest to the newest
+    pub commits: Vec<HashMap<String, String>>,
+}
+
+#[derive(Default)]
+pub struct FakeState {
+    pub accounts: Vec<Account>,
+    pub details: HashMap<String, HashMap<String, String>>,
+    /// Conversations per account
+    pub conversations: HashMap<String, Vec<FakeConversation>>,
+    pub conversation_requests: HashMap<String, Vec<HashMap<String, String>>>,
+    pub trust_requests: HashMap<String, Vec<String>>,
+    /// Contacts per account
+    pub contacts: HashMap<String, Vec<String>>,
+    /// Name of each linked device, per account
+    pub devices: HashMap<String, HashMap<String, String>>,
+    /// Archive passwords per account, accounts without one accept any password
+    pub passwords: HashMap<String, String>,
+    /// Registered names (name -> address)
+    pub names: HashMap<String, String>,
+    pub transfers: HashMap<u64, TransferInfo>,
+    /// Every call made by the client, for assertions
+    pub calls: Vec<String>,
+    /// Events waiting to be handled by the app
+    pub events: VecDeque<Event>,
+    pub call_events: VecDeque<CallEvent>,
+    pub account_events: VecDeque<AccountEvent>,
+    /// Peer of each placed call
+    pub call_peers: HashMap<String, String>,
+    /// Participants of each conference
+    pub conferences: HashMap<String, Vec<String>>,
+    next_id: u64,
+}
+
+impl FakeState {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn conversation(
+        &mut self,
+        account_id: &str,
+        conversation_id: &str,
+    ) -> Option<&mut FakeConversation> {
+        self.conversations
+            .get_mut(account_id)?
+            .iter_mut()
+            .find(|conversation| conversation.id == conversation_id)
+    }
+
+    /**
+     * Add a commit to a conversation and announce it to the app
+     * @return the commit id
+     */
+    fn commit(
+        &mut self,
+        account_id: &str,
+        conversation_id: &str,
+        mut payloads: HashMap<String, String>,
+    ) -> String {
+        let id = self.next_id();
+        let commit_id = format!("commit_{}", id);
+        payloads.insert(String::from("id"), commit_id.clone());
+        payloads
+            .entry(String::from("timestamp"))
+            .or_insert_with(|| id.to_string());
+        if let Some(conversation) = self.conversation(account_id, conversation_id) {
+            conversation.commits.push(payloads.clone());
+            self.events.push_back(Event::Message {
+                account_id: account_id.to_string(),
+                conversation_id: conversation_id.to_string(),
+                payloads,
+            });
+        }
+        commit_id
+    }
+}
+
+/**
+ * In-memory daemon used to drive the app without dring. Every call is
+ * recorded and events are queued instead of being sent by DBus.
+ */
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<FakeState>>,
+}
+
+fn payloads(values: &[(&str, &str)]) -> HashMap<String, String> {
+    values
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Direct access to the fake daemon state
+     */
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    /**
+     * Add an account to the fake daemon
+     * @param account_id
+     */
+    pub fn with_account(&self, account_id: &str) -> &Self {
+        let mut account = Account::null();
+        account.id = account_id.to_string();
+        let mut state = self.state();
+        state.accounts.push(account);
+        // The uri of the account is its id
+        state.details.insert(
+            account_id.to_string(),
+            payloads(&[("Account.username", &format!("ring:{}", account_id))]),
+        );
+        state.conversations.entry(account_id.to_string()).or_default();
+        self
+    }
+
+    /**
+     * Add a conversation to an account
+     * @param account_id
+     * @param conversation_id
+     * @param members           uris of the members, the first one is admin
+     */
+    pub fn with_conversation(&self, account_id: &str, conversation_id: &str, members: &[&str]) -> &Self {
+        let members = members
+            .iter()
+            .enumerate()
+            .map(|(idx, uri)| {
+                let role = if idx == 0 { "admin" } else { "member" };
+                payloads(&[("uri", *uri), ("role", role)])
+            })
+            .collect();
+        self.state()
+            .conversations
+            .entry(account_id.to_string())
+            .or_default()
+            .push(FakeConversation {
+                id: conversation_id.to_string(),
+                members,
+                ..Default::default()
+            });
+        self
+    }
+
+    /**
+     * Link a device to an account, the first one is the current device
+     * @param account_id
+     * @param device_id
+     * @param name
+     */
+    pub fn with_device(&self, account_id: &str, device_id: &str, name: &str) -> &Self {
+        let mut state = self.state();
+        let details = state.details.entry(account_id.to_string()).or_default();
+        if !details.contains_key("Account.deviceID") {
+            details.insert(String::from("Account.deviceID"), device_id.to_string());
+            details.insert(String::from("Account.deviceName"), name.to_string());
+        }
+        state
+            .devices
+            .entry(account_id.to_string())
+            .or_default()
+            .insert(device_id.to_string(), name.to_string());
+        self
+    }
+
+    /**
+     * Register a name on the fake name server
+     */
+    pub fn with_name(&self, name: &str, address: &str) -> &Self {
+        self.state()
+            .names
+            .insert(name.to_string(), address.to_string());
+        self
+    }
+
+    /**
+     * Receive a text message in a conversation
+     * @return the commit id
+     */
+    pub fn receive_message(
+        &self,
+        account_id: &str,
+        conversation_id: &str,
+        author: &str,
+        body: &str,
+    ) -> String {
+        self.state().commit(
+            account_id,
+            conversation_id,
+            payloads(&[("type", "text/plain"), ("author", author), ("body", body)]),
+        )
+    }
+
+    /**
+     * Receive a file in a conversation, waiting to be accepted
+     * @return the transfer id
+     */
+    pub fn receive_file(
+        &self,
+        account_id: &str,
+        conversation_id: &str,
+        author: &str,
+        name: &str,
+        size: i64,
+    ) -> u64 {
+        let mut state = self.state();
+        let tid = state.next_id();
+        state.transfers.insert(
+            tid,
+            TransferInfo {
+                display_name: name.to_string(),
+                last_event: 4,
+                flags: 1,
+                total_size: size,
+                ..Default::default()
+            },
+        );
+        state.commit(
+            account_id,
+            conversation_id,
+            payloads(&[
+                ("type", "application/data-transfer+json"),
+                ("author", author),
+                ("tid", tid.to_string().as_str()),
+                ("displayName", name),
+            ]),
+        );
+        tid
+    }
+
+    /**
+     * Change the progress of a transfer. Like the daemon, an event is only
+     * sent when the status changes.
+     */
+    pub fn update_transfer(
+        &self,
+        account_id: &str,
+        conversation_id: &str,
+        tid: u64,
+        last_event: i32,
+        bytes_progress: i64,
+    ) {
+        let mut state = self.state();
+        let changed = match state.transfers.get_mut(&tid) {
+            Some(transfer) => {
+                transfer.bytes_progress = bytes_progress;
+                std::mem::replace(&mut transfer.last_event, last_event) != last_event
+            }
+            None => false,
+        };
+        if changed {
+            state.events.push_back(Event::DataTransferEvent(
+                account_id.to_string(),
+                conversation_id.to_string(),
+                tid,
+                last_event,
+            ));
+        }
+    }
+
+    /**
+     * Queue an event as if it was sent by the daemon
+     */
+    pub fn emit(&self, event: Event) {
+        self.state().events.push_back(event);
+    }
+
+    /**
+     * Receive a call
+     * @return the call id
+     */
+    pub fn incoming_call(&self, account_id: &str, from: &str) -> String {
+        let mut state = self.state();
+        let call_id = format!("call_{}", state.next_id());
+        state.call_events.push_back(CallEvent::Incoming {
+            account_id: account_id.to_string(),
+            call_id: call_id.clone(),
+            from: from.to_string(),
+        });
+        call_id
+    }
+
+    /**
+     * Queue a call signal as if it was sent by the daemon
+     */
+    pub fn emit_call(&self, event: CallEvent) {
+        self.state().call_events.push_back(event);
+    }
+
+    /**
+     * Get the next call signal to give to the app
+     */
+    pub fn pop_call_event(&self) -> Option<CallEvent> {
+        self.state().call_events.pop_front()
+    }
+
+    /**
+     * Get the next account signal to give to the app
+     */
+    pub fn pop_account_event(&self) -> Option<AccountEvent> {
+        self.state().account_events.pop_front()
+    }
+
+    fn check_password(&self, account_id: &String, password: &String) -> bool {
+        self.state()
+            .passwords
+            .get(account_id)
+            .map_or(true, |expected| expected == password)
+    }
+
+    fn call_state_changed(&self, call_id: &String, state: &str) {
+        self.emit_call(CallEvent::StateChanged {
+            call_id: call_id.clone(),
+            state: state.to_string(),
+            code: 0,
+        });
+    }
+
+    fn conference_infos_updated(&self, conf_id: &String) {
+        let uris = self.state().conferences.get(conf_id).cloned().unwrap_or_default();
+        // The host is the first participant, without uri
+        let infos = std::iter::once(String::new())
+            .chain(uris)
+            .map(|uri| payloads(&[("uri", &uri), ("audioLocalMuted", "false"), ("isModerator", "false")]))
+            .collect();
+        self.emit_call(CallEvent::ConferenceInfos {
+            conf_id: conf_id.clone(),
+            infos,
+        });
+    }
+
+    /**
+     * Get the next event to give to the app
+     */
+    pub fn pop_event(&self) -> Option<Event> {
+        self.state().events.pop_front()
+    }
+
+    /**
+     * Calls received by the fake daemon
+     */
+    pub fn calls(&self) -> Vec<String> {
+        self.state().calls.clone()
+    }
+
+    fn record(&self, call: String) {
+        self.state().calls.push(call);
+    }
+}
+
+impl Backend for FakeBackend {
+    fn select_jami_account(&self, _create_if_not: bool) -> Account {
+        self.state()
+            .accounts
+            .first()
+            .cloned()
+            .unwrap_or_else(Account::null)
+    }
+
+    fn get_account(&self, account_id: &str) -> Account {
+        self.state()
+            .accounts
+            .iter()
+            .find(|account| account.id == account_id)
+            .cloned()
+            .unwrap_or_else(Account::null)
+    }
+
+    fn get_account_list(&self) -> Vec<Account> {
+        self.state().accounts.clone()
+    }
+
+    fn get_account_details(&self, account_id: &String) -> HashMap<String, String> {
+        self.state()
+            .details
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set_account_details(&self, account_id: &String, details: HashMap<String, String>) {
+        self.record(format!("set_account_details {}", account_id));
+        let mut state = self.state();
+        // Like the daemon, announce the new name of the device
+        if let (Some(device_id), Some(name)) = (details.get("Account.deviceID"), details.get("Account.deviceName")) {
+            let devices = state.devices.entry(account_id.clone()).or_default();
+            if devices.get(device_id) != Some(name) {
+                devices.insert(device_id.clone(), name.clone());
+                let devices = devices.clone();
+                state.account_events.push_back(AccountEvent::KnownDevicesChanged {
+                    account_id: account_id.clone(),
+                    devices,
+                });
+            }
+        }
+        state.details.insert(account_id.clone(), details);
+    }
+
+    fn create_account(&self, details: HashMap<String, String>) -> Option<String> {
+        let alias = details.get("Account.alias").cloned().unwrap_or_default();
+        self.record(format!("create_account {}", alias));
+        let mut state = self.state();
+        let account_id = format!("account_{}", state.next_id());
+        let mut account = Account::null();
+        account.id = account_id.clone();
+        state.accounts.push(account);
+        let mut details = details;
+        details.insert(String::from("Account.username"), format!("ring:{}", account_id));
+        state.details.insert(account_id.clone(), details);
+        state.conversations.entry(account_id.clone()).or_default();
+        state.events.push_back(Event::AccountsChanged());
+        state
+            .events
+            .push_back(Event::RegistrationStateChanged(account_id.clone(), String::from("REGISTERED")));
+        Some(account_id)
+    }
+
+    fn rm_account(&self, account_id: &str) {
+        self.record(format!("rm_account {}", account_id));
+        let mut state = self.state();
+        state.accounts.retain(|account| account.id != account_id);
+        state.events.push_back(Event::AccountsChanged());
+    }
+
+    fn update_profile(&self, account_id: &String, display_name: &String, avatar: &String) -> bool {
+        self.record(format!("update_profile {} {} {}", account_id, display_name, avatar));
+        true
+    }
+
+    fn export_to_file(&self, account_id: &String, path: &String, password: &String) -> bool {
+        self.record(format!("export_to_file {} {}", account_id, path));
+        self.check_password(account_id, password) && std::fs::write(path, account_id).is_ok()
+    }
+
+    fn export_on_ring(&self, account_id: &String, password: &String) -> bool {
+        self.record(format!("export_on_ring {}", account_id));
+        let status = if self.check_password(account_id, password) { 0 } else { 1 };
+        self.state().account_events.push_back(AccountEvent::PinExported {
+            account_id: account_id.clone(),
+            status,
+            pin: if status == 0 { String::from("ABCD1234") } else { String::new() },
+        });
+        true
+    }
+
+    fn get_known_devices(&self, account_id: &String) -> HashMap<String, String> {
+        self.state().devices.get(account_id).cloned().unwrap_or_default()
+    }
+
+    fn revoke_device(&self, account_id: &String, password: &String, device_id: &String) -> bool {
+        self.record(format!("revoke_device {} {}", account_id, device_id));
+        let password_ok = self.check_password(account_id, password);
+        let mut state = self.state();
+        let devices = state.devices.entry(account_id.clone()).or_default();
+        let status = if !password_ok {
+            1
+        } else if devices.remove(device_id).is_none() {
+            2
+        } else {
+            0
+        };
+        let devices = devices.clone();
+        state.account_events.push_back(AccountEvent::DeviceRevoked {
+            account_id: account_id.clone(),
+            device_id: device_id.clone(),
+            status,
+        });
+        if status == 0 {
+            state.account_events.push_back(AccountEvent::KnownDevicesChanged {
+                account_id: account_id.clone(),
+                devices,
+            });
+        }
+        true
+    }
+
+    fn register_name(&self, account_id: &String, _password: &String, name: &String) -> bool {
+        self.record(format!("register_name {}", name));
+        let mut state = self.state();
+        let status = if state.names.contains_key(name) { 3 } else { 0 };
+        if status == 0 {
+            state.names.insert(name.clone(), account_id.clone());
+        }
+        state.account_events.push_back(AccountEvent::NameRegistered {
+            account_id: account_id.clone(),
+            status,
+            name: name.clone(),
+        });
+        true
+    }
+
+    fn lookup_name(&self, account_id: &String, _name_service: &String, name: &String) {
+        self.record(format!("lookup_name {}", name));
+        let mut state = self.state();
+        let event = match state.names.get(name) {
+            Some(address) => {
+                Event::RegisteredNameFound(account_id.clone(), 0, address.clone(), name.clone())
+            }
+            None => Event::RegisteredNameFound(account_id.clone(), 2, String::new(), name.clone()),
+        };
+        state.events.push_back(event);
+    }
+
+    fn lookup_address(&self, account_id: &String, _name_service: &String, address: &String) {
+        let mut state = self.state();
+        let name = state
+            .names
+            .iter()
+            .find(|(_, registered)| *registered == address)
+            .map(|(name, _)| name.clone());
+        if let Some(name) = name {
+            state.events.push_back(Event::RegisteredNameFound(
+                account_id.clone(),
+                0,
+                address.clone(),
+                name,
+            ));
+        }
+    }
+
+    fn add_contact(&self, account_id: &String, uri: &String) {
+        self.record(format!("add_contact {}", uri));
+        self.state()
+            .contacts
+            .entry(account_id.clone())
+            .or_default()
+            .push(uri.clone());
+    }
+
+    fn get_contacts(&self, account_id: &String) -> Vec<String> {
+        self.state().contacts.get(account_id).cloned().unwrap_or_default()
+    }
+
+    fn subscribe_presence(&self, _account_id: &String, _uri: &String, _flag: bool) {}
+
+    fn get_trust_requests(&self, account_id: &String) -> Vec<String> {
+        self.state()
+            .trust_requests
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn send_trust_request(&self, _account_id: &String, to: &String, _payloads: Vec<u8>) {
+        self.record(format!("send_trust_request {}", to));
+    }
+
+    fn accept_trust_request(&self, account_id: &String, from: &String) {
+        self.record(format!("accept_trust_request {}", from));
+        if let Some(requests) = self.state().trust_requests.get_mut(account_id) {
+            requests.retain(|request| request != from);
+        }
+    }
+
+    fn discard_trust_request(&self, account_id: &String, from: &String) {
+        self.record(format!("discard_trust_request {}", from));
+        if let Some(requests) = self.state().trust_requests.get_mut(account_id) {
+            requests.retain(|request| request != from);
+        }
+    }
+
+    fn get_conversations(&self, account_id: &String) -> Vec<String> {
+        self.state()
+            .conversations
+            .get(account_id)
+            .map(|conversations| conversations.iter().map(|c| c.id.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn get_conversations_requests(&self, account_id: &String) -> Vec<HashMap<String, String>> {
+        self.state()
+            .conversation_requests
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn get_conversation_infos(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+    ) -> HashMap<String, String> {
+        self.state()
+            .conversation(account_id, conversation_id)
+            .map(|conversation| conversation.infos.clone())
+            .unwrap_or_default()
+    }
+
+    fn update_conversation_infos(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        infos: HashMap<String, String>,
+    ) {
+        self.record(format!("update_conversation_infos {}", conversation_id));
+        let mut state = self.state();
+        if let Some(conversation) = state.conversation(account_id, conversation_id) {
+            conversation.infos.extend(infos);
+        }
+        state.commit(
+            account_id,
+            conversation_id,
+            payloads(&[("type", "application/update-profile"), ("author", account_id.as_str())]),
+        );
+    }
+
+    fn start_conversation(&self, account_id: &String) {
+        self.record(String::from("start_conversation"));
+        let mut state = self.state();
+        let conversation_id = format!("conversation_{}", state.next_id());
+        state
+            .conversations
+            .entry(account_id.clone())
+            .or_default()
+            .push(FakeConversation {
+                id: conversation_id.clone(),
+                members: vec![payloads(&[("uri", account_id.as_str()), ("role", "admin")])],
+                ..Default::default()
+            });
+        state.commit(
+            account_id,
+            &conversation_id,
+            payloads(&[("type", "initial"), ("author", account_id.as_str())]),
+        );
+        state
+            .events
+            .push_back(Event::ConversationReady(account_id.clone(), conversation_id));
+    }
+
+    fn rm_conversation(&self, account_id: &String, conversation_id: &String) -> bool {
+        self.record(format!("rm_conversation {}", conversation_id));
+        let mut state = self.state();
+        let conversations = state.conversations.entry(account_id.clone()).or_default();
+        let len = conversations.len();
+        conversations.retain(|conversation| &conversation.id != conversation_id);
+        if conversations.len() == len {
+            return false;
+        }
+        state.events.push_back(Event::ConversationRemoved(
+            account_id.clone(),
+            conversation_id.clone(),
+        ));
+        true
+    }
+
+    fn accept_request(&self, account_id: &String, conversation_id: &String) {
+        self.record(format!("accept_request {}", conversation_id));
+        let mut state = self.state();
+        if let Some(requests) = state.conversation_requests.get_mut(account_id) {
+            requests.retain(|request| request.get("id") != Some(conversation_id));
+        }
+        state
+            .conversations
+            .entry(account_id.clone())
+            .or_default()
+            .push(FakeConversation {
+                id: conversation_id.clone(),
+                ..Default::default()
+            });
+        state.events.push_back(Event::ConversationReady(
+            account_id.clone(),
+            conversation_id.clone(),
+        ));
+    }
+
+    fn decline_request(&self, account_id: &String, conversation_id: &String) {
+        self.record(format!("decline_request {}", conversation_id));
+        if let Some(requests) = self.state().conversation_requests.get_mut(account_id) {
+            requests.retain(|request| request.get("id") != Some(conversation_id));
+        }
+    }
+
+    fn get_members(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+    ) -> Vec<HashMap<String, String>> {
+        self.state()
+            .conversation(account_id, conversation_id)
+            .map(|conversation| conversation.members.clone())
+            .unwrap_or_default()
+    }
+
+    fn add_conversation_member(&self, account_id: &String, conversation_id: &String, uri: &String) {
+        self.record(format!("add_conversation_member {} {}", conversation_id, uri));
+        let mut state = self.state();
+        if let Some(conversation) = state.conversation(account_id, conversation_id) {
+            conversation
+                .members
+                .push(payloads(&[("uri", uri.as_str()), ("role", "invited")]));
+        }
+        state.commit(
+            account_id,
+            conversation_id,
+            payloads(&[("type", "member"), ("action", "add"), ("uri", uri.as_str()), ("author", account_id.as_str())]),
+        );
+    }
+
+    fn rm_conversation_member(&self, account_id: &String, conversation_id: &String, uri: &String) {
+        self.record(format!("rm_conversation_member {} {}", conversation_id, uri));
+        let mut state = self.state();
+        if let Some(conversation) = state.conversation(account_id, conversation_id) {
+            conversation
+                .members
+                .retain(|member| member.get("uri") != Some(uri));
+        }
+        state.commit(
+            account_id,
+            conversation_id,
+            payloads(&[("type", "member"), ("action", "remove"), ("uri", uri.as_str()), ("author", account_id.as_str())]),
+        );
+    }
+
+    fn send_conversation_message(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        message: &String,
+        parent: &String,
+    ) {
+        if parent.is_empty() {
+            self.record(format!("send_conversation_message {} {}", conversation_id, message));
+        } else {
+            self.record(format!("send_conversation_message {} {} (reply to {})", conversation_id, message, parent));
+        }
+        let mut commit = payloads(&[("type", "text/plain"), ("author", account_id.as_str()), ("body", message.as_str())]);
+        if !parent.is_empty() {
+            commit.insert(String::from("reply-to"), parent.clone());
+        }
+        self.state().commit(account_id, conversation_id, commit);
+    }
+
+    fn load_conversation(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        from: &String,
+        size: u32,
+    ) -> u32 {
+        let mut state = self.state();
+        let id = state.next_id() as u32;
+        let commits = match state.conversation(account_id, conversation_id) {
+            Some(conversation) => conversation.commits.clone(),
+            None => return 0,
+        };
+        // Like the daemon, send from the newest to the oldest, including `from`
+        let messages: Vec<_> = commits
+            .into_iter()
+            .rev()
+            .skip_while(|commit| !from.is_empty() && commit.get("id") != Some(from))
+            .take(if size == 0 { usize::MAX } else { size as usize })
+            .collect();
+        state.events.push_back(Event::ConversationLoaded(
+            id,
+            account_id.clone(),
+            conversation_id.clone(),
+            messages,
+        ));
+        id
+    }
+
+    fn send_file(&self, account_id: &String, conversation_id: &String, path: &String) {
+        self.record(format!("send_file {} {}", conversation_id, path));
+        let mut state = self.state();
+        let tid = state.next_id();
+        let display_name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        state.transfers.insert(
+            tid,
+            TransferInfo {
+                display_name: display_name.clone(),
+                path: path.clone(),
+                last_event: 6,
+                flags: 0,
+                total_size: std::fs::metadata(path).map_or(0, |metadata| metadata.len() as i64),
+                ..Default::default()
+            },
+        );
+        state.commit(
+            account_id,
+            conversation_id,
+            payloads(&[
+                ("type", "application/data-transfer+json"),
+                ("author", account_id.as_str()),
+                ("tid", tid.to_string().as_str()),
+                ("displayName", display_name.as_str()),
+            ]),
+        );
+    }
+
+    fn data_transfer_info(
+        &self,
+        _account_id: &String,
+        _conversation_id: &String,
+        tid: u64,
+    ) -> Option<TransferInfo> {
+        self.state().transfers.get(&tid).cloned()
+    }
+
+    fn accept_file_transfer(
+        &self,
+        account_id: &String,
+        conversation_id: &String,
+        tid: u64,
+        path: &String,
+    ) {
+        self.record(format!("accept_file_transfer {} {}", tid, path));
+        let mut state = self.state();
+        if let Some(transfer) = state.transfers.get_mut(&tid) {
+            transfer.path = path.clone();
+            transfer.last_event = 6;
+        }
+        state.events.push_back(Event::DataTransferEvent(
+            account_id.clone(),
+            conversation_id.clone(),
+            tid,
+            6,
+        ));
+    }
+
+    fn cancel_file_transfer(&self, account_id: &String, conversation_id: &String, tid: u64) {
+        self.record(format!("cancel_file_transfer {}", tid));
+        let mut state = self.state();
+        if let Some(transfer) = state.transfers.get_mut(&tid) {
+            transfer.last_event = 7;
+        }
+        state.events.push_back(Event::DataTransferEvent(
+            account_id.clone(),
+            conversation_id.clone(),
+            tid,
+            7,
+        ));
+    }
+
+    fn place_call(&self, _account_id: &String, to: &String) -> Option<String> {
+        self.record(format!("place_call {}", to));
+        let call_id = {
+            let mut state = self.state();
+            let call_id = format!("call_{}", state.next_id());
+            state.call_peers.insert(call_id.clone(), to.clone());
+            call_id
+        };
+        self.call_state_changed(&call_id, "RINGING");
+        Some(call_id)
+    }
+
+    fn accept_call(&self, call_id: &String) {
+        self.record(format!("accept_call {}", call_id));
+        self.call_state_changed(call_id, "CURRENT");
+    }
+
+    fn refuse_call(&self, call_id: &String) {
+        self.record(format!("refuse_call {}", call_id));
+        self.call_state_changed(call_id, "OVER");
+    }
+
+    fn hang_up(&self, call_id: &String) {
+        self.record(format!("hang_up {}", call_id));
+        self.call_state_changed(call_id, "OVER");
+    }
+
+    fn hold_call(&self, call_id: &String, hold: bool) {
+        self.record(format!("hold_call {} {}", call_id, hold));
+        self.call_state_changed(call_id, if hold { "HOLD" } else { "CURRENT" });
+    }
+
+    fn mute_call(&self, call_id: &String, mute: bool) {
+        self.record(format!("mute_call {} {}", call_id, mute));
+    }
+
+    fn create_conference(&self, _account_id: &String, participants: &Vec<String>) {
+        self.record(format!("create_conference {}", participants.join(",")));
+        let conf_id = {
+            let mut state = self.state();
+            let conf_id = format!("conf_{}", state.next_id());
+            state.conferences.insert(conf_id.clone(), participants.clone());
+            conf_id
+        };
+        self.emit_call(CallEvent::ConferenceCreated {
+            conf_id: conf_id.clone(),
+        });
+        self.conference_infos_updated(&conf_id);
+    }
+
+    fn add_to_conference(&self, call_id: &String, conf_id: &String) {
+        self.record(format!("add_to_conference {} {}", call_id, conf_id));
+        {
+            let mut state = self.state();
+            let peer = state.call_peers.get(call_id).cloned().unwrap_or_default();
+            state.conferences.entry(conf_id.clone()).or_default().push(peer);
+        }
+        self.conference_infos_updated(conf_id);
+    }
+
+    fn hang_up_participant(&self, conf_id: &String, uri: &String) {
+        self.record(format!("hang_up_participant {} {}", conf_id, uri));
+        if let Some(participants) = self.state().conferences.get_mut(conf_id) {
+            participants.retain(|participant| participant != uri);
+        }
+        self.conference_infos_updated(conf_id);
+    }
+
+    fn hang_up_conference(&self, conf_id: &String) {
+        self.record(format!("hang_up_conference {}", conf_id));
+        self.state().conferences.remove(conf_id);
+        self.emit_call(CallEvent::ConferenceRemoved {
+            conf_id: conf_id.clone(),
+        });
+    }
+}
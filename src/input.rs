@@ -0,0 +1,81 @@
+//! Terminal input. Pastes are read as one event thanks to bracketed paste.
+
+use crossterm::event::{Event as CEvent, KeyEvent, MouseEvent};
+
+use std::path::Path;
+
+/**
+ * Event read from the terminal
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    Key(KeyEvent),
+    /// Text pasted or dropped on the terminal
+    Paste(String),
+    Mouse(MouseEvent),
+    Resize,
+}
+
+/**
+ * Convert an event of the terminal
+ * @param event
+ * @return nothing for events the app doesn't use
+ */
+pub fn convert(event: CEvent) -> Option<InputEvent> {
+    match event {
+        CEvent::Key(key) => Some(InputEvent::Key(key)),
+        CEvent::Paste(text) => Some(InputEvent::Paste(text)),
+        CEvent::Mouse(mouse) => Some(InputEvent::Mouse(mouse)),
+        CEvent::Resize(_, _) => Some(InputEvent::Resize),
+        CEvent::FocusGained | CEvent::FocusLost => None,
+    }
+}
+
+/**
+ * Decode %XX escapes of an uri
+ * @param uri
+ */
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes.get(idx + 1..idx + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[idx], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/**
+ * Files dropped on the terminal. Terminals paste them as paths, quoted or
+ * escaped like in a shell, or as file:// uris.
+ * @param text      pasted text
+ * @return the paths, or nothing if any word isn't an existing file
+ */
+pub fn pasted_files(text: &str) -> Vec<String> {
+    let words = match crate::commands::split_args(text.trim()) {
+        Ok(words) => words,
+        Err(_) => return Vec::new(),
+    };
+    let paths: Vec<String> = words
+        .iter()
+        .map(|word| match word.strip_prefix("file://") {
+            Some(uri) => percent_decode(uri),
+            None => word.clone(),
+        })
+        .collect();
+    if paths.iter().all(|path| Path::new(path).is_absolute() && Path::new(path).is_file()) {
+        paths
+    } else {
+        Vec::new()
+    }
+}
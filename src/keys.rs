@@ -0,0 +1,301 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * Actions which can be bound to keys. Text editing keys of the input are
+ * not configurable.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    Quit,
+    Search,
+    NextChannel,
+    PreviousChannel,
+    ScrollUp,
+    ScrollDown,
+    ScrollTop,
+    ScrollBottom,
+    FocusPane,
+    OpenFile,
+    Reply,
+    SelectMessage,
+    Transfers,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Quit,
+        Action::Search,
+        Action::NextChannel,
+        Action::PreviousChannel,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::ScrollTop,
+        Action::ScrollBottom,
+        Action::FocusPane,
+        Action::OpenFile,
+        Action::Reply,
+        Action::SelectMessage,
+        Action::Transfers,
+    ];
+
+    /**
+     * Name used in the config file
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Search => "search",
+            Action::NextChannel => "next_channel",
+            Action::PreviousChannel => "previous_channel",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollTop => "scroll_top",
+            Action::ScrollBottom => "scroll_bottom",
+            Action::FocusPane => "focus_pane",
+            Action::OpenFile => "open_file",
+            Action::Reply => "reply",
+            Action::SelectMessage => "select_message",
+            Action::Transfers => "transfers",
+        }
+    }
+
+    /**
+     * Keys used if the config doesn't bind the action
+     */
+    fn default_chords(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["ctrl+c"],
+            Action::Search => &["ctrl+f"],
+            Action::NextChannel => &["alt+down"],
+            Action::PreviousChannel => &["alt+up"],
+            Action::ScrollUp => &["pageup"],
+            Action::ScrollDown => &["pagedown"],
+            Action::ScrollTop => &["home"],
+            Action::ScrollBottom => &["end"],
+            Action::FocusPane => &["backtab"],
+            Action::OpenFile => &["ctrl+o"],
+            Action::Reply => &["ctrl+r"],
+            Action::SelectMessage => &["ctrl+s"],
+            Action::Transfers => &["ctrl+t"],
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum KeyError {
+    #[error("Unknown action \"{0}\"")]
+    UnknownAction(String),
+    #[error("Invalid key \"{0}\"")]
+    InvalidKey(String),
+    #[error("Key \"{chord}\" edits the input, it can't be bound to {action}")]
+    Reserved { chord: String, action: Action },
+    #[error("Key \"{chord}\" is bound to both {first} and {second}")]
+    Conflict {
+        chord: String,
+        first: Action,
+        second: Action,
+    },
+}
+
+/**
+ * A key with its modifiers, like "ctrl+alt+left"
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /**
+     * Terminals send uppercase chars with Shift, so Shift is part of the char
+     * @param event
+     */
+    pub fn from_event(event: &KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+
+    /**
+     * If the key is used by the input editor. Actions run before the editor,
+     * so such a key would stop working (or typing a char would be impossible).
+     * @param self
+     */
+    pub fn is_reserved(&self) -> bool {
+        let ctrl = self.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = self.modifiers.contains(KeyModifiers::ALT);
+        match self.code {
+            KeyCode::Char(_) if !ctrl && !alt => true,
+            KeyCode::Char(c) if ctrl && !alt => "aewuk".contains(c),
+            KeyCode::Char(c) if alt && !ctrl => "bf".contains(c),
+            KeyCode::Enter
+            | KeyCode::Backspace
+            | KeyCode::Delete
+            | KeyCode::Tab
+            | KeyCode::Esc
+            | KeyCode::Left
+            | KeyCode::Right => true,
+            // Recall the input history, or move in the focused pane
+            KeyCode::Up | KeyCode::Down => !ctrl && !alt,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = KeyError;
+
+    fn from_str(chord: &str) -> Result<Self, KeyError> {
+        let invalid = || KeyError::InvalidKey(chord.to_string());
+        let mut parts: Vec<&str> = chord.split('+').collect();
+        // "ctrl++" binds '+'
+        if chord.ends_with("++") {
+            parts.pop();
+            *parts.last_mut().unwrap() = "+";
+        }
+        let key = parts.pop().ok_or_else(invalid)?;
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match &*modifier.to_lowercase() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match &*key.to_lowercase() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "esc" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(invalid()),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+/**
+ * One or several keys for an action in the config file
+ */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Chords {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Chords {
+    fn list(&self) -> Vec<&str> {
+        match self {
+            Chords::One(chord) => vec![&chord[..]],
+            Chords::Many(chords) => chords.iter().map(|chord| &chord[..]).collect(),
+        }
+    }
+}
+
+/**
+ * Keys bound to actions
+ */
+#[derive(Debug)]
+pub struct KeyBindings {
+    bindings: HashMap<KeyChord, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::new(&HashMap::new()).unwrap()
+    }
+}
+
+impl KeyBindings {
+    /**
+     * Build bindings from the config. Actions which are not in the config keep
+     * their default keys, an empty list unbinds an action.
+     * @param config    keys per action
+     * @return the bindings, or an error if a key is invalid, edits the input or is bound twice
+     */
+    pub fn new(config: &HashMap<String, Chords>) -> Result<Self, KeyError> {
+        if let Some(name) = config
+            .keys()
+            .find(|name| !Action::ALL.iter().any(|action| action.name() == name.as_str()))
+        {
+            return Err(KeyError::UnknownAction(name.clone()));
+        }
+        let mut bindings = HashMap::new();
+        for action in Action::ALL {
+            let chords = match config.get(action.name()) {
+                Some(chords) => chords.list(),
+                None => action.default_chords().to_vec(),
+            };
+            for chord in chords {
+                let key = chord.parse::<KeyChord>()?;
+                if key.is_reserved() {
+                    return Err(KeyError::Reserved {
+                        chord: chord.to_string(),
+                        action: *action,
+                    });
+                }
+                if let Some(first) = bindings.insert(key, *action) {
+                    if first != *action {
+                        return Err(KeyError::Conflict {
+                            chord: chord.to_string(),
+                            first,
+                            second: *action,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    /**
+     * Get the action bound to a key event
+     * @param self
+     * @param event
+     */
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings.get(&KeyChord::from_event(event)).copied()
+    }
+}
@@ -0,0 +1,21 @@
+pub mod accounts;
+pub mod app;
+pub mod appdata;
+pub mod backfill;
+pub mod backend;
+pub mod calls;
+pub mod commands;
+pub mod completion;
+pub mod config;
+pub mod editor;
+#[cfg(any(test, feature = "fake-backend"))]
+pub mod fake_backend;
+pub mod input;
+pub mod keys;
+pub mod notifications;
+pub mod search;
+pub mod theme;
+pub mod transfers;
+pub mod ui;
+pub mod util;
+pub mod wizard;
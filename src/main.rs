@@ -1,16 +1,15 @@
-mod app;
-mod appdata;
-mod ui;
-mod util;
-
-use jami_rs::{Event, Jami};
-use app::App;
+use jami_cli::app::App;
+use jami_cli::appdata::AppData;
+use jami_cli::backend::JamiBackend;
+use jami_cli::accounts;
+use jami_cli::calls;
+use jami_cli::config::Config;
+use jami_cli::input;
+use jami_cli::ui;
+use jami_rs::Jami;
 
 use crossterm::{
-    event::{
-        DisableMouseCapture, EnableMouseCapture, Event as CEvent, EventStream, KeyCode,
-        KeyModifiers,
-    },
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, EventStream},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -21,6 +20,8 @@ use tui::{backend::CrosstermBackend, Terminal};
 use std::io::Write;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 #[derive(Debug, StructOpt)]
 struct Args {
@@ -31,6 +32,8 @@ struct Args {
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::from_args();
+    // Report config errors before the terminal is taken
+    let config = Config::load()?;
 
     enable_raw_mode()?;
     let _raw_mode_guard = scopeguard::guard((), |_| {
@@ -38,114 +41,88 @@ async fn main() -> anyhow::Result<()> {
     });
 
     let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
 
-    let (tx, mut rx) = tokio::sync::mpsc::channel(100);
-    tokio::spawn({
-        let mut tx = tx.clone();
-        async move {
-            let mut reader = EventStream::new().fuse();
-            while let Some(event) = reader.next().await {
-                match event {
-                    Ok(CEvent::Key(key)) => tx.send(Event::Input(key)).await.unwrap(),
-                    Ok(CEvent::Resize(_, _)) => tx.send(Event::Resize).await.unwrap(),
-                    _ => (),
-                }
+    let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut reader = EventStream::new().fuse();
+        while let Some(event) = reader.next().await {
+            let event = match event.ok().and_then(input::convert) {
+                Some(event) => event,
+                None => continue,
+            };
+            if input_tx.send(event).is_err() {
+                return;
             }
         }
     });
 
+    let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+
     let stop = Arc::new(AtomicBool::new(false));
     let stop_cloned = stop.clone();
 
     tokio::spawn(async move { Jami::handle_events(tx, stop_cloned).await });
 
+    // jami-rs doesn't forward call signals, listen to them on dbus
+    let (call_tx, mut call_rx) = tokio::sync::mpsc::unbounded_channel();
+    let stop_cloned = stop.clone();
+    thread::spawn(move || calls::listen_signals(call_tx, stop_cloned));
+    // Same for account signals (PIN export...)
+    let (account_tx, mut account_rx) = tokio::sync::mpsc::unbounded_channel();
+    let stop_cloned = stop.clone();
+    thread::spawn(move || accounts::listen_signals(account_tx, stop_cloned));
+    // Refresh the duration of calls and the progress of transfers
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let mut app = App::try_new(args.verbose)?;
+    let mut app = App::try_new(args.verbose, Box::new(JamiBackend), config)?;
+    // Cached data is shown and usable while the daemon is read
+    let (sync_tx, mut sync_rx) = tokio::sync::mpsc::unbounded_channel();
+    if let Some((account_id, unified)) = app.sync_request() {
+        tokio::task::spawn_blocking(move || {
+            if let Some(snapshot) = AppData::fetch_from_jami(&JamiBackend, &account_id, unified) {
+                let _ = sync_tx.send(snapshot);
+            }
+        });
+    }
     loop {
         terminal.draw(|f| ui::draw(f, &mut app))?;
-        match rx.recv().await {
-            Some(Event::Input(event)) => match event.code {
-                KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                    break;
-                }
-                KeyCode::Left => app.on_left(),
-                KeyCode::Up => app.on_up(),
-                KeyCode::Right => app.on_right(),
-                KeyCode::Down => app.on_down(),
-                code => app.on_key(code),
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => app.handle_event(event).await,
+                None => break,
             },
-            Some(Event::Message {
-                account_id,
-                conversation_id,
-                payloads,
-            }) => {
-                app.on_message(&account_id, &conversation_id, payloads)
-                    .await;
-            }
-            Some(Event::Resize) => {
-                // will just redraw the app
-            }
-            Some(Event::RegistrationStateChanged(account_id, registration_state)) => {
-                app.on_registration_state_changed(&account_id, &registration_state)
-                    .await;
-            }
-            Some(Event::ConversationReady(account_id, conversation_id)) => {
-                app.on_conversation_ready(account_id, conversation_id).await;
-            }
-            Some(Event::ConversationRemoved(account_id, conversation_id)) => {
-                app.on_conversation_removed(account_id, conversation_id)
-                    .await;
-            }
-            Some(Event::ConversationRequest(account_id, conversation_id)) => {
-                app.on_conversation_request(account_id, conversation_id)
-                    .await;
-            }
-            Some(Event::RegisteredNameFound(account_id, status, address, name)) => {
-                app.on_registered_name_found(account_id, status, address, name)
-                    .await;
-            }
-            Some(Event::ConversationLoaded(id, account_id, conversation_id, messages)) => {
-                app.on_conversation_loaded(id, account_id, conversation_id, messages)
-                    .await;
-            }
-            Some(Event::DataTransferEvent(account_id, conversation_id, tid, status)) => {
-                app.on_data_transfer_event(account_id, conversation_id, tid, status)
-                    .await;
-            }
-            Some(Event::ProfileReceived(account_id, from, path)) => {
-                app.on_profile_received(&account_id, &from, &path).await;
-            }
-            Some(Event::IncomingTrustRequest(account_id, from, payload, receive_time)) => {
-                app.on_incoming_trust_request(&account_id, &from, payload, receive_time)
-                    .await;
-            }
-            Some(Event::MemberPresenceChanged(account_id, uri, flag)) => {
-                app.on_member_presence_changed(&account_id, &uri, flag)
-                    .await;
-            }
-            Some(Event::AccountsChanged()) => {
-                app.on_accounts_changed().await;
-            }
-            None => {
-                break;
-            }
+            Some(event) = input_rx.recv() => app.handle_input(event).await,
+            Some(event) = call_rx.recv() => app.handle_call_event(event),
+            Some(event) = account_rx.recv() => app.handle_account_event(event),
+            Some(snapshot) = sync_rx.recv() => app.on_synced(snapshot),
+            _ = ticks.tick() => app.on_tick(),
+        }
+        // Clipboard requests and other escape sequences
+        for sequence in app.terminal_output.drain(..) {
+            write!(terminal.backend_mut(), "{}", sequence)?;
         }
+        terminal.backend_mut().flush()?;
+        app.flush_cache();
         if app.should_quit {
             break;
         }
     }
 
+    app.save_cache();
+
     // Stop handle_events
     stop.store(true, Ordering::Relaxed);
 
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )
     .unwrap();
     terminal.show_cursor().unwrap();
@@ -0,0 +1,242 @@
+use crate::commands::split_args;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+use std::thread;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum NotificationError {
+    #[error("Invalid mode \"{0}\", expected all, mentions or none")]
+    InvalidMode(String),
+    #[error("Invalid desktop notifications \"{0}\", expected osc9, osc777 or none")]
+    InvalidDesktop(String),
+    #[error("Invalid command \"{0}\"")]
+    InvalidCommand(String),
+}
+
+/**
+ * Which messages of a channel notify the user
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NotifyMode {
+    All,
+    /// Only messages mentioning the user
+    Mentions,
+    None,
+}
+
+impl NotifyMode {
+    /**
+     * Parse a mode like "mentions"
+     * @param mode
+     */
+    pub fn parse(mode: &str) -> Result<Self, NotificationError> {
+        match mode.to_lowercase().as_str() {
+            "all" => Ok(NotifyMode::All),
+            "mentions" => Ok(NotifyMode::Mentions),
+            "none" => Ok(NotifyMode::None),
+            _ => Err(NotificationError::InvalidMode(mode.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotifyMode::All => "all",
+            NotifyMode::Mentions => "mentions",
+            NotifyMode::None => "none",
+        }
+    }
+}
+
+/**
+ * Escape sequence used for desktop notifications, depending on the terminal
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DesktopNotification {
+    None,
+    /// iTerm2, kitty, Windows Terminal…
+    Osc9,
+    /// rxvt-unicode, foot, VTE based terminals with the notify extension
+    Osc777,
+}
+
+/**
+ * [notifications] section of the config
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Ring the terminal bell
+    bell: Option<bool>,
+    /// "osc9", "osc777" or "none"
+    desktop: Option<String>,
+    /// Command run for each notification, like "notify-send {title} {body}"
+    command: Option<String>,
+    /// Default mode of the channels
+    mode: Option<String>,
+    /// Modes per conversation id
+    channels: HashMap<String, String>,
+    /// Words highlighted and notified like mentions
+    keywords: Vec<String>,
+}
+
+/**
+ * How the user is notified of new messages
+ */
+#[derive(Clone, Debug)]
+pub struct NotificationPolicy {
+    pub bell: bool,
+    pub desktop: DesktopNotification,
+    /// Program and arguments, {title} and {body} are replaced in each argument
+    pub command: Vec<String>,
+    pub mode: NotifyMode,
+    pub channels: HashMap<String, NotifyMode>,
+    pub keywords: Vec<String>,
+}
+
+impl Default for NotificationPolicy {
+    fn default() -> Self {
+        Self {
+            bell: true,
+            desktop: DesktopNotification::None,
+            command: Vec::new(),
+            mode: NotifyMode::All,
+            channels: HashMap::new(),
+            keywords: Vec::new(),
+        }
+    }
+}
+
+impl NotificationPolicy {
+    /**
+     * Build the policy from the config
+     * @param config
+     */
+    pub fn new(config: &NotificationsConfig) -> Result<Self, NotificationError> {
+        let desktop = match config.desktop.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("none") => DesktopNotification::None,
+            Some("osc9") => DesktopNotification::Osc9,
+            Some("osc777") => DesktopNotification::Osc777,
+            Some(_) => {
+                return Err(NotificationError::InvalidDesktop(
+                    config.desktop.clone().unwrap_or_default(),
+                ))
+            }
+        };
+        let command = match &config.command {
+            Some(command) => split_args(command)
+                .map_err(|_| NotificationError::InvalidCommand(command.clone()))?,
+            None => Vec::new(),
+        };
+        let mode = match &config.mode {
+            Some(mode) => NotifyMode::parse(mode)?,
+            None => NotifyMode::All,
+        };
+        let mut channels = HashMap::new();
+        for (conversation_id, mode) in &config.channels {
+            channels.insert(conversation_id.clone(), NotifyMode::parse(mode)?);
+        }
+        Ok(Self {
+            bell: config.bell.unwrap_or(true),
+            desktop,
+            command,
+            mode,
+            channels,
+            keywords: config.keywords.clone(),
+        })
+    }
+
+    /**
+     * Mode of a channel
+     * @param self
+     * @param conversation_id
+     * @param chosen            mode chosen with /notify, which wins over the config
+     */
+    pub fn mode(&self, conversation_id: &str, chosen: Option<NotifyMode>) -> NotifyMode {
+        chosen
+            .or_else(|| self.channels.get(conversation_id).copied())
+            .unwrap_or(self.mode)
+    }
+
+    /**
+     * Escape sequences to write to the terminal for a notification
+     * @param self
+     * @param title
+     * @param body
+     */
+    pub fn terminal_output(&self, title: &str, body: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        if self.bell {
+            output.push(String::from("\x07"));
+        }
+        match self.desktop {
+            DesktopNotification::None => {}
+            DesktopNotification::Osc9 => {
+                output.push(format!("\x1b]9;{}: {}\x07", sanitize(title), sanitize(body)));
+            }
+            DesktopNotification::Osc777 => output.push(format!(
+                "\x1b]777;notify;{};{}\x07",
+                sanitize(title).replace(';', ","),
+                sanitize(body)
+            )),
+        }
+        output
+    }
+
+    /**
+     * Run the command of the config, without waiting for it
+     * @param self
+     * @param title
+     * @param body
+     */
+    pub fn run_command(&self, title: &str, body: &str) -> io::Result<()> {
+        let args: Vec<String> = self
+            .command
+            .iter()
+            .map(|arg| arg.replace("{title}", title).replace("{body}", body))
+            .collect();
+        let (program, args) = match args.split_first() {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+        let mut child = Command::new(program).args(args).spawn()?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/**
+ * Remove control chars, which would end the escape sequence
+ * @param text
+ */
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/**
+ * If a text mentions one of the names of the user, like "@alice" or "Alice"
+ * @param text
+ * @param names     uri, registered name, display name, keywords…
+ */
+pub fn mentions(text: &str, names: &[String]) -> bool {
+    let text = text.to_lowercase();
+    names
+        .iter()
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_lowercase())
+        .any(|name| {
+            text.match_indices(&name).any(|(idx, _)| {
+                // Whole words only
+                let before = text[..idx].chars().last();
+                let after = text[idx + name.len()..].chars().next();
+                !matches!(before, Some(c) if c.is_alphanumeric())
+                    && !matches!(after, Some(c) if c.is_alphanumeric())
+            })
+        })
+}
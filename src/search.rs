@@ -0,0 +1,208 @@
+use crate::appdata::APP_INFO;
+use crate::util::Message;
+
+use app_dirs::{app_dir, AppDataType};
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection};
+
+/**
+ * A message matching a search
+ */
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub account_id: String,
+    pub conversation_id: String,
+    pub id: String,
+    pub author: String,
+    pub body: String,
+    pub arrived_at: DateTime<Utc>,
+}
+
+/**
+ * How far the history of a conversation was indexed by the backfill
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackfillMark {
+    /// Newest commit indexed, the high-water mark. Empty if never indexed
+    pub newest: String,
+    /// Oldest commit indexed, where the history goes on
+    pub oldest: String,
+    /// If the history was indexed down to the first commit
+    pub complete: bool,
+}
+
+/**
+ * Index of text messages of the conversations, received or read from their
+ * history, to search without loading conversations from the daemon
+ */
+pub struct SearchIndex {
+    conn: Connection,
+}
+
+impl SearchIndex {
+    /**
+     * Open the index stored in the cache directory
+     */
+    pub fn open() -> anyhow::Result<Self> {
+        let dir = app_dir(AppDataType::UserCache, &APP_INFO, "")?;
+        let conn = Connection::open(dir.join("search.sqlite"))?;
+        SearchIndex::with_connection(conn)
+    }
+
+    /**
+     * Open an index which is not persisted
+     */
+    pub fn in_memory() -> anyhow::Result<Self> {
+        SearchIndex::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS messages (
+                account TEXT NOT NULL,
+                conversation TEXT NOT NULL,
+                id TEXT NOT NULL,
+                author TEXT NOT NULL,
+                body TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                PRIMARY KEY (account, conversation, id)
+            );
+            CREATE INDEX IF NOT EXISTS messages_by_date ON messages (account, timestamp);
+            CREATE TABLE IF NOT EXISTS backfill (
+                account TEXT NOT NULL,
+                conversation TEXT NOT NULL,
+                newest TEXT NOT NULL,
+                oldest TEXT NOT NULL,
+                complete INTEGER NOT NULL,
+                PRIMARY KEY (account, conversation)
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /**
+     * Add a message to the index (messages without commit id are ignored)
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param message
+     */
+    pub fn add(
+        &self,
+        account_id: &str,
+        conversation_id: &str,
+        message: &Message,
+    ) -> anyhow::Result<()> {
+        if message.id.is_empty() {
+            return Ok(());
+        }
+        self.conn.execute(
+            "INSERT OR IGNORE INTO messages (account, conversation, id, author, body, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                account_id,
+                conversation_id,
+                message.id,
+                message.from,
+                message.text(),
+                message.arrived_at.timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /**
+     * Add messages of a conversation at once
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param messages
+     */
+    pub fn add_all(&self, account_id: &str, conversation_id: &str, messages: &[Message]) -> anyhow::Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        for message in messages {
+            self.add(account_id, conversation_id, message)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /**
+     * How far the history of a conversation was indexed
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @return an empty mark if it never was
+     */
+    pub fn backfill_mark(&self, account_id: &str, conversation_id: &str) -> anyhow::Result<BackfillMark> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT newest, oldest, complete FROM backfill WHERE account = ?1 AND conversation = ?2")?;
+        let mut marks = stmt.query_map(params![account_id, conversation_id], |row| {
+            Ok(BackfillMark {
+                newest: row.get(0)?,
+                oldest: row.get(1)?,
+                complete: row.get(2)?,
+            })
+        })?;
+        Ok(marks.next().transpose()?.unwrap_or_default())
+    }
+
+    /**
+     * Remember how far the history of a conversation was indexed
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     * @param mark
+     */
+    pub fn set_backfill_mark(&self, account_id: &str, conversation_id: &str, mark: &BackfillMark) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO backfill (account, conversation, newest, oldest, complete)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![account_id, conversation_id, mark.newest, mark.oldest, mark.complete],
+        )?;
+        Ok(())
+    }
+
+    /**
+     * Search messages of an account containing a text, newest first
+     * @param self
+     * @param account_id
+     * @param query         text to search (case insensitive)
+     * @param limit         max number of hits
+     * @return the hits
+     */
+    pub fn search(
+        &self,
+        account_id: &str,
+        query: &str,
+        limit: u32,
+    ) -> anyhow::Result<Vec<SearchHit>> {
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let mut stmt = self.conn.prepare(
+            "SELECT account, conversation, id, author, body, timestamp FROM messages
+             WHERE account = ?1 AND body LIKE ?2 ESCAPE '\\'
+             ORDER BY timestamp DESC LIMIT ?3",
+        )?;
+        let hits = stmt
+            .query_map(params![account_id, pattern, limit], |row| {
+                let timestamp: i64 = row.get(5)?;
+                Ok(SearchHit {
+                    account_id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    id: row.get(2)?,
+                    author: row.get(3)?,
+                    body: row.get(4)?,
+                    arrived_at: Utc.timestamp(timestamp, 0),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
+}
@@ -0,0 +1,327 @@
+use serde::Deserialize;
+use thiserror::Error;
+use tui::style::{Color, Modifier, Style};
+
+use std::env;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ThemeError {
+    #[error("Invalid color \"{0}\"")]
+    InvalidColor(String),
+}
+
+/**
+ * Colors used for authors
+ */
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Palette {
+    #[serde(rename = "16")]
+    Basic,
+    #[serde(rename = "256")]
+    Indexed,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+}
+
+const BASIC_COLORS: &[Color] = &[
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+];
+
+/// Colors of the 256 colors palette readable on dark and light backgrounds
+const INDEXED_COLORS: &[u8] = &[
+    33, 37, 40, 41, 45, 69, 73, 77, 99, 105, 111, 135, 141, 147, 160, 165, 166, 172, 178, 197,
+    203, 209, 214, 220,
+];
+
+/**
+ * Style as written in the config, like { fg = "black", bg = "#c0c0c0", bold = true }
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    reversed: bool,
+    dim: bool,
+}
+
+impl StyleConfig {
+    fn to_style(&self) -> Result<Style, ThemeError> {
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(parse_color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(parse_color(bg)?);
+        }
+        let modifiers = [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.underlined, Modifier::UNDERLINED),
+            (self.reversed, Modifier::REVERSED),
+            (self.dim, Modifier::DIM),
+        ];
+        for (enabled, modifier) in modifiers.iter() {
+            if *enabled {
+                style = style.add_modifier(*modifier);
+            }
+        }
+        Ok(style)
+    }
+}
+
+/**
+ * [theme] section of the config. Missing styles keep their default value.
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Only use bold, reversed, etc. Enabled by default if NO_COLOR is set
+    monochrome: Option<bool>,
+    palette: Option<Palette>,
+    /// Colors for authors, replacing the palette
+    author_colors: Vec<String>,
+    text: Option<StyleConfig>,
+    time: Option<StyleConfig>,
+    selection: Option<StyleConfig>,
+    present: Option<StyleConfig>,
+    absent: Option<StyleConfig>,
+    border: Option<StyleConfig>,
+    focused_border: Option<StyleConfig>,
+    unread_separator: Option<StyleConfig>,
+    quote: Option<StyleConfig>,
+    call: Option<StyleConfig>,
+    mention: Option<StyleConfig>,
+}
+
+/**
+ * Styles used to draw the interface
+ */
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub text: Style,
+    pub time: Style,
+    /// Selected channel
+    pub selection: Style,
+    pub present: Style,
+    pub absent: Style,
+    pub border: Style,
+    /// Border of the pane receiving Up and Down keys
+    pub focused_border: Style,
+    pub unread_separator: Style,
+    /// Excerpt of the message replied to
+    pub quote: Style,
+    /// Calls in progress, above the input
+    pub call: Style,
+    /// Messages mentioning the user, and channels with unread mentions
+    pub mention: Style,
+    pub monochrome: bool,
+    pub palette: Palette,
+    pub author_colors: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: Style::default().fg(Color::White),
+            time: Style::default().fg(Color::Yellow),
+            selection: Style::default().fg(Color::Black).bg(Color::Gray),
+            present: Style::default().fg(Color::White),
+            absent: Style::default().fg(Color::Red),
+            border: Style::default(),
+            focused_border: Style::default().fg(Color::Cyan),
+            unread_separator: Style::default(),
+            quote: Style::default().fg(Color::DarkGray),
+            call: Style::default().fg(Color::Black).bg(Color::Green),
+            mention: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            monochrome: false,
+            palette: Palette::Basic,
+            author_colors: Vec::new(),
+        }
+    }
+}
+
+impl Theme {
+    /**
+     * Theme without colors
+     */
+    pub fn monochrome() -> Self {
+        Self {
+            text: Style::default(),
+            time: Style::default(),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            present: Style::default(),
+            absent: Style::default().add_modifier(Modifier::DIM),
+            border: Style::default(),
+            focused_border: Style::default().add_modifier(Modifier::BOLD),
+            unread_separator: Style::default().add_modifier(Modifier::BOLD),
+            quote: Style::default().add_modifier(Modifier::DIM | Modifier::ITALIC),
+            call: Style::default().add_modifier(Modifier::REVERSED),
+            mention: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            monochrome: true,
+            ..Theme::default()
+        }
+    }
+
+    /**
+     * Build the theme from the config
+     * @param config
+     */
+    pub fn new(config: &ThemeConfig) -> Result<Self, ThemeError> {
+        let monochrome = config
+            .monochrome
+            .unwrap_or_else(|| env::var_os("NO_COLOR").is_some());
+        let mut theme = if monochrome {
+            Theme::monochrome()
+        } else {
+            Theme::default()
+        };
+        let styles = [
+            (&config.text, &mut theme.text),
+            (&config.time, &mut theme.time),
+            (&config.selection, &mut theme.selection),
+            (&config.present, &mut theme.present),
+            (&config.absent, &mut theme.absent),
+            (&config.border, &mut theme.border),
+            (&config.focused_border, &mut theme.focused_border),
+            (&config.unread_separator, &mut theme.unread_separator),
+            (&config.quote, &mut theme.quote),
+            (&config.call, &mut theme.call),
+            (&config.mention, &mut theme.mention),
+        ];
+        for (style_config, style) in styles {
+            if let Some(style_config) = style_config {
+                *style = style_config.to_style()?;
+            }
+        }
+        if let Some(palette) = config.palette {
+            theme.palette = palette;
+        }
+        theme.author_colors = config
+            .author_colors
+            .iter()
+            .map(|color| parse_color(color))
+            .collect::<Result<_, _>>()?;
+        if theme.monochrome {
+            theme.strip_colors();
+        }
+        Ok(theme)
+    }
+
+    /**
+     * Keep only modifiers, for styles set by the user in monochrome mode
+     */
+    fn strip_colors(&mut self) {
+        for style in [
+            &mut self.text,
+            &mut self.time,
+            &mut self.selection,
+            &mut self.present,
+            &mut self.absent,
+            &mut self.border,
+            &mut self.focused_border,
+            &mut self.unread_separator,
+            &mut self.quote,
+            &mut self.call,
+            &mut self.mention,
+        ] {
+            style.fg = None;
+            style.bg = None;
+        }
+    }
+
+    /**
+     * Randomly but deterministically choose a style for an author
+     * @param self
+     * @param author
+     */
+    pub fn author(&self, author: &str) -> Style {
+        if self.monochrome {
+            return Style::default().add_modifier(Modifier::BOLD);
+        }
+        let color = if !self.author_colors.is_empty() {
+            self.author_colors[hash_index(author, self.author_colors.len())]
+        } else {
+            match self.palette {
+                Palette::Basic => BASIC_COLORS[hash_index(author, BASIC_COLORS.len())],
+                Palette::Indexed => {
+                    Color::Indexed(INDEXED_COLORS[hash_index(author, INDEXED_COLORS.len())])
+                }
+                Palette::TrueColor => hue_to_rgb(hash_index(author, 360) as f32),
+            }
+        };
+        Style::default().fg(color)
+    }
+}
+
+fn hash_index(name: &str, len: usize) -> usize {
+    name.bytes().map(|b| usize::from(b) % len).sum::<usize>() % len
+}
+
+/**
+ * Bright color with a given hue, in degrees
+ */
+fn hue_to_rgb(hue: f32) -> Color {
+    let (saturation, lightness) = (0.6, 0.6);
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f32).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = lightness - chroma / 2.0;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let to_u8 = |v: f32| ((v + m) * 255.0).round() as u8;
+    Color::Rgb(to_u8(r), to_u8(g), to_u8(b))
+}
+
+/**
+ * Parse a color name, a 256 colors index or "#rrggbb"
+ * @param color
+ */
+pub fn parse_color(color: &str) -> Result<Color, ThemeError> {
+    let invalid = || ThemeError::InvalidColor(color.to_string());
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        let component = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| invalid());
+        return Ok(Color::Rgb(component(0)?, component(2)?, component(4)?));
+    }
+    if let Ok(idx) = color.parse::<u8>() {
+        return Ok(Color::Indexed(idx));
+    }
+    let color = match &*color.to_lowercase().replace(|c: char| c == '_' || c == '-', "") {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return Err(invalid()),
+    };
+    Ok(color)
+}
@@ -0,0 +1,267 @@
+use serde::Deserialize;
+use thiserror::Error;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_DOWNLOAD_DIR: &str = "{downloads}/Jami";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TransferError {
+    #[error("Invalid size \"{0}\"")]
+    InvalidSize(String),
+}
+
+/**
+ * Size as written in the config, like 1048576 or "1 MiB"
+ */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum SizeConfig {
+    Bytes(u64),
+    Text(String),
+}
+
+/**
+ * [transfers.auto_accept] section of the config
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AutoAcceptConfig {
+    /// Accept files sent by contacts of the account
+    contacts: bool,
+    /// Accept files sent in these conversations
+    conversations: Vec<String>,
+    max_size: Option<SizeConfig>,
+    /// Extensions ("pdf") or MIME types ("image/*") to accept, everything if empty
+    allow: Vec<String>,
+    /// Extensions or MIME types never accepted
+    deny: Vec<String>,
+}
+
+/**
+ * [transfers] section of the config
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransfersConfig {
+    /// Template like "~/Downloads/{conversation}"
+    download_dir: Option<String>,
+    /// Templates per conversation id
+    download_dirs: HashMap<String, String>,
+    auto_accept: AutoAcceptConfig,
+}
+
+/**
+ * Where incoming files go, and which ones are accepted without asking
+ */
+#[derive(Clone, Debug)]
+pub struct TransferPolicy {
+    pub download_dir: String,
+    pub download_dirs: HashMap<String, String>,
+    pub from_contacts: bool,
+    pub conversations: Vec<String>,
+    pub max_size: Option<u64>,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl Default for TransferPolicy {
+    fn default() -> Self {
+        Self {
+            download_dir: String::from(DEFAULT_DOWNLOAD_DIR),
+            download_dirs: HashMap::new(),
+            from_contacts: false,
+            conversations: Vec::new(),
+            max_size: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+}
+
+impl TransferPolicy {
+    /**
+     * Build the policy from the config
+     * @param config
+     */
+    pub fn new(config: &TransfersConfig) -> Result<Self, TransferError> {
+        let max_size = match &config.auto_accept.max_size {
+            None => None,
+            Some(SizeConfig::Bytes(bytes)) => Some(*bytes),
+            Some(SizeConfig::Text(size)) => Some(parse_size(size)?),
+        };
+        Ok(Self {
+            download_dir: config
+                .download_dir
+                .clone()
+                .unwrap_or_else(|| String::from(DEFAULT_DOWNLOAD_DIR)),
+            download_dirs: config.download_dirs.clone(),
+            from_contacts: config.auto_accept.contacts,
+            conversations: config.auto_accept.conversations.clone(),
+            max_size,
+            allow: config.auto_accept.allow.clone(),
+            deny: config.auto_accept.deny.clone(),
+        })
+    }
+
+    /**
+     * If an incoming file can be accepted without asking the user
+     * @param self
+     * @param conversation_id
+     * @param from_contact      if the author is a contact of the account
+     * @param name              name of the file
+     * @param size              size of the file, in bytes
+     */
+    pub fn should_accept(&self, conversation_id: &str, from_contact: bool, name: &str, size: i64) -> bool {
+        let trusted = (self.from_contacts && from_contact)
+            || self.conversations.iter().any(|id| id == conversation_id);
+        if !trusted || size < 0 {
+            return false;
+        }
+        if matches!(self.max_size, Some(max_size) if size as u64 > max_size) {
+            return false;
+        }
+        if self.deny.iter().any(|pattern| file_matches(name, pattern)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|pattern| file_matches(name, pattern))
+    }
+
+    /**
+     * Directory where files of a conversation are downloaded
+     * @param self
+     * @param conversation_id
+     * @param conversation_name     replaces {conversation} in the template
+     * @return None if the template can't be expanded
+     */
+    pub fn download_dir(&self, conversation_id: &str, conversation_name: &str) -> Option<PathBuf> {
+        let template = self
+            .download_dirs
+            .get(conversation_id)
+            .unwrap_or(&self.download_dir);
+        let mut dir = template
+            .replace("{conversation_id}", conversation_id)
+            .replace("{conversation}", &conversation_name.replace('/', "_"));
+        if dir.contains("{downloads}") {
+            let downloads = dirs::download_dir()?;
+            dir = dir.replace("{downloads}", downloads.to_str()?);
+        }
+        if let Some(rest) = dir.strip_prefix('~') {
+            dir = format!("{}{}", dirs::home_dir()?.to_str()?, rest);
+        }
+        Some(PathBuf::from(dir))
+    }
+}
+
+/**
+ * Name chosen by the sender of a file, without any directory so that it
+ * can't be written outside of the download directory
+ * @param name
+ * @return None if nothing is left, like for ".."
+ */
+pub fn safe_file_name(name: &str) -> Option<&str> {
+    let file_name = Path::new(name).file_name()?.to_str()?;
+    match file_name {
+        "" | "." | ".." => None,
+        file_name => Some(file_name),
+    }
+}
+
+/**
+ * Path for a file in a directory which doesn't overwrite an existing file:
+ * name, then name_1, name_2…
+ * @param dir
+ * @param name
+ */
+pub fn free_path(dir: &Path, name: &str) -> PathBuf {
+    (0..)
+        .map(|idx| match idx {
+            0 => dir.join(name),
+            i => dir.join(format!("{}_{}", name, i)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/**
+ * Parse a size like 512, "20 KiB", "1.5MB" or "2G". Units are powers of 1024.
+ * @param size
+ */
+pub fn parse_size(size: &str) -> Result<u64, TransferError> {
+    let invalid = || TransferError::InvalidSize(size.to_string());
+    let size = size.trim();
+    let unit_pos = size
+        .find(|c: char| c.is_alphabetic())
+        .unwrap_or_else(|| size.len());
+    let value: f64 = size[..unit_pos].trim().parse().map_err(|_| invalid())?;
+    let multiplier: u64 = match size[unit_pos..].to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+    if value < 0.0 {
+        return Err(invalid());
+    }
+    Ok((value * multiplier as f64) as u64)
+}
+
+/**
+ * If a file matches an extension ("pdf", ".pdf"), a MIME type ("image/png") or every type
+ * of a family ("image/" followed by a star)
+ * @param name
+ * @param pattern
+ */
+fn file_matches(name: &str, pattern: &str) -> bool {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let pattern = pattern.to_lowercase();
+    match pattern.find('/') {
+        Some(_) => {
+            let mime = guess_mime(&extension);
+            match pattern.strip_suffix("/*") {
+                Some(prefix) => mime.split('/').next() == Some(prefix),
+                None => mime == pattern,
+            }
+        }
+        None => pattern.trim_start_matches('.') == extension,
+    }
+}
+
+/**
+ * MIME type of common extensions
+ * @param extension     lowercase, without the dot
+ */
+fn guess_mime(extension: &str) -> &'static str {
+    match extension {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "ogg" | "opus" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "exe" | "msi" => "application/x-msdownload",
+        "sh" => "application/x-sh",
+        _ => "application/octet-stream",
+    }
+}
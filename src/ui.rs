@@ -1,15 +1,90 @@
-use crate::App;
-use crate::util::Role;
+use crate::app::{App, Focus};
+use crate::calls::{format_duration, Call, CallState, Conference};
+use crate::editor;
+use crate::notifications;
+use crate::util::{excerpt, format_size, ChannelType, MemberAction, MessageKind, Role, TransferStatus};
+use crate::wizard::{AccountWizard, PasswordPrompt, Progress};
 
 use chrono::Timelike;
+use jami_rs::ProfileManager;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Corner, Direction, Layout, Rect};
-use tui::style::{Color, Style};
+use tui::style::Style;
 use tui::text::{Span, Spans, Text};
-use tui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use tui::Frame;
 use unicode_width::UnicodeWidthStr;
 
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/**
+ * Where the last frame drew things, to find what the mouse clicks on
+ */
+#[derive(Clone, Debug, Default)]
+pub struct ClickAreas {
+    pub channels: Rect,
+    /// Index of each channel shown, channels of collapsed accounts are skipped
+    pub channel_rows: Vec<usize>,
+    /// First row shown, the list scrolls to keep the selection visible
+    pub channels_offset: usize,
+    /// Selected row of the list, which keeps its offset between frames
+    pub channels_state: ListState,
+    pub messages: Rect,
+    /// First row, last row + 1 and index in the channel of each message shown
+    pub message_rows: Vec<(u16, u16, usize)>,
+    pub members: Rect,
+    /// Hashes of the members shown, from the top
+    pub member_hashes: Vec<String>,
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
+
+/**
+ * Row in the list drawn in an area with borders
+ */
+fn list_row(area: Rect, column: u16, row: u16) -> Option<usize> {
+    if contains(area, column, row) && row > area.y && row + 1 < area.y + area.height {
+        Some((row - area.y - 1) as usize)
+    } else {
+        None
+    }
+}
+
+impl ClickAreas {
+    /**
+     * Index of the channel at a position
+     */
+    pub fn channel_at(&self, column: u16, row: u16) -> Option<usize> {
+        list_row(self.channels, column, row).and_then(|row| self.channel_rows.get(self.channels_offset + row).copied())
+    }
+
+    pub fn in_messages(&self, column: u16, row: u16) -> bool {
+        contains(self.messages, column, row)
+    }
+
+    /**
+     * Index in the selected channel of the message at a position
+     */
+    pub fn message_at(&self, column: u16, row: u16) -> Option<usize> {
+        if !self.in_messages(column, row) {
+            return None;
+        }
+        self.message_rows
+            .iter()
+            .find(|(top, bottom, _)| row >= *top && row < *bottom)
+            .map(|(_, _, msg_idx)| *msg_idx)
+    }
+
+    /**
+     * Hash of the member at a position
+     */
+    pub fn member_at(&self, column: u16, row: u16) -> Option<&String> {
+        list_row(self.members, column, row).and_then(|row| self.member_hashes.get(row))
+    }
+}
+
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let has_members = app
         .data
@@ -17,8 +92,13 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .state
         .selected()
         .and_then(|idx| app.data.channels.items.get(idx))
-        .map(|channel| !channel.members.is_empty())
+        .map(|channel| !channel.members.is_empty() || selected_conference(app).is_some())
         .unwrap_or(false);
+    let account_id = match app.data.channels.state.selected().and_then(|idx| app.data.channels.items.get(idx)) {
+        Some(channel) => app.data.account_of(channel),
+        None => app.data.account.id.clone(),
+    };
+    let mention_names = app.mention_names(&account_id);
 
     let chunks = match has_members {
         false => Layout::default()
@@ -32,18 +112,29 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     };
 
     let channel_list_width = chunks[0].width.saturating_sub(2) as usize;
-    let channels: Vec<ListItem> = app
-        .data
-        .channels
-        .items
+    let channel_rows: Vec<usize> = (0..app.data.channels.items.len())
+        .filter(|idx| !app.is_hidden(*idx))
+        .collect();
+    let channels: Vec<ListItem> = channel_rows
         .iter()
-        .map(|channel| {
-            let unread_messages_label = if channel.unread_messages != 0 {
-                format!(" ({})", channel.unread_messages)
-            } else {
-                String::new()
+        .map(|idx| {
+            let channel = &app.data.channels.items[*idx];
+            let unread_messages_label = match (channel.unread_messages, channel.unread_mentions) {
+                (0, _) => String::new(),
+                (unread, 0) => format!(" ({})", unread),
+                (unread, mentions) => format!(" ({}, @{})", unread, mentions),
+            };
+            let channel_name = match channel.channel_type {
+                // Account headers, in unified mode
+                ChannelType::Generated if app.data.unified => {
+                    let arrow = match app.collapsed_accounts.contains(&channel.account_id) {
+                        true => "▸",
+                        false => "▾",
+                    };
+                    format!("{} {}", arrow, channel.bestname())
+                }
+                _ => channel.bestname(),
             };
-            let channel_name = channel.bestname();
             let label = format!("{}{}", channel_name, unread_messages_label);
             let label_width = label.width();
             let label = if label.width() <= channel_list_width || unread_messages_label.is_empty() {
@@ -56,68 +147,402 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                 }
                 format!("{}{}", &channel_name[0..end], unread_messages_label)
             };
-            ListItem::new(vec![Spans::from(Span::raw(label))])
+            let item = ListItem::new(vec![Spans::from(Span::raw(label))]);
+            if channel.unread_mentions > 0 {
+                item.style(app.config.theme.mention)
+            } else {
+                item
+            }
         })
         .collect();
     let channels = List::new(channels)
-        .block(Block::default().borders(Borders::ALL).title("Channels"))
-        .highlight_style(Style::default().fg(Color::Black).bg(Color::Gray));
-    f.render_stateful_widget(channels, chunks[0], &mut app.data.channels.state);
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style(app, Focus::Channels))
+                .title("Channels"),
+        )
+        .highlight_style(app.config.theme.selection);
+    let selected_row = app
+        .data
+        .channels
+        .state
+        .selected()
+        .and_then(|selected| channel_rows.iter().position(|idx| *idx == selected));
+    // Same scrolling as the list widget, which doesn't expose its offset
+    let list_height = chunks[0].height.saturating_sub(2) as usize;
+    let offset = app
+        .click_areas
+        .channels_offset
+        .min(channel_rows.len().saturating_sub(1));
+    app.click_areas.channels = chunks[0];
+    app.click_areas.channels_offset = match selected_row.unwrap_or(0) {
+        selected if selected < offset => selected,
+        selected if list_height > 0 && selected >= offset + list_height => selected + 1 - list_height,
+        _ => offset,
+    };
+    app.click_areas.channel_rows = channel_rows;
+    app.click_areas.channels_state.select(selected_row);
+    f.render_stateful_widget(channels, chunks[0], &mut app.click_areas.channels_state);
 
-    draw_chat(f, app, chunks[1]);
+    draw_chat(f, app, chunks[1], &mention_names);
+    app.click_areas.members = Rect::default();
+    app.click_areas.member_hashes.clear();
     if has_members {
-        draw_members(f, app, chunks[2]);
+        let members_area = match selected_conference(app) {
+            Some(conference) => {
+                let side = Layout::default()
+                    .constraints(
+                        [
+                            Constraint::Min(0),
+                            Constraint::Length(conference.participants.len() as u16 + 2),
+                        ]
+                        .as_ref(),
+                    )
+                    .direction(Direction::Vertical)
+                    .split(chunks[2]);
+                draw_conference(f, app, conference, side[1]);
+                side[0]
+            }
+            None => chunks[2],
+        };
+        app.click_areas.member_hashes = draw_members(f, app, members_area);
+        app.click_areas.members = members_area;
+    }
+    if let Some(wizard) = &app.wizard {
+        draw_wizard(f, app, wizard);
+    }
+    if let Some(prompt) = &app.password_prompt {
+        draw_password_prompt(f, app, prompt);
     }
 }
 
-fn draw_chat<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
-    let text_width = area.width.saturating_sub(2) as usize;
-    let lines = app
-        .data
-        .input
-        .chars()
-        .enumerate()
-        .fold(Vec::new(), |mut lines, (idx, c)| {
-            if idx % text_width == 0 {
-                lines.push(String::new())
+/**
+ * Password asked by an account command, centered over the other panes
+ */
+fn draw_password_prompt<B: Backend>(f: &mut Frame<B>, app: &App, prompt: &PasswordPrompt) {
+    let theme = &app.config.theme;
+    let size = f.size();
+    let width = size.width.min(60);
+    let height = size.height.min(7);
+    let area = Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    );
+    let field = &prompt.field;
+    let label = format!("{}: ", field.label);
+    let value = field.shown();
+    let before: String = value.chars().take(field.cursor).collect();
+    let cursor = (area.x + 1 + (label.width() + before.width()) as u16, area.y + 1);
+    let lines = vec![
+        Spans::from(vec![Span::raw(label), Span::styled(format!("{} ", value), theme.selection)]),
+        Spans::from(""),
+        Spans::from("Password of the account, empty if it has none"),
+        Spans::from(""),
+        Spans::from("Enter: confirm, Esc: cancel"),
+    ];
+    let paragraph = Paragraph::new(Text::from(lines))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.focused_border)
+                .title(prompt.title()),
+        )
+        .style(theme.text)
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+    f.set_cursor(cursor.0.min((area.x + area.width).saturating_sub(2)), cursor.1);
+}
+
+/**
+ * Form of the account wizard, centered over the other panes
+ */
+fn draw_wizard<B: Backend>(f: &mut Frame<B>, app: &App, wizard: &AccountWizard) {
+    let theme = &app.config.theme;
+    let size = f.size();
+    let width = size.width.min(72);
+    let height = size.height.min(14);
+    let area = Rect::new(
+        size.x + (size.width - width) / 2,
+        size.y + (size.height - height) / 2,
+        width,
+        height,
+    );
+    let step = &wizard.steps[wizard.step];
+    let mut lines = Vec::new();
+    let mut cursor = None;
+    match &wizard.progress {
+        Progress::Editing => {
+            lines.push(Spans::from(format!("{} ({}/{})", step.title, wizard.step + 1, wizard.steps.len())));
+            lines.push(Spans::from(""));
+            for (idx, field) in step.fields.iter().enumerate() {
+                let label = format!("{}: ", field.label);
+                let mut spans = vec![Span::raw(label.clone())];
+                let value = field.shown();
+                if idx == wizard.focused {
+                    let before: String = value.chars().take(field.cursor).collect();
+                    cursor = Some((
+                        area.x + 1 + (label.width() + before.width()) as u16,
+                        area.y + 1 + lines.len() as u16,
+                    ));
+                    spans.push(Span::styled(format!("{} ", value), theme.selection));
+                } else {
+                    spans.push(Span::raw(value));
+                }
+                if field.key == "username" {
+                    spans.push(Span::raw(format!("  {}", wizard.name_status.label())));
+                }
+                lines.push(Spans::from(spans));
             }
-            lines.last_mut().unwrap().push(c);
-            lines
-        });
-    let num_input_lines = lines.len().max(1);
-    let input: Vec<Spans> = lines.into_iter().map(Spans::from).collect();
-    let extra_cursor_line = if app.data.input_cursor > 0 && app.data.input_cursor % text_width == 0
-    {
-        1
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(step.help));
+            if let Some(error) = &wizard.error {
+                lines.push(Spans::from(format!("✗ {}", error)));
+            }
+            lines.push(Spans::from(""));
+            let last = wizard.step + 1 == wizard.steps.len();
+            lines.push(Spans::from(match (wizard.step, last) {
+                (0, true) => "Enter: create, Tab: next field, Esc: cancel",
+                (0, false) => "Enter: next step, Tab: next field, Esc: cancel",
+                (_, true) => "Enter: create, Tab: next field, Esc: previous step",
+                (_, false) => "Enter: next step, Tab: next field, Esc: previous step",
+            }));
+        }
+        Progress::Working(text) => {
+            lines.push(Spans::from(text.as_str()));
+            lines.push(Spans::from(""));
+            lines.push(Spans::from("Esc: hide, the account is still added"));
+        }
+        Progress::Registering => {
+            lines.push(Spans::from(format!("Registering {}…", wizard.value("username"))));
+            lines.push(Spans::from(""));
+            lines.push(Spans::from("Esc: hide, the username is still registered"));
+        }
+        Progress::Done(text) => {
+            lines.push(Spans::from(text.as_str()));
+            lines.push(Spans::from(""));
+            lines.push(Spans::from("Enter: switch to the account"));
+        }
+        Progress::Failed(text) => {
+            lines.push(Spans::from(format!("✗ {}", text)));
+            lines.push(Spans::from(""));
+            lines.push(Spans::from("Enter: edit the form, Esc: close"));
+        }
+    }
+    let paragraph = Paragraph::new(Text::from(lines))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.focused_border)
+                .title(wizard.title()),
+        )
+        .style(theme.text)
+        .wrap(Wrap { trim: false });
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+    if let Some((x, y)) = cursor {
+        f.set_cursor(x.min((area.x + area.width).saturating_sub(2)), y);
+    }
+}
+
+/**
+ * Conference started from the selected channel
+ */
+fn selected_conference(app: &App) -> Option<&Conference> {
+    let channel = app
+        .data
+        .channels
+        .state
+        .selected()
+        .and_then(|idx| app.data.channels.items.get(idx))?;
+    app.conferences
+        .iter()
+        .find(|conference| conference.conversation_id == channel.id)
+}
+
+/**
+ * Highlight the border of the pane receiving Up and Down keys
+ */
+fn border_style(app: &App, pane: Focus) -> Style {
+    if app.focus == pane {
+        app.config.theme.focused_border
     } else {
-        0
+        app.config.theme.border
+    }
+}
+
+fn draw_chat<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect, mention_names: &[String]) {
+    let text_width = area.width.saturating_sub(2) as usize;
+    let (lines, (cursor_x, cursor_y)) =
+        editor::layout(&app.data.input, app.data.input_cursor, text_width);
+    let title = if let Some(status) = &app.status {
+        status.clone()
+    } else if app.focus == Focus::Messages {
+        String::from("Message: ↑↓ select, y copy, o open, p payload, r reply, a author, Esc back")
+    } else if app.focus == Focus::Transfers {
+        String::from("Transfers: ↑↓ select, a accept, d destination, c cancel, o open, Esc back")
+    } else if app.focus == Focus::Devices {
+        String::from("Devices: ↑↓ select, n rename this device, r revoke, Esc back")
+    } else if let Some(reply) = &app.reply {
+        let prefix = format!("Reply to {} (Esc to cancel): ", displayed_name(&reply.author, true));
+        let body = excerpt(&reply.body, text_width.saturating_sub(prefix.width()));
+        prefix + &body
+    } else {
+        String::from("Input")
     };
+    // Long inputs can't take more than half of the screen
+    let max_input_lines = (area.height as usize / 2).saturating_sub(2).max(1);
+    let num_input_lines = lines.len().min(max_input_lines);
+    let input_scroll = (cursor_y + 1).saturating_sub(num_input_lines);
+    let input: Vec<Spans> = lines.into_iter().map(Spans::from).collect();
 
     let chunks = Layout::default()
         .constraints(
             [
                 Constraint::Min(0),
-                Constraint::Length(num_input_lines as u16 + 2 + extra_cursor_line),
+                Constraint::Length(app.calls.len() as u16),
+                Constraint::Length(num_input_lines as u16 + 2),
             ]
             .as_ref(),
         )
         .direction(Direction::Vertical)
         .split(area);
 
-    draw_messages(f, app, chunks[0]);
+    app.click_areas.messages = chunks[0];
+    if app.focus == Focus::Transfers {
+        draw_transfers(f, app, chunks[0]);
+        app.click_areas.message_rows.clear();
+    } else if app.focus == Focus::Devices {
+        draw_devices(f, app, chunks[0]);
+        app.click_areas.message_rows.clear();
+    } else {
+        app.click_areas.message_rows = draw_messages(f, app, chunks[0], mention_names);
+    }
+    if !app.calls.is_empty() {
+        let calls: Vec<Spans> = app
+            .calls
+            .iter()
+            .map(|call| Spans::from(call_label(app, call)))
+            .collect();
+        let calls = Paragraph::new(Text::from(calls)).style(app.config.theme.call);
+        f.render_widget(calls, chunks[1]);
+    }
 
     let input = Paragraph::new(Text::from(input))
-        .block(Block::default().borders(Borders::ALL).title("Input"));
-    f.render_widget(input, chunks[1]);
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style(app, Focus::Input))
+                .title(title),
+        )
+        .scroll((input_scroll as u16, 0));
+    f.render_widget(input, chunks[2]);
     f.set_cursor(
-        // Put cursor past the end of the input text
-        chunks[1].x + ((app.data.input_cursor as u16) % text_width as u16) + 1,
-        // Move one line down, from the border to the input line
-        chunks[1].y + (app.data.input_cursor as u16 / (text_width as u16)) + 1,
+        // Skip the border
+        chunks[2].x + cursor_x as u16 + 1,
+        chunks[2].y + (cursor_y - input_scroll) as u16 + 1,
     );
 }
 
-fn draw_messages<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+fn draw_devices<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let theme = &app.config.theme;
+    let items: Vec<ListItem> = app
+        .devices
+        .items
+        .iter()
+        .map(|device| {
+            let current = if device.current { " (this device)" } else { "" };
+            ListItem::new(format!("{} - {}{}", device.name, device.id, current))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.focused_border)
+                .title(format!("Devices ({})", app.devices.items.len())),
+        )
+        .style(theme.text)
+        .highlight_style(theme.selection);
+    f.render_stateful_widget(list, area, &mut app.devices.state);
+}
+
+fn draw_transfers<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let theme = &app.config.theme;
+    let items: Vec<ListItem> = app
+        .transfers
+        .items
+        .iter()
+        .map(|transfer| {
+            let conversation = app
+                .data
+                .channels
+                .items
+                .iter()
+                .find(|channel| channel.id == transfer.conversation_id)
+                .map(|channel| channel.bestname())
+                .unwrap_or_default();
+            let state = match transfer.status {
+                TransferStatus::Ongoing => format!(
+                    "{} {} / {}",
+                    progress_bar(transfer.bytes, transfer.total, PROGRESS_BAR_WIDTH),
+                    format_size(transfer.bytes),
+                    format_size(transfer.total)
+                ),
+                status => format!("{} ({})", status.label(), format_size(transfer.total)),
+            };
+            let line = format!("{} {} - {} - {}", transfer.tid, transfer.name, conversation, state);
+            ListItem::new(line)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.focused_border)
+                .title(format!("Transfers ({})", app.transfers.items.len())),
+        )
+        .style(theme.text)
+        .highlight_style(theme.selection);
+    f.render_stateful_widget(list, area, &mut app.transfers.state);
+}
+
+/**
+ * One line describing a call, like "📞 alice — 01:23 (muted)"
+ */
+fn call_label(app: &App, call: &Call) -> String {
+    let peer = app.data.profile_manager.display_name(&call.peer);
+    let state = match &call.state {
+        CallState::Incoming => {
+            return format!("📞 Incoming call from {}: /accept-call or /refuse-call", peer);
+        }
+        CallState::Connecting => String::from("connecting…"),
+        CallState::Ringing => String::from("ringing…"),
+        CallState::Current => format_duration(call.duration()),
+        CallState::Hold => format!("on hold {}", format_duration(call.duration())),
+        CallState::Busy => String::from("busy"),
+        CallState::Failure => String::from("failed"),
+        CallState::Over => String::from("ended"),
+        CallState::Other(state) => state.to_lowercase(),
+    };
+    let muted = if call.muted { " (muted)" } else { "" };
+    format!("📞 {} — {}{}", peer, state, muted)
+}
+
+/**
+ * Draw the messages of the selected channel, the newest at the bottom
+ * @param mention_names     words highlighted in messages
+ * @return rows of the messages shown, cf ClickAreas::message_rows
+ */
+fn draw_messages<B: Backend>(
+    f: &mut Frame<B>,
+    app: &App,
+    area: Rect,
+    mention_names: &[String],
+) -> Vec<(u16, u16, usize)> {
     let messages = app
         .data
         .channels
@@ -145,17 +570,32 @@ fn draw_messages<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
         "" => "Messages",
         d => d,
     };
+    let scroll = app
+        .data
+        .channels
+        .state
+        .selected()
+        .and_then(|idx| app.data.channels.items.get(idx))
+        .map(|channel| channel.scroll)
+        .unwrap_or(0);
+    let room_description = match scroll {
+        0 => String::from(room_description),
+        scroll => format!("{} [↓ {} more]", room_description, scroll),
+    };
 
     let width = area.width - 2; // without borders
     let max_lines = area.height;
 
-    let time_style = Style::default().fg(Color::Yellow);
+    let theme = &app.config.theme;
+    let time_style = theme.time;
+    let all_messages = messages;
     let messages = messages
         .iter()
+        .enumerate()
         .rev()
-        // we can't show more messages atm and don't have messages navigation
+        .skip(scroll)
         .take(max_lines as usize)
-        .map(|msg| {
+        .map(|(msg_idx, msg)| {
             let arrived_at = msg.arrived_at.with_timezone(&chrono::Local);
 
             let time = Span::styled(
@@ -165,46 +605,97 @@ fn draw_messages<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
             let from = displayed_name(&msg.from, true);
             let from = Span::styled(
                 textwrap::indent(&from, &" ".repeat(max_username_width - from.width())),
-                Style::default().fg(user_color(&msg.from)),
+                theme.author(&msg.from),
             );
             let delimeter = Span::from(": ");
 
             let prefix_width = (time.width() + from.width() + delimeter.width()) as u16;
             let indent = " ".repeat(prefix_width.into());
-            let message = msg.message.clone();
+            let message = message_text(&app.data.profile_manager, &msg.kind);
+            let text_style = if notifications::mentions(msg.text(), mention_names) {
+                theme.mention
+            } else {
+                Style::default()
+            };
             let lines =
                 textwrap::wrap_iter(message.as_str(), width.saturating_sub(prefix_width).into());
 
-            let spans: Vec<Spans> = lines
-                .enumerate()
-                .map(|(idx, line)| {
-                    let res = if idx == 0 {
-                        vec![
-                            time.clone(),
-                            from.clone(),
-                            delimeter.clone(),
-                            Span::from(line.to_string()),
-                        ]
-                    } else {
-                        vec![Span::from(format!("{}{}", indent, line))]
-                    };
-                    Spans::from(res)
-                })
-                .collect();
-            spans
+            let mut spans: Vec<Spans> = Vec::new();
+            if !msg.reply_to.is_empty() {
+                let quote_width = (width as usize).saturating_sub(prefix_width as usize + 2);
+                let quote = match all_messages.iter().find(|original| original.id == msg.reply_to) {
+                    Some(original) => {
+                        let author = displayed_name(&original.from, true);
+                        let body_width = quote_width.saturating_sub(author.width() + 2);
+                        format!("↳ {}: {}", author, excerpt(&message_text(&app.data.profile_manager, &original.kind), body_width))
+                    }
+                    None => String::from("↳ reply to a message not loaded"),
+                };
+                spans.push(Spans::from(vec![
+                    Span::from(indent.clone()),
+                    Span::styled(quote, theme.quote),
+                ]));
+            }
+            spans.extend(lines.enumerate().map(|(idx, line)| {
+                let res = if idx == 0 {
+                    vec![
+                        time.clone(),
+                        from.clone(),
+                        delimeter.clone(),
+                        Span::styled(line.to_string(), text_style),
+                    ]
+                } else {
+                    vec![Span::from(indent.clone()), Span::styled(line.to_string(), text_style)]
+                };
+                Spans::from(res)
+            }));
+            (msg_idx, spans)
         });
 
-    let mut items: Vec<_> = messages.map(|s| ListItem::new(Text::from(s))).collect();
+    let (mut item_messages, mut items): (Vec<Option<usize>>, Vec<_>) = messages
+        .enumerate()
+        .map(|(idx, (msg_idx, s))| {
+            let item = ListItem::new(Text::from(s));
+            // The message at the bottom is the one used by actions like reply
+            if idx == 0 && app.focus == Focus::Messages {
+                (Some(msg_idx), item.style(theme.selection))
+            } else {
+                (Some(msg_idx), item)
+            }
+        })
+        .unzip();
 
     if let Some(selected_idx) = app.data.channels.state.selected() {
-        let unread_messages = app.data.channels.items[selected_idx].unread_messages;
+        let unread_messages = app.data.channels.items[selected_idx]
+            .unread_messages
+            .saturating_sub(scroll);
         if unread_messages > 0 && unread_messages < items.len() {
             let prefix_width = max_username_width + 8;
             let new_message_line = "-".repeat(prefix_width)
                 + "new messages"
                 + &"-".repeat((width as usize).saturating_sub(prefix_width));
 
-            items.insert(unread_messages, ListItem::new(Span::from(new_message_line)));
+            items.insert(
+                unread_messages,
+                ListItem::new(Span::styled(new_message_line, theme.unread_separator)),
+            );
+            item_messages.insert(unread_messages, None);
+        }
+    }
+
+    // Items are drawn from the bottom while they fit entirely
+    let inner_height = area.height.saturating_sub(2);
+    let bottom = area.y + 1 + inner_height;
+    let mut rows = Vec::new();
+    let mut height = 0;
+    for (item, msg_idx) in items.iter().zip(&item_messages) {
+        let item_height = item.height() as u16;
+        if height + item_height > inner_height {
+            break;
+        }
+        height += item_height;
+        if let Some(msg_idx) = msg_idx {
+            rows.push((bottom - height, bottom - height + item_height, *msg_idx));
         }
     }
 
@@ -212,14 +703,20 @@ fn draw_messages<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
         .block(
             Block::default()
                 .title(room_description)
-                .borders(Borders::ALL),
+                .borders(Borders::ALL)
+                .border_style(border_style(app, Focus::Messages)),
         )
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .start_corner(Corner::BottomLeft);
     f.render_widget(list, area);
+    rows
 }
 
-fn draw_members<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+/**
+ * Draw the members of the selected channel
+ * @return hashes of the members shown, from the top
+ */
+fn draw_members<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) -> Vec<String> {
     let members = app
         .data
         .channels
@@ -229,10 +726,17 @@ fn draw_members<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
         .map(|channel| &channel.members[..])
         .unwrap_or(&[]);
 
-    let max_lines = area.height;
+    let max_lines = area.height.saturating_sub(2);
+    let shown: Vec<String> = members
+        .iter()
+        .rev()
+        .take(max_lines as usize)
+        .map(|member| member.hash.clone())
+        .collect();
 
-    let present_style = Style::default().fg(Color::White);
-    let absent_style = Style::default().fg(Color::Red);
+    let theme = &app.config.theme;
+    let present_style = theme.present;
+    let absent_style = theme.absent;
     let members = members
         .iter()
         .rev()
@@ -241,6 +745,7 @@ fn draw_members<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
         .map(|member| {
             let present = app.data.tracked_presences.get(&member.hash);
             let style = match present {
+                _ if app.highlighted_member.as_ref() == Some(&member.hash) => theme.selection,
                 Some(true) => present_style,
                 _ => absent_style,
             };
@@ -264,23 +769,110 @@ fn draw_members<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
     let list = List::new(items)
         .block(
             Block::default()
-                .borders(Borders::ALL),
+                .borders(Borders::ALL)
+                .border_style(theme.border),
         )
-        .style(Style::default().fg(Color::White))
+        .style(theme.text)
         .start_corner(Corner::TopLeft);
     f.render_widget(list, area);
+    shown
+}
+
+fn draw_conference<B: Backend>(f: &mut Frame<B>, app: &App, conference: &Conference, area: Rect) {
+    let theme = &app.config.theme;
+    let items: Vec<_> = conference
+        .participants
+        .iter()
+        .map(|participant| {
+            let name = if participant.uri.is_empty() {
+                app.data.account.get_display_name()
+            } else {
+                app.data.profile_manager.display_name(&participant.uri)
+            };
+            let mic = if participant.muted { "🔇" } else { "🎙" };
+            let moderator = if participant.moderator { " 👑" } else { "" };
+            ListItem::new(Text::from(format!("{} {}{}", mic, name, moderator)))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.border)
+                .title("Conference"),
+        )
+        .style(theme.text);
+    f.render_widget(list, area);
+}
+
+/**
+ * Text shown for a message
+ * @param profile_manager   to show names of members
+ * @param kind
+ */
+pub fn message_text(profile_manager: &ProfileManager, kind: &MessageKind) -> String {
+    match kind {
+        MessageKind::Text(text) => text.clone(),
+        MessageKind::Initial { invited: None } => String::from("--> started the conversation"),
+        MessageKind::Initial { invited: Some(uri) } => format!(
+            "--> started a private conversation with {}",
+            profile_manager.display_name(uri)
+        ),
+        MessageKind::Call { duration_secs: 0 } => String::from("❌ Call missed"),
+        MessageKind::Call { duration_secs } => format!("📞 Call with duration: {} secs", duration_secs),
+        MessageKind::Transfer {
+            tid,
+            name,
+            status: TransferStatus::Ongoing,
+            bytes,
+            total,
+            rate,
+            ..
+        } => format!(
+            "<File transfer {}: {} {} {} / {}, {}/s>",
+            tid,
+            name,
+            progress_bar(*bytes, *total, PROGRESS_BAR_WIDTH),
+            format_size(*bytes),
+            format_size(*total),
+            format_size(*rate as i64)
+        ),
+        // Outgoing transfers have a path from the start, only link it once complete
+        MessageKind::Transfer {
+            status: TransferStatus::Finished,
+            path: Some(path),
+            ..
+        } => format!("<file://{}>", path),
+        MessageKind::Transfer {
+            tid, name, status, ..
+        } => format!("<New file transfer with id: {} - {} - {}>", tid, name, status.label()),
+        MessageKind::Member { action, uri } => {
+            let name = profile_manager.display_name(uri);
+            match action {
+                MemberAction::Add => format!("--> | {} has been added", name),
+                MemberAction::Join => format!("--> | {} joins the conversation", name),
+                MemberAction::Remove => format!("<-- | {} leaves the conversation", name),
+                MemberAction::Ban => format!("<-- | {} was banned from the conversation", name),
+            }
+        }
+        MessageKind::Unknown(payloads) => format!("{:?}", payloads),
+    }
 }
 
-// Randomly but deterministically choose a color for a username
-fn user_color(username: &str) -> Color {
-    use Color::*;
-    const COLORS: &[Color] = &[Red, Green, Yellow, Blue, Magenta, Cyan, Gray];
-    let idx = username
-        .bytes()
-        .map(|b| usize::from(b) % COLORS.len())
-        .sum::<usize>()
-        % COLORS.len();
-    COLORS[idx]
+/**
+ * Progress bar like [#####-----]
+ * @param done
+ * @param total
+ * @param width     number of cells between the brackets
+ */
+fn progress_bar(done: i64, total: i64, width: usize) -> String {
+    let filled = if total > 0 {
+        (done.clamp(0, total) as usize * width) / total as usize
+    } else {
+        0
+    };
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled))
 }
 
 fn displayed_name(name: &str, first_name_only: bool) -> &str {
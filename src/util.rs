@@ -1,7 +1,17 @@
+use crate::notifications::NotifyMode;
+
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
+use std::io;
+use std::process::{Command, Stdio};
 use tui::widgets::ListState;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Number of messages per channel kept in the local cache
+const MAX_CACHED_MESSAGES: usize = 200;
+/// Number of sent inputs remembered per channel
+const MAX_INPUT_HISTORY: usize = 100;
 
 /**
  * To store current invites
@@ -40,32 +50,141 @@ pub enum ChannelType {
     Group,
     Invite,
     TrustRequest(String),
+    Search,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Channel {
     /// Either phone number or group id
     pub id: String,
+    /// Account owning the channel
+    #[serde(default)]
+    pub account_id: String,
     pub title: String,
     pub description: String,
     pub channel_type: ChannelType,
     pub members: Vec<Member>,
+    #[serde(serialize_with = "serialize_recent_messages")]
     pub messages: Vec<Message>,
     #[serde(default)]
     pub unread_messages: usize,
+    /// Unread messages mentioning the user
+    #[serde(default)]
+    pub unread_mentions: usize,
+    /// Mode chosen with /notify, else the one of the config
+    #[serde(default)]
+    pub notify: Option<NotifyMode>,
+    /// Number of messages hidden below the view (0 means following the last message)
+    #[serde(skip)]
+    pub scroll: usize,
+    /// Oldest commit received from the daemon, used to load older messages
+    #[serde(skip)]
+    pub oldest_commit: String,
+    #[serde(skip)]
+    pub loading_history: bool,
+    #[serde(skip)]
+    pub history_complete: bool,
+    /// Inputs sent in this channel, oldest first
+    #[serde(default)]
+    pub input_history: Vec<String>,
 }
 
 impl Channel {
     pub fn new(id: &String, channel_type: ChannelType) -> Channel {
         Channel {
             id: id.clone(),
+            account_id: String::new(),
             title: String::new(),
             description: String::new(),
             members: Vec::new(),
             channel_type,
             messages: Vec::new(),
             unread_messages: 0,
+            unread_mentions: 0,
+            notify: None,
+            scroll: 0,
+            oldest_commit: String::new(),
+            loading_history: false,
+            history_complete: false,
+            input_history: Vec::new(),
+        }
+    }
+
+    /**
+     * If the channel is the conversation of an account. Channels without
+     * account match every account.
+     * @param self
+     * @param account_id
+     * @param conversation_id
+     */
+    pub fn is(&self, account_id: &str, conversation_id: &str) -> bool {
+        self.id == conversation_id && (self.account_id.is_empty() || self.account_id == account_id)
+    }
+
+    /**
+     * Forget history navigation state before reloading the last messages
+     * @param self
+     */
+    pub fn reset_history(&mut self) {
+        self.scroll = 0;
+        self.oldest_commit = String::new();
+        self.loading_history = false;
+        self.history_complete = false;
+    }
+
+    /**
+     * Scroll towards older messages
+     * @param self
+     * @param count     number of messages to scroll
+     * @return true if the oldest loaded message was reached
+     */
+    pub fn scroll_up(&mut self, count: usize) -> bool {
+        let max_scroll = self.messages.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add(count).min(max_scroll);
+        self.scroll == max_scroll
+    }
+
+    /**
+     * Scroll towards newer messages
+     * @param self
+     * @param count     number of messages to scroll
+     */
+    pub fn scroll_down(&mut self, count: usize) {
+        self.scroll = self.scroll.saturating_sub(count);
+    }
+
+    /**
+     * Remember a sent input, to recall it later
+     * @param self
+     * @param input
+     */
+    pub fn push_input_history(&mut self, input: &String) {
+        if self.input_history.last() != Some(input) {
+            self.input_history.push(input.clone());
+        }
+        if self.input_history.len() > MAX_INPUT_HISTORY {
+            self.input_history.remove(0);
+        }
+    }
+
+    /**
+     * Index of the message at the bottom of the view
+     * @param self
+     */
+    pub fn bottom_message(&self) -> Option<usize> {
+        self.messages.len().checked_sub(self.scroll + 1)
+    }
+
+    /**
+     * Find a loaded message by its commit id
+     * @param self
+     * @param id
+     */
+    pub fn message_by_id(&self, id: &str) -> Option<&Message> {
+        if id.is_empty() {
+            return None;
         }
+        self.messages.iter().find(|msg| msg.id == id)
     }
 
     /**
@@ -104,28 +223,205 @@ impl Channel {
     }
 }
 
+// Only store the last messages of a channel in the cache
+fn serialize_recent_messages<S>(messages: &[Message], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let start = messages.len().saturating_sub(MAX_CACHED_MESSAGES);
+    messages[start..].serialize(serializer)
+}
+
+/**
+ * Status of a file transfer, from the codes of DataTransferEvent
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TransferStatus {
+    NotDownloaded,
+    AwaitingPeer,
+    AwaitingHost,
+    Ongoing,
+    Finished,
+    ClosedByHost,
+    ClosedByPeer,
+    UnjoinablePeer,
+    TimeoutExpired,
+}
+
+impl TransferStatus {
+    /**
+     * @param code      last event of the transfer
+     */
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            3 => TransferStatus::AwaitingPeer,
+            4 => TransferStatus::AwaitingHost,
+            5 => TransferStatus::Ongoing,
+            6 => TransferStatus::Finished,
+            7 => TransferStatus::ClosedByHost,
+            8 => TransferStatus::ClosedByPeer,
+            10 => TransferStatus::UnjoinablePeer,
+            11 => TransferStatus::TimeoutExpired,
+            _ => TransferStatus::NotDownloaded,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransferStatus::NotDownloaded => "not downloaded",
+            TransferStatus::AwaitingPeer => "awaiting peer",
+            TransferStatus::AwaitingHost => "awaiting host",
+            TransferStatus::Ongoing => "ongoing",
+            TransferStatus::Finished => "finished",
+            TransferStatus::ClosedByHost => "closed by host",
+            TransferStatus::ClosedByPeer => "closed by peer",
+            TransferStatus::UnjoinablePeer => "unjoinable peer",
+            TransferStatus::TimeoutExpired => "timeout expired",
+        }
+    }
+}
+
+/**
+ * Device linked to an account, listed in the devices panel
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceEntry {
+    pub id: String,
+    pub name: String,
+    /// If it is the device running the daemon
+    pub current: bool,
+}
+
+/**
+ * File transfer listed in the transfers panel
+ */
+#[derive(Clone, Debug)]
+pub struct TransferEntry {
+    pub account_id: String,
+    pub conversation_id: String,
+    pub tid: u64,
+    pub name: String,
+    pub status: TransferStatus,
+    /// Where the file is, once sent or downloaded
+    pub path: Option<String>,
+    pub bytes: i64,
+    pub total: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MemberAction {
+    Add,
+    Join,
+    Remove,
+    Ban,
+}
+
+impl MemberAction {
+    /**
+     * @param action    "action" of a member commit
+     */
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "add" => Some(MemberAction::Add),
+            "join" => Some(MemberAction::Join),
+            "remove" => Some(MemberAction::Remove),
+            "ban" => Some(MemberAction::Ban),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Content of a message, rendered by the UI
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MessageKind {
+    /// Text of a commit or information from the client
+    Text(String),
+    /// Start of the conversation, with the invited peer for 1:1 conversations
+    Initial { invited: Option<String> },
+    /// Call history, a duration of 0 is a missed call
+    Call { duration_secs: u64 },
+    Transfer {
+        tid: u64,
+        name: String,
+        status: TransferStatus,
+        /// Where the file is, once sent or downloaded
+        path: Option<String>,
+        /// Bytes transferred
+        #[serde(default)]
+        bytes: i64,
+        /// Size of the file
+        #[serde(default)]
+        total: i64,
+        /// Bytes per second while ongoing
+        #[serde(default)]
+        rate: u64,
+    },
+    Member { action: MemberAction, uri: String },
+    /// Commit type not supported
+    Unknown(HashMap<String, String>),
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message {
+    /// Commit id, empty for local informations
+    #[serde(default)]
+    pub id: String,
     pub from: String,
-    #[serde(alias = "text")] // remove
-    pub message: String,
+    pub kind: MessageKind,
     pub arrived_at: DateTime<Utc>,
+    /// Commit id of the message this one replies to
+    #[serde(default)]
+    pub reply_to: String,
+    /// Uri of the author
+    #[serde(default)]
+    pub author: String,
+    /// Commit as received from the daemon
+    #[serde(skip)]
+    pub payload: HashMap<String, String>,
 }
 
 impl Message {
     pub fn info(message: String) -> Message {
-        Message {
-            from: String::new(),
-            message,
-            arrived_at: Utc::now(),
-        }
+        Message::new(String::new(), message, Utc::now())
     }
 
     pub fn new(from: String, message: String, arrived_at: DateTime<Utc>) -> Message {
+        Message::with_kind(from, MessageKind::Text(message), arrived_at)
+    }
+
+    pub fn with_kind(from: String, kind: MessageKind, arrived_at: DateTime<Utc>) -> Message {
         Message {
+            id: String::new(),
             from,
-            message,
+            kind,
             arrived_at,
+            reply_to: String::new(),
+            author: String::new(),
+            payload: HashMap::new(),
+        }
+    }
+
+    /**
+     * Text of the message, empty if it isn't a text
+     * @param self
+     */
+    pub fn text(&self) -> &str {
+        match &self.kind {
+            MessageKind::Text(text) => text,
+            _ => "",
+        }
+    }
+
+    /**
+     * Path of the file shown by this message, if any
+     * @param self
+     */
+    pub fn file_path(&self) -> Option<&str> {
+        match &self.kind {
+            MessageKind::Transfer { path, .. } => path.as_deref(),
+            _ => None,
         }
     }
 }
@@ -185,3 +481,74 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 }
+
+/**
+ * Open a file with the default application of the system
+ * @param path
+ */
+pub fn open_path(path: &str) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener)
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/**
+ * First line of a text, cut to fit a width
+ * @param text
+ * @param max_width     in columns
+ */
+pub fn excerpt(text: &str, max_width: usize) -> String {
+    let mut lines = text.lines();
+    let first_line = lines.next().unwrap_or("");
+    let cut = lines.next().is_some();
+    if !cut && first_line.width() <= max_width {
+        return first_line.to_string();
+    }
+    let mut result = String::new();
+    let mut width = 0;
+    for c in first_line.chars() {
+        let c_width = c.width().unwrap_or(0);
+        // Keep a column for the ellipsis
+        if width + c_width + 1 > max_width {
+            break;
+        }
+        result.push(c);
+        width += c_width;
+    }
+    result.push('…');
+    result
+}
+
+/**
+ * Escape sequence asking the terminal to copy a text to the clipboard (OSC 52)
+ * @param text
+ */
+pub fn osc52_copy(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64::encode(text))
+}
+
+/**
+ * Format a size like 1.5 MiB
+ * @param bytes
+ */
+pub fn format_size(bytes: i64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
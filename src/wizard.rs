@@ -0,0 +1,664 @@
+//! Guided forms to create, link or import an account, and the password
+//! prompt of account commands, drawn over the chat. Passwords stay in these
+//! forms, they never go through the input box.
+
+use crate::editor;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Delay without typing before a username is looked up on the name server
+pub const LOOKUP_DELAY: Duration = Duration::from_millis(500);
+
+/**
+ * Where the account comes from
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WizardKind {
+    /// New account
+    Create,
+    /// Account of another device, with the PIN generated on it
+    Link,
+    /// Account from a backup archive
+    Import,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldKind {
+    Text,
+    /// Masked when drawn
+    Password,
+    Path,
+}
+
+/**
+ * Input of a step
+ */
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub kind: FieldKind,
+    pub value: String,
+    /// Position of the cursor, in chars
+    pub cursor: usize,
+}
+
+impl Field {
+    fn new(key: &'static str, label: &'static str, kind: FieldKind) -> Self {
+        Self {
+            key,
+            label,
+            kind,
+            value: String::new(),
+            cursor: 0,
+        }
+    }
+
+    /**
+     * Value as drawn, passwords are masked
+     * @param self
+     */
+    pub fn shown(&self) -> String {
+        match self.kind {
+            FieldKind::Password => "•".repeat(self.value.chars().count()),
+            _ => self.value.clone(),
+        }
+    }
+
+    /**
+     * Edit the value with a key
+     * @param self
+     * @param key
+     * @return if the value changed
+     */
+    fn edit(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let (value, cursor) = (&mut self.value, &mut self.cursor);
+        match key.code {
+            KeyCode::Left => *cursor = cursor.saturating_sub(1),
+            KeyCode::Right => *cursor = (*cursor + 1).min(value.chars().count()),
+            KeyCode::Home => *cursor = 0,
+            KeyCode::End => *cursor = value.chars().count(),
+            KeyCode::Char('u') if ctrl => {
+                let end = *cursor;
+                editor::delete_range(value, cursor, 0, end);
+                return true;
+            }
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => {
+                editor::insert(value, cursor, &c.to_string());
+                return true;
+            }
+            KeyCode::Backspace => {
+                editor::backspace(value, cursor);
+                return true;
+            }
+            KeyCode::Delete => {
+                editor::delete(value, cursor);
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /**
+     * Insert pasted text at the cursor
+     * @param self
+     * @param text
+     */
+    fn paste(&mut self, text: &str) {
+        // Fields are single lines
+        let text = text.trim_end_matches('\n').replace('\n', " ");
+        editor::insert(&mut self.value, &mut self.cursor, &text);
+    }
+}
+
+/**
+ * Page of the wizard
+ */
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub title: &'static str,
+    pub help: &'static str,
+    pub fields: Vec<Field>,
+}
+
+/**
+ * Availability of the chosen username on the name server
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NameStatus {
+    /// No username, the account is created without one
+    None,
+    /// Edited, looked up once the user stops typing
+    Pending,
+    Checking,
+    Available,
+    Taken,
+    Invalid,
+    /// The name server didn't answer
+    Unknown,
+}
+
+impl NameStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NameStatus::None => "",
+            NameStatus::Pending | NameStatus::Checking => "checking…",
+            NameStatus::Available => "✓ available",
+            NameStatus::Taken => "✗ already taken",
+            NameStatus::Invalid => "✗ invalid: 3 to 32 lowercase letters, digits, - or _",
+            NameStatus::Unknown => "? name server unreachable",
+        }
+    }
+}
+
+/**
+ * What happens once the form is filled
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Progress {
+    Editing,
+    /// Waiting for the daemon
+    Working(String),
+    /// Waiting for the username to be registered
+    Registering,
+    Done(String),
+    Failed(String),
+}
+
+/**
+ * What the app must do after a key
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum WizardAction {
+    None,
+    Close,
+    /// Create the account with the details of the form
+    Submit,
+    /// Remove the account which failed, then edit the form again
+    Retry,
+}
+
+/**
+ * Multi-step form to add an account
+ */
+#[derive(Clone, Debug)]
+pub struct AccountWizard {
+    pub kind: WizardKind,
+    pub steps: Vec<Step>,
+    pub step: usize,
+    /// Field of the step being edited
+    pub focused: usize,
+    pub name_status: NameStatus,
+    name_edited_at: Instant,
+    /// Id of the account, once created
+    pub account_id: String,
+    /// If the daemon registered the account, which then works even if the username can't be registered
+    account_ready: bool,
+    pub progress: Progress,
+    /// Why the step can't be validated
+    pub error: Option<String>,
+}
+
+impl AccountWizard {
+    /**
+     * Empty form for a kind of account
+     * @param kind
+     */
+    pub fn new(kind: WizardKind) -> Self {
+        let profile = Step {
+            title: "Profile",
+            help: "Shown to your contacts. The avatar is an image file, both are optional.",
+            fields: vec![
+                Field::new("display_name", "Display name", FieldKind::Text),
+                Field::new("avatar", "Avatar", FieldKind::Path),
+            ],
+        };
+        let steps = match kind {
+            WizardKind::Create => vec![
+                profile,
+                Step {
+                    title: "Username",
+                    help: "Registered on the name server so that people can find you. Optional.",
+                    fields: vec![Field::new("username", "Username", FieldKind::Text)],
+                },
+                Step {
+                    title: "Password",
+                    help: "Encrypts the account on this device. Optional, but needed to link other devices.",
+                    fields: vec![
+                        Field::new("password", "Password", FieldKind::Password),
+                        Field::new("confirm", "Confirm password", FieldKind::Password),
+                    ],
+                },
+            ],
+            WizardKind::Link => vec![
+                Step {
+                    title: "Link a device",
+                    help: "Generate a PIN on a device of the account, then enter it with the password of the account.",
+                    fields: vec![
+                        Field::new("pin", "PIN", FieldKind::Text),
+                        Field::new("password", "Password", FieldKind::Password),
+                    ],
+                },
+                profile,
+            ],
+            WizardKind::Import => vec![
+                Step {
+                    title: "Import a backup",
+                    help: "Archive exported from a Jami client, with the password of the account.",
+                    fields: vec![
+                        Field::new("archive", "Backup file", FieldKind::Path),
+                        Field::new("password", "Password", FieldKind::Password),
+                    ],
+                },
+                profile,
+            ],
+        };
+        Self {
+            kind,
+            steps,
+            step: 0,
+            focused: 0,
+            name_status: NameStatus::None,
+            name_edited_at: Instant::now(),
+            account_id: String::new(),
+            account_ready: false,
+            progress: Progress::Editing,
+            error: None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.kind {
+            WizardKind::Create => "New account",
+            WizardKind::Link => "Link an account",
+            WizardKind::Import => "Import an account",
+        }
+    }
+
+    /**
+     * Value of a field, empty if the form doesn't have it
+     * @param self
+     * @param key
+     */
+    pub fn value(&self, key: &str) -> &str {
+        self.steps
+            .iter()
+            .flat_map(|step| step.fields.iter())
+            .find(|field| field.key == key)
+            .map(|field| field.value.as_str())
+            .unwrap_or("")
+    }
+
+    /**
+     * Set a field, like the archive given to /import
+     * @param self
+     * @param key
+     * @param value
+     */
+    pub fn set_value(&mut self, key: &str, value: &str) {
+        let field = self
+            .steps
+            .iter_mut()
+            .flat_map(|step| step.fields.iter_mut())
+            .find(|field| field.key == key);
+        if let Some(field) = field {
+            field.value = value.to_string();
+            field.cursor = field.value.chars().count();
+        }
+    }
+
+    /**
+     * Handle a key
+     * @param self
+     * @param key
+     */
+    pub fn on_key(&mut self, key: KeyEvent) -> WizardAction {
+        match &self.progress {
+            Progress::Editing => {}
+            Progress::Working(_) | Progress::Registering => {
+                // The daemon goes on if the wizard is closed
+                return match key.code {
+                    KeyCode::Esc => WizardAction::Close,
+                    _ => WizardAction::None,
+                };
+            }
+            Progress::Done(_) => {
+                return match key.code {
+                    KeyCode::Enter | KeyCode::Esc => WizardAction::Close,
+                    _ => WizardAction::None,
+                };
+            }
+            Progress::Failed(_) => {
+                return match key.code {
+                    // Fix the form and try again
+                    KeyCode::Enter => WizardAction::Retry,
+                    KeyCode::Esc => WizardAction::Close,
+                    _ => WizardAction::None,
+                };
+            }
+        }
+        let num_fields = self.steps[self.step].fields.len();
+        match key.code {
+            KeyCode::Esc if self.step == 0 => return WizardAction::Close,
+            KeyCode::Esc => {
+                self.step -= 1;
+                self.focused = 0;
+                self.error = None;
+            }
+            KeyCode::Enter => return self.next_step(),
+            KeyCode::Tab | KeyCode::Down => self.focused = (self.focused + 1) % num_fields,
+            KeyCode::BackTab | KeyCode::Up => self.focused = (self.focused + num_fields - 1) % num_fields,
+            _ => {
+                if self.steps[self.step].fields[self.focused].edit(key) {
+                    self.on_edit();
+                }
+            }
+        }
+        WizardAction::None
+    }
+
+    /**
+     * Insert pasted text in the field being edited
+     * @param self
+     * @param text
+     */
+    pub fn on_paste(&mut self, text: &str) {
+        if self.progress != Progress::Editing {
+            return;
+        }
+        self.steps[self.step].fields[self.focused].paste(text);
+        self.on_edit();
+    }
+
+    fn on_edit(&mut self) {
+        self.error = None;
+        if self.steps[self.step].fields[self.focused].key == "username" {
+            self.name_status = match self.value("username") {
+                "" => NameStatus::None,
+                _ => NameStatus::Pending,
+            };
+            self.name_edited_at = Instant::now();
+        }
+    }
+
+    /**
+     * Validate the current step and go to the next one
+     * @param self
+     * @return Submit after the last step
+     */
+    fn next_step(&mut self) -> WizardAction {
+        self.error = self.step_error();
+        if self.error.is_some() {
+            return WizardAction::None;
+        }
+        if self.step + 1 < self.steps.len() {
+            self.step += 1;
+            self.focused = 0;
+            return WizardAction::None;
+        }
+        self.progress = Progress::Working(String::from("Creating the account…"));
+        WizardAction::Submit
+    }
+
+    /**
+     * Why the fields of the current step are wrong, if they are
+     * @param self
+     */
+    fn step_error(&self) -> Option<String> {
+        for field in &self.steps[self.step].fields {
+            let value = field.value.as_str();
+            let error = match field.key {
+                "avatar" if !value.is_empty() && !Path::new(value).is_file() => {
+                    Some(format!("No image at {}", value))
+                }
+                "archive" if value.is_empty() => Some(String::from("Choose the backup file")),
+                "archive" if !Path::new(value).is_file() => Some(format!("No backup at {}", value)),
+                "pin" if value.trim().is_empty() => Some(String::from("Enter the PIN")),
+                "username" => match self.name_status {
+                    NameStatus::Pending | NameStatus::Checking => {
+                        Some(String::from("Wait for the name server to check the username"))
+                    }
+                    NameStatus::Taken | NameStatus::Invalid => Some(format!("Username {}", self.name_status.label())),
+                    _ => None,
+                },
+                "confirm" if value != self.value("password") => Some(String::from("Passwords don't match")),
+                _ => None,
+            };
+            if error.is_some() {
+                return error;
+            }
+        }
+        None
+    }
+
+    /**
+     * Time goes on: look up the username once the user stops typing
+     * @param self
+     * @param now
+     * @return the name to look up, if any
+     */
+    pub fn tick(&mut self, now: Instant) -> Option<String> {
+        if self.progress != Progress::Editing
+            || self.name_status != NameStatus::Pending
+            || now < self.name_edited_at + LOOKUP_DELAY
+        {
+            return None;
+        }
+        let username = self.value("username").to_string();
+        if !valid_username(&username) {
+            self.name_status = NameStatus::Invalid;
+            return None;
+        }
+        self.name_status = NameStatus::Checking;
+        Some(username)
+    }
+
+    /**
+     * Result of a lookup of the username
+     * @param self
+     * @param status        0 if found, 1 if invalid, 2 if not found
+     * @param name
+     */
+    pub fn on_name_found(&mut self, status: u64, name: &str) {
+        if name != self.value("username") || self.name_status != NameStatus::Checking {
+            return;
+        }
+        self.name_status = match status {
+            0 => NameStatus::Taken,
+            1 => NameStatus::Invalid,
+            2 => NameStatus::Available,
+            _ => NameStatus::Unknown,
+        };
+    }
+
+    /**
+     * End of the registration of the username
+     * @param self
+     * @param status        0 on success, 1 for a wrong password, 2 for an invalid name,
+     *                      3 if already taken, else a network error
+     * @param name
+     */
+    pub fn on_name_registered(&mut self, status: i32, name: &str) {
+        if self.progress != Progress::Registering || name != self.value("username") {
+            return;
+        }
+        self.progress = match status {
+            0 => Progress::Done(format!("Account ready, registered as {}", name)),
+            1 => Progress::Failed(format!("Cannot register {}: wrong password", name)),
+            2 => Progress::Failed(format!("{} is not a valid username", name)),
+            3 => Progress::Failed(format!("{} was registered by someone else", name)),
+            _ => Progress::Failed(format!("Cannot register {}: network error", name)),
+        };
+    }
+
+    /**
+     * Edit the form again after a failure, the failed account must be removed
+     * @param self
+     */
+    pub fn retry(&mut self) {
+        self.progress = Progress::Editing;
+        self.account_id.clear();
+        self.account_ready = false;
+    }
+
+    /**
+     * Account left unusable by a failure, to remove when the wizard is closed
+     * @param self
+     */
+    pub fn broken_account(&self) -> Option<&String> {
+        match self.progress {
+            Progress::Failed(_) if !self.account_ready && !self.account_id.is_empty() => Some(&self.account_id),
+            _ => None,
+        }
+    }
+
+    /**
+     * Details of the account for the daemon
+     * @param self
+     */
+    pub fn details(&self) -> HashMap<String, String> {
+        let mut details = HashMap::new();
+        let alias = match (self.value("display_name"), self.value("username")) {
+            ("", "") => "Jami",
+            ("", username) => username,
+            (display_name, _) => display_name,
+        };
+        details.insert(String::from("Account.type"), String::from("RING"));
+        details.insert(String::from("Account.alias"), alias.to_string());
+        details.insert(String::from("Account.displayName"), self.value("display_name").to_string());
+        details.insert(String::from("Account.archivePassword"), self.value("password").to_string());
+        match self.kind {
+            WizardKind::Create => {}
+            WizardKind::Link => {
+                details.insert(String::from("Account.archivePin"), self.value("pin").trim().to_string());
+            }
+            WizardKind::Import => {
+                details.insert(String::from("Account.archivePath"), self.value("archive").to_string());
+            }
+        }
+        details
+    }
+
+    /**
+     * Follow the registration of the created account
+     * @param self
+     * @param registration_state    state sent by the daemon
+     * @return true if the username must now be registered
+     */
+    pub fn on_registration_state(&mut self, registration_state: &str) -> bool {
+        if !matches!(self.progress, Progress::Working(_)) {
+            return false;
+        }
+        let progress = match registration_state {
+            "REGISTERED" if !self.value("username").is_empty() => Progress::Registering,
+            "REGISTERED" => Progress::Done(String::from("Account ready")),
+            "INITIALIZING" => Progress::Working(String::from("Generating the keys…")),
+            "TRYING" => Progress::Working(String::from("Connecting…")),
+            "ERROR_AUTH" => Progress::Failed(String::from("Wrong password")),
+            "ERROR_NETWORK" => Progress::Failed(match self.kind {
+                WizardKind::Link => String::from("Cannot get the account, check the PIN and the network"),
+                _ => String::from("Network error"),
+            }),
+            "ERROR_NEED_MIGRATION" => Progress::Failed(String::from("The account must be migrated")),
+            state if state.starts_with("ERROR") => Progress::Failed(format!("Cannot add the account ({})", state)),
+            _ => return false,
+        };
+        let register = progress == Progress::Registering;
+        self.account_ready = registration_state == "REGISTERED";
+        self.progress = progress;
+        register
+    }
+}
+
+/**
+ * Account command waiting for the password of the account
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum PasswordRequest {
+    /// Write a backup of the account
+    Export { account_id: String, path: String },
+    /// Generate a PIN to link another device
+    Pin { account_id: String },
+    /// Revoke another device of the account
+    Revoke { account_id: String, device_id: String },
+}
+
+/**
+ * What the app must do after a key in the password prompt
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum PromptAction {
+    None,
+    Cancel,
+    /// Run the request with the typed password
+    Submit,
+}
+
+/**
+ * Masked input of the password of an account, asked by /export, /pin and /revoke
+ */
+#[derive(Clone, Debug)]
+pub struct PasswordPrompt {
+    pub request: PasswordRequest,
+    pub field: Field,
+}
+
+impl PasswordPrompt {
+    pub fn new(request: PasswordRequest) -> Self {
+        Self {
+            request,
+            field: Field::new("password", "Password", FieldKind::Password),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.request {
+            PasswordRequest::Export { .. } => "Export the account",
+            PasswordRequest::Pin { .. } => "Link a device",
+            PasswordRequest::Revoke { .. } => "Revoke a device",
+        }
+    }
+
+    /**
+     * Handle a key
+     * @param self
+     * @param key
+     */
+    pub fn on_key(&mut self, key: KeyEvent) -> PromptAction {
+        match key.code {
+            KeyCode::Esc => PromptAction::Cancel,
+            KeyCode::Enter => PromptAction::Submit,
+            _ => {
+                self.field.edit(key);
+                PromptAction::None
+            }
+        }
+    }
+
+    /**
+     * Insert pasted text in the password
+     * @param self
+     * @param text
+     */
+    pub fn on_paste(&mut self, text: &str) {
+        self.field.paste(text);
+    }
+}
+
+/**
+ * If a username can be registered on the name server
+ * @param name
+ */
+pub fn valid_username(name: &str) -> bool {
+    (3..=32).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
@@ -0,0 +1,916 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use jami_cli::app::{App, Focus};
+use jami_cli::appdata::AppData;
+use jami_cli::backend::Event;
+use jami_cli::calls::CallState;
+use jami_cli::config::Config;
+use jami_cli::fake_backend::FakeBackend;
+use jami_cli::input::InputEvent;
+use jami_cli::ui;
+use jami_cli::util::{Channel, ChannelType, Message, MessageKind, TransferStatus};
+use jami_cli::wizard::{self, NameStatus, Progress};
+use tui::backend::TestBackend;
+use tui::Terminal;
+
+const ACCOUNT: &str = "account";
+const PEER: &str = "peer";
+const CONVERSATION: &str = "conversation";
+
+fn new_app(backend: &FakeBackend) -> App {
+    App::with_backend(Box::new(backend.clone())).unwrap()
+}
+
+async fn process_events(app: &mut App, backend: &FakeBackend) {
+    while let Some(event) = backend.pop_event() {
+        app.handle_event(event).await;
+    }
+    while let Some(event) = backend.pop_call_event() {
+        app.handle_call_event(event);
+    }
+    while let Some(event) = backend.pop_account_event() {
+        app.handle_account_event(event);
+    }
+}
+
+async fn press(app: &mut App, code: KeyCode) {
+    press_with(app, code, KeyModifiers::NONE).await;
+}
+
+async fn press_with(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+    app.handle_event(Event::Input(KeyEvent::new(code, modifiers)))
+        .await;
+}
+
+async fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c)).await;
+    }
+}
+
+async fn next_channel(app: &mut App) {
+    press_with(app, KeyCode::Down, KeyModifiers::ALT).await;
+}
+
+async fn send(app: &mut App, text: &str) {
+    type_text(app, text).await;
+    press(app, KeyCode::Enter).await;
+}
+
+/// Text of the screen drawn for the app, one line per row
+fn render(app: &mut App) -> String {
+    let (width, height) = (160, 30);
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| ui::draw(f, app)).unwrap();
+    let cells = &terminal.backend().buffer().content;
+    cells
+        .chunks(width as usize)
+        .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect::<String>() + "\n")
+        .collect()
+}
+
+fn channel<'a>(app: &'a App, id: &str) -> &'a Channel {
+    app.data
+        .channels
+        .items
+        .iter()
+        .find(|channel| channel.id == id)
+        .unwrap()
+}
+
+fn setup() -> FakeBackend {
+    let backend = FakeBackend::new();
+    backend
+        .with_account(ACCOUNT)
+        .with_conversation(ACCOUNT, CONVERSATION, &[ACCOUNT, PEER]);
+    backend
+}
+
+#[tokio::test]
+async fn incoming_message_is_added_to_its_channel() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "hello");
+    process_events(&mut app, &backend).await;
+
+    let channel = channel(&app, CONVERSATION);
+    assert_eq!(channel.messages.last().unwrap().text(), "hello");
+}
+
+#[tokio::test]
+async fn text_is_sent_to_the_selected_conversation() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    send(&mut app, "hi").await;
+    process_events(&mut app, &backend).await;
+
+    assert!(backend
+        .calls()
+        .contains(&format!("send_conversation_message {} hi", CONVERSATION)));
+    let channel = channel(&app, CONVERSATION);
+    assert_eq!(channel.messages.last().unwrap().text(), "hi");
+    assert!(app.data.input.is_empty());
+}
+
+#[tokio::test]
+async fn new_conversation_adds_a_channel() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    send(&mut app, "/new").await;
+    process_events(&mut app, &backend).await;
+
+    let conversations = app
+        .data
+        .channels
+        .items
+        .iter()
+        .filter(|channel| channel.channel_type == ChannelType::Group)
+        .count();
+    assert_eq!(conversations, 2);
+}
+
+#[tokio::test]
+async fn invite_by_username_adds_the_registered_address() {
+    let backend = setup();
+    backend.with_name("bob", "bobhash");
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    send(&mut app, "/invite bob").await;
+    process_events(&mut app, &backend).await;
+
+    assert!(backend
+        .calls()
+        .contains(&format!("add_conversation_member {} bobhash", CONVERSATION)));
+}
+
+#[tokio::test]
+async fn scrolling_up_loads_older_messages() {
+    let backend = setup();
+    for i in 0..120 {
+        backend.receive_message(ACCOUNT, CONVERSATION, PEER, &format!("message {}", i));
+    }
+    backend.state().events.clear();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    assert_eq!(channel(&app, CONVERSATION).messages.len(), 50);
+
+    press(&mut app, KeyCode::Home).await;
+    process_events(&mut app, &backend).await;
+
+    let messages = &channel(&app, CONVERSATION).messages;
+    assert_eq!(messages.len(), 99);
+    assert_eq!(messages.first().unwrap().text(), "message 21");
+    assert_eq!(messages.last().unwrap().text(), "message 119");
+}
+
+#[tokio::test]
+async fn search_jumps_to_the_message() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "where is the cake?");
+    for i in 0..20 {
+        backend.receive_message(ACCOUNT, CONVERSATION, PEER, &format!("message {}", i));
+    }
+    process_events(&mut app, &backend).await;
+
+    send(&mut app, "/search CAKE").await;
+    let selected = app.data.channels.state.selected().unwrap();
+    let results = &app.data.channels.items[selected];
+    assert_eq!(results.channel_type, ChannelType::Search);
+    assert!(results
+        .messages
+        .iter()
+        .any(|msg| msg.text().ends_with("where is the cake?")));
+
+    send(&mut app, "/open 1").await;
+    process_events(&mut app, &backend).await;
+    let selected = app.data.channels.state.selected().unwrap();
+    let channel = &app.data.channels.items[selected];
+    assert_eq!(channel.id, CONVERSATION);
+    assert_eq!(channel.scroll, 20);
+}
+
+#[tokio::test]
+async fn search_finds_messages_of_the_history_never_shown() {
+    let backend = setup();
+    for i in 0..120 {
+        backend.receive_message(ACCOUNT, CONVERSATION, PEER, &format!("message {}", i));
+    }
+    backend.state().events.clear();
+    let mut app = new_app(&backend);
+
+    for _ in 0..5 {
+        app.on_tick();
+        process_events(&mut app, &backend).await;
+    }
+    assert!(channel(&app, CONVERSATION).messages.is_empty());
+
+    send(&mut app, "/search message 5").await;
+    let selected = app.data.channels.state.selected().unwrap();
+    assert!(app.data.channels.items[selected]
+        .messages
+        .iter()
+        .any(|msg| msg.text().ends_with("message 5")));
+}
+
+#[tokio::test]
+async fn tab_completes_and_cycles_commands() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    type_text(&mut app, "/s").await;
+    press(&mut app, KeyCode::Tab).await;
+    assert_eq!(app.data.input, "/search");
+    press(&mut app, KeyCode::Tab).await;
+    assert_eq!(app.data.input, "/set");
+    press(&mut app, KeyCode::Tab).await;
+    assert_eq!(app.data.input, "/switch");
+    press(&mut app, KeyCode::Tab).await;
+    assert_eq!(app.data.input, "/search");
+    assert_eq!(app.data.input_cursor, 7);
+}
+
+#[tokio::test]
+async fn missing_argument_shows_usage() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    send(&mut app, "/invite").await;
+
+    let channel = channel(&app, CONVERSATION);
+    assert_eq!(
+        channel.messages.last().unwrap().text(),
+        "Usage: /invite <hash|username>"
+    );
+}
+
+#[tokio::test]
+async fn alt_enter_inserts_a_new_line() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    type_text(&mut app, "first").await;
+    press_with(&mut app, KeyCode::Enter, KeyModifiers::ALT).await;
+    send(&mut app, "second").await;
+
+    assert!(backend
+        .calls()
+        .contains(&format!("send_conversation_message {} first\nsecond", CONVERSATION)));
+}
+
+#[tokio::test]
+async fn up_and_down_recall_sent_inputs() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    send(&mut app, "one").await;
+    send(&mut app, "two").await;
+    type_text(&mut app, "draft").await;
+
+    press(&mut app, KeyCode::Up).await;
+    assert_eq!(app.data.input, "two");
+    press(&mut app, KeyCode::Up).await;
+    assert_eq!(app.data.input, "one");
+    press(&mut app, KeyCode::Up).await;
+    assert_eq!(app.data.input, "one");
+    press(&mut app, KeyCode::Down).await;
+    press(&mut app, KeyCode::Down).await;
+    assert_eq!(app.data.input, "draft");
+    assert_eq!(app.data.input_cursor, 5);
+}
+
+#[tokio::test]
+async fn readline_shortcuts_edit_wide_chars() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    type_text(&mut app, "日本 語").await;
+    press_with(&mut app, KeyCode::Char('w'), KeyModifiers::CONTROL).await;
+    assert_eq!(app.data.input, "日本 ");
+    press_with(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL).await;
+    press(&mut app, KeyCode::Delete).await;
+    assert_eq!(app.data.input, "本 ");
+    press_with(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+    assert_eq!(app.data.input_cursor, 2);
+    press(&mut app, KeyCode::Right).await;
+    assert_eq!(app.data.input_cursor, 2);
+}
+
+#[tokio::test]
+async fn reply_is_sent_with_the_parent_commit() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let commit = backend.receive_message(ACCOUNT, CONVERSATION, PEER, "question?");
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    press_with(&mut app, KeyCode::Char('r'), KeyModifiers::CONTROL).await;
+    assert_eq!(app.reply.as_ref().unwrap().commit_id, commit);
+    send(&mut app, "answer").await;
+    process_events(&mut app, &backend).await;
+
+    assert!(app.reply.is_none());
+    assert!(backend.calls().contains(&format!(
+        "send_conversation_message {} answer (reply to {})",
+        CONVERSATION, commit
+    )));
+    let answer = channel(&app, CONVERSATION).messages.last().unwrap();
+    assert_eq!(answer.reply_to, commit);
+}
+
+#[tokio::test]
+async fn selected_message_can_be_copied_and_its_author_shown() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "first");
+    backend.receive_message(ACCOUNT, CONVERSATION, ACCOUNT, "second");
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    press_with(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL).await;
+    press(&mut app, KeyCode::Up).await;
+    press(&mut app, KeyCode::Char('y')).await;
+    press(&mut app, KeyCode::Char('a')).await;
+
+    assert_eq!(app.terminal_output, vec![String::from("\x1b]52;c;Zmlyc3Q=\x07")]);
+    assert_eq!(app.highlighted_member.as_deref(), Some(PEER));
+    assert!(app.data.input.is_empty());
+
+    press(&mut app, KeyCode::Char('p')).await;
+    let last = channel(&app, CONVERSATION).messages.last().unwrap().text();
+    assert_eq!(last, "type: text/plain");
+}
+
+#[tokio::test]
+async fn call_places_a_call_to_the_peer() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    send(&mut app, "/call").await;
+    process_events(&mut app, &backend).await;
+
+    assert!(backend.calls().contains(&format!("place_call {}", PEER)));
+    assert_eq!(app.calls.len(), 1);
+    assert_eq!(app.calls[0].conversation_id, CONVERSATION);
+    assert_eq!(app.calls[0].state, CallState::Ringing);
+
+    send(&mut app, "/mute").await;
+    assert!(app.calls[0].muted);
+    send(&mut app, "/hangup").await;
+    process_events(&mut app, &backend).await;
+    assert!(app.calls.is_empty());
+}
+
+#[tokio::test]
+async fn incoming_call_can_be_accepted() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let call_id = backend.incoming_call(ACCOUNT, &format!("ring:{}", PEER));
+    process_events(&mut app, &backend).await;
+
+    assert_eq!(app.calls[0].peer, PEER);
+    assert_eq!(app.calls[0].conversation_id, CONVERSATION);
+    send(&mut app, "/accept-call").await;
+    process_events(&mut app, &backend).await;
+
+    assert!(backend.calls().contains(&format!("accept_call {}", call_id)));
+    assert_eq!(app.calls[0].state, CallState::Current);
+    assert!(app.calls[0].started_at.is_some());
+}
+
+#[tokio::test]
+async fn conference_is_started_with_the_members_and_admins_can_kick() {
+    let backend = FakeBackend::new();
+    backend
+        .with_account(ACCOUNT)
+        .with_conversation(ACCOUNT, CONVERSATION, &[ACCOUNT, PEER, "carol"]);
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    send(&mut app, "/conference").await;
+    process_events(&mut app, &backend).await;
+
+    assert!(backend.calls().contains(&format!("create_conference {},carol", PEER)));
+    assert_eq!(app.conferences[0].conversation_id, CONVERSATION);
+    assert_eq!(app.conferences[0].participants.len(), 3);
+
+    send(&mut app, "/conf-kick carol").await;
+    process_events(&mut app, &backend).await;
+    let uris: Vec<_> = app.conferences[0].participants.iter().map(|p| p.uri.as_str()).collect();
+    assert_eq!(uris, vec!["", PEER]);
+
+    send(&mut app, "/hangup").await;
+    process_events(&mut app, &backend).await;
+    assert!(app.conferences.is_empty());
+    assert_eq!(channel(&app, CONVERSATION).messages.last().unwrap().text(), "Conference ended");
+}
+
+#[tokio::test]
+async fn commits_are_parsed_into_message_kinds() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    send(&mut app, "/send /tmp/notes.txt").await;
+    process_events(&mut app, &backend).await;
+
+    let message = channel(&app, CONVERSATION).messages.last().unwrap();
+    assert!(matches!(
+        &message.kind,
+        MessageKind::Transfer { name, status: TransferStatus::Finished, .. } if name == "notes.txt"
+    ));
+    assert_eq!(message.text(), "");
+}
+
+#[tokio::test]
+async fn transfer_progress_is_updated_in_place() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let tid = backend.receive_file(ACCOUNT, CONVERSATION, PEER, "big.iso", 4096);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    let messages_len = channel(&app, CONVERSATION).messages.len();
+
+    backend.update_transfer(ACCOUNT, CONVERSATION, tid, 5, 1024);
+    process_events(&mut app, &backend).await;
+    backend.update_transfer(ACCOUNT, CONVERSATION, tid, 5, 2048);
+    app.on_tick();
+
+    let messages = &channel(&app, CONVERSATION).messages;
+    assert_eq!(messages.len(), messages_len);
+    assert!(matches!(
+        messages.last().unwrap().kind,
+        MessageKind::Transfer { status: TransferStatus::Ongoing, bytes: 2048, total: 4096, .. }
+    ));
+
+    backend.update_transfer(ACCOUNT, CONVERSATION, tid, 8, 2048);
+    process_events(&mut app, &backend).await;
+    assert!(matches!(
+        channel(&app, CONVERSATION).messages.last().unwrap().kind,
+        MessageKind::Transfer { status: TransferStatus::ClosedByPeer, .. }
+    ));
+
+    // Outgoing transfers know their path from the start
+    let path = std::env::temp_dir().join("jami-cli-upload.bin");
+    std::fs::write(&path, vec![0; 4096]).unwrap();
+    send(&mut app, &format!("/send {}", path.to_str().unwrap())).await;
+    process_events(&mut app, &backend).await;
+    let tid = match channel(&app, CONVERSATION).messages.last().unwrap().kind {
+        MessageKind::Transfer { tid, .. } => tid,
+        _ => panic!("no outgoing transfer"),
+    };
+    backend.update_transfer(ACCOUNT, CONVERSATION, tid, 5, 1024);
+    process_events(&mut app, &backend).await;
+    let message = channel(&app, CONVERSATION).messages.last().unwrap();
+    assert!(matches!(&message.kind, MessageKind::Transfer { path: Some(_), .. }));
+    let screen = render(&mut app);
+    assert!(screen.contains("[#####---------------] 1.0 KiB / 4.0 KiB"), "{}", screen);
+    assert!(!screen.contains("file://"));
+
+    backend.update_transfer(ACCOUNT, CONVERSATION, tid, 6, 4096);
+    process_events(&mut app, &backend).await;
+    assert!(render(&mut app).contains("<file:///"));
+}
+
+#[tokio::test]
+async fn transfers_panel_lists_and_cancels_transfers() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let tid = backend.receive_file(ACCOUNT, CONVERSATION, PEER, "photo.jpg", 2048);
+    process_events(&mut app, &backend).await;
+
+    press_with(&mut app, KeyCode::Char('t'), KeyModifiers::CONTROL).await;
+    assert_eq!(app.focus, Focus::Transfers);
+    assert_eq!(app.transfers.items.len(), 1);
+    assert_eq!(app.transfers.items[0].tid, tid);
+    assert_eq!(app.transfers.items[0].status, TransferStatus::AwaitingHost);
+
+    press(&mut app, KeyCode::Char('c')).await;
+    process_events(&mut app, &backend).await;
+    assert!(backend.calls().contains(&format!("cancel_file_transfer {}", tid)));
+    assert_eq!(app.transfers.items[0].status, TransferStatus::ClosedByHost);
+
+    press(&mut app, KeyCode::Esc).await;
+    assert_eq!(app.focus, Focus::Input);
+}
+
+#[tokio::test]
+async fn files_are_accepted_according_to_the_policy() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let download_dir = std::env::temp_dir().join("jami-cli-auto-accept");
+    app.config = Config::parse(&format!(
+        "[transfers]\ndownload_dir = \"{}\"\n[transfers.auto_accept]\nconversations = [\"{}\"]\nmax_size = 1024\n",
+        download_dir.display(),
+        CONVERSATION
+    ))
+    .unwrap();
+
+    let small = backend.receive_file(ACCOUNT, CONVERSATION, PEER, "small.txt", 100);
+    let big = backend.receive_file(ACCOUNT, CONVERSATION, PEER, "big.iso", 4096);
+    process_events(&mut app, &backend).await;
+
+    let calls = backend.calls();
+    assert!(calls.contains(&format!(
+        "accept_file_transfer {} {}",
+        small,
+        download_dir.join("small.txt").display()
+    )));
+    assert!(!calls.iter().any(|call| call.starts_with(&format!("accept_file_transfer {}", big))));
+}
+
+#[tokio::test]
+async fn pasted_text_is_inserted_without_sending() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    app.handle_input(InputEvent::Paste(String::from("first\r\nsecond"))).await;
+
+    assert_eq!(app.data.input, "first\nsecond");
+    assert!(!backend.calls().iter().any(|call| call.starts_with("send_conversation_message")));
+}
+
+#[tokio::test]
+async fn dropped_files_are_sent_after_confirmation() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let path = std::env::temp_dir().join("jami-cli dropped.txt");
+    std::fs::write(&path, "content").unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    next_channel(&mut app).await;
+    process_events(&mut app, &backend).await;
+    app.on_paste(&format!("'{}' ", path));
+    assert_eq!(app.dropped_files, vec![path.clone()]);
+    assert!(app.data.input.is_empty());
+    press(&mut app, KeyCode::Enter).await;
+
+    assert!(backend
+        .calls()
+        .contains(&format!("send_file {} {}", CONVERSATION, path)));
+    assert!(app.dropped_files.is_empty());
+}
+
+#[tokio::test]
+async fn clicks_select_channels_and_accept_transfers() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    let download_dir = std::env::temp_dir().join("jami-cli-click");
+    app.config = Config::parse(&format!("[transfers]\ndownload_dir = \"{}\"\n", download_dir.display())).unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    let click = |column, row| InputEvent::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+
+    terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
+    let channel_idx = app
+        .data
+        .channels
+        .items
+        .iter()
+        .position(|channel| channel.id == CONVERSATION)
+        .unwrap();
+    // Skip the border of the list
+    app.handle_input(click(1, channel_idx as u16 + 1)).await;
+    process_events(&mut app, &backend).await;
+    assert_eq!(app.data.channels.state.selected(), Some(channel_idx));
+
+    let tid = backend.receive_file(ACCOUNT, CONVERSATION, PEER, "photo.jpg", 2048);
+    process_events(&mut app, &backend).await;
+    terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
+    let messages = app.click_areas.messages;
+    app.handle_input(click(messages.x + 2, messages.y + messages.height - 2)).await;
+
+    assert!(backend.calls().contains(&format!(
+        "accept_file_transfer {} {}",
+        tid,
+        download_dir.join("photo.jpg").display()
+    )));
+}
+
+#[tokio::test]
+async fn messages_in_other_channels_are_counted_and_notified() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    app.config = Config::parse("[notifications]\ndesktop = \"osc9\"\n").unwrap();
+
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "hello");
+    process_events(&mut app, &backend).await;
+
+    assert_eq!(channel(&app, CONVERSATION).unread_messages, 1);
+    assert_eq!(app.terminal_output[0], "\x07");
+    assert!(app.terminal_output[1].starts_with("\x1b]9;"));
+    assert!(app.terminal_output[1].contains("hello"));
+}
+
+#[tokio::test]
+async fn mentions_mode_only_notifies_mentions() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    app.config = Config::parse(&format!("[notifications.channels]\n\"{}\" = \"mentions\"\n", CONVERSATION)).unwrap();
+
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "hello");
+    process_events(&mut app, &backend).await;
+    assert!(app.terminal_output.is_empty());
+
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, &format!("hello @{}", ACCOUNT));
+    process_events(&mut app, &backend).await;
+    assert_eq!(app.terminal_output, vec!["\x07"]);
+    assert_eq!(channel(&app, CONVERSATION).unread_messages, 2);
+}
+
+#[tokio::test]
+async fn trust_requests_follow_the_mode_of_their_channel() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    app.config = Config::parse("[notifications.channels]\n\"stranger\" = \"none\"\n").unwrap();
+
+    let request = |from: &str| Event::IncomingTrustRequest(ACCOUNT.to_string(), from.to_string(), Vec::new(), 0);
+    app.handle_event(request("stranger")).await;
+    assert!(app.terminal_output.is_empty());
+    app.handle_event(request("friend")).await;
+    assert_eq!(app.terminal_output, vec!["\x07"]);
+}
+
+#[tokio::test]
+async fn mentions_and_keywords_are_counted_and_listed() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+    app.config = Config::parse("[notifications]\nkeywords = [\"deploy\"]\n").unwrap();
+
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "hello");
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, &format!("@{} can you look?", ACCOUNT));
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "Deploy is done");
+    process_events(&mut app, &backend).await;
+    assert_eq!(channel(&app, CONVERSATION).unread_messages, 3);
+    assert_eq!(channel(&app, CONVERSATION).unread_mentions, 2);
+
+    send(&mut app, "/mentions").await;
+    let selected = app.data.channels.state.selected().unwrap();
+    let mentions = &app.data.channels.items[selected];
+    assert_eq!(mentions.channel_type, ChannelType::Search);
+    let texts: Vec<&str> = mentions.messages.iter().map(|message| message.text()).collect();
+    assert!(texts.iter().any(|text| text.ends_with("can you look?")));
+    assert!(texts.iter().any(|text| text.ends_with("Deploy is done")));
+    assert!(!texts.iter().any(|text| text.ends_with("hello")));
+}
+
+#[test]
+fn cached_messages_are_kept_when_the_daemon_answers() {
+    let backend = setup();
+    let mut data = AppData::init_from_jami(&backend, false).unwrap();
+    let idx = data.channels.items.iter().position(|channel| channel.id == CONVERSATION).unwrap();
+    data.channels.items[idx].messages.push(Message::info(String::from("cached")));
+    backend.with_conversation(ACCOUNT, "new-conversation", &[ACCOUNT, PEER]);
+
+    let snapshot = AppData::fetch_from_jami(&backend, ACCOUNT, false).unwrap();
+    data.merge_from_jami(snapshot);
+    let ids: Vec<&str> = data.channels.items.iter().map(|channel| channel.id.as_str()).collect();
+    assert!(ids.contains(&"new-conversation"));
+    let channel = data.channels.items.iter().find(|channel| channel.id == CONVERSATION).unwrap();
+    assert_eq!(channel.messages.last().unwrap().text(), "cached");
+}
+
+#[tokio::test]
+async fn unified_search_finds_messages_of_every_account() {
+    let backend = setup();
+    backend
+        .with_account("other")
+        .with_conversation("other", "other-conversation", &["other", PEER]);
+    let mut app = new_app(&backend);
+    send(&mut app, "/unified").await;
+    process_events(&mut app, &backend).await;
+    backend.receive_message("other", "other-conversation", PEER, "where is the cake?");
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "the cake is a lie");
+    process_events(&mut app, &backend).await;
+
+    send(&mut app, "/search cake").await;
+    let selected = app.data.channels.state.selected().unwrap();
+    let hits: Vec<String> = app.data.channels.items[selected]
+        .messages
+        .iter()
+        .map(|msg| msg.text().to_string())
+        .filter(|text| text.starts_with('['))
+        .collect();
+    assert_eq!(hits.len(), 2);
+    let hit = hits.iter().find(|hit| hit.ends_with("where is the cake?")).unwrap();
+    let hit_idx = &hit[1..hit.find(']').unwrap()];
+
+    send(&mut app, &format!("/open {}", hit_idx)).await;
+    process_events(&mut app, &backend).await;
+    let selected = app.data.channels.state.selected().unwrap();
+    let channel = &app.data.channels.items[selected];
+    assert!(channel.is("other", "other-conversation"));
+    assert_eq!(channel.scroll, 0);
+}
+
+#[tokio::test]
+async fn unified_view_shows_and_uses_every_account() {
+    let backend = setup();
+    backend
+        .with_account("other")
+        .with_conversation("other", "other-conversation", &["other", PEER]);
+    let mut app = new_app(&backend);
+    assert!(app.data.channels.items.iter().all(|channel| channel.id != "other-conversation"));
+
+    send(&mut app, "/unified").await;
+    process_events(&mut app, &backend).await;
+    let headers: Vec<&str> = app
+        .data
+        .channels
+        .items
+        .iter()
+        .filter(|channel| channel.channel_type == ChannelType::Generated)
+        .map(|channel| channel.account_id.as_str())
+        .collect();
+    assert_eq!(headers, vec![ACCOUNT, "other"]);
+
+    backend.receive_message("other", "other-conversation", PEER, "hello");
+    process_events(&mut app, &backend).await;
+    assert_eq!(channel(&app, "other-conversation").unread_messages, 1);
+
+    // Sending uses the account of the selected channel
+    let selected_id = |app: &App| {
+        let idx = app.data.channels.state.selected().unwrap();
+        app.data.channels.items[idx].id.clone()
+    };
+    while selected_id(&app) != "other-conversation" {
+        next_channel(&mut app).await;
+    }
+    process_events(&mut app, &backend).await;
+    send(&mut app, "hi").await;
+    process_events(&mut app, &backend).await;
+    let last = channel(&app, "other-conversation").messages.last().unwrap().clone();
+    assert_eq!((last.text(), last.author.as_str()), ("hi", "other"));
+
+    // Enter on a header collapses the channels of its account
+    let header_idx = app
+        .data
+        .channels
+        .items
+        .iter()
+        .position(|channel| channel.channel_type == ChannelType::Generated && channel.account_id == "other")
+        .unwrap();
+    app.data.channels.state.select(Some(header_idx));
+    press(&mut app, KeyCode::Enter).await;
+    let channel_idx = app
+        .data
+        .channels
+        .items
+        .iter()
+        .position(|channel| channel.id == "other-conversation")
+        .unwrap();
+    assert!(app.is_hidden(channel_idx));
+    next_channel(&mut app).await;
+    assert_ne!(app.data.channels.state.selected(), Some(channel_idx));
+}
+
+#[tokio::test]
+async fn account_wizard_creates_and_registers_an_account() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    send(&mut app, "/add").await;
+    assert!(app.wizard.is_some());
+    send(&mut app, "Alice").await;
+    type_text(&mut app, "alice").await;
+    std::thread::sleep(wizard::LOOKUP_DELAY);
+    app.on_tick();
+    process_events(&mut app, &backend).await;
+    assert_eq!(app.wizard.as_ref().unwrap().name_status, NameStatus::Available);
+    press(&mut app, KeyCode::Enter).await;
+    type_text(&mut app, "secret").await;
+    press(&mut app, KeyCode::Tab).await;
+    send(&mut app, "secret").await;
+    assert!(backend.calls().contains(&String::from("create_account Alice")));
+
+    process_events(&mut app, &backend).await;
+    assert!(backend.calls().contains(&String::from("register_name alice")));
+    let wizard = app.wizard.as_ref().unwrap();
+    assert_eq!(wizard.progress, Progress::Done(String::from("Account ready, registered as alice")));
+    let account_id = wizard.account_id.clone();
+    assert!(backend.calls().contains(&format!("update_profile {} Alice ", account_id)));
+
+    press(&mut app, KeyCode::Enter).await;
+    assert!(app.wizard.is_none());
+    assert_eq!(app.data.account.id, account_id);
+    assert!(app.data.input.is_empty());
+}
+
+#[tokio::test]
+async fn account_wizard_removes_a_failed_account_before_trying_again() {
+    let backend = setup();
+    let mut app = new_app(&backend);
+
+    send(&mut app, "/add").await;
+    send(&mut app, "Bob").await;
+    type_text(&mut app, "bob").await;
+    std::thread::sleep(wizard::LOOKUP_DELAY);
+    app.on_tick();
+    process_events(&mut app, &backend).await;
+    // Someone is faster
+    backend.with_name("bob", "someone");
+    press(&mut app, KeyCode::Enter).await;
+    send(&mut app, "").await;
+    process_events(&mut app, &backend).await;
+    let wizard = app.wizard.as_ref().unwrap();
+    assert_eq!(wizard.progress, Progress::Failed(String::from("bob was registered by someone else")));
+    let account_id = wizard.account_id.clone();
+
+    press(&mut app, KeyCode::Enter).await;
+    assert!(backend.calls().contains(&format!("rm_account {}", account_id)));
+    let wizard = app.wizard.as_ref().unwrap();
+    assert_eq!(wizard.progress, Progress::Editing);
+    assert!(wizard.account_id.is_empty());
+}
+
+#[tokio::test]
+async fn export_and_pin_ask_passwords_in_a_masked_prompt() {
+    let backend = setup();
+    backend.state().passwords.insert(ACCOUNT.to_string(), String::from("secret"));
+    let mut app = new_app(&backend);
+    let path = std::env::temp_dir().join("jami-cli-export.gz");
+    let path = path.to_str().unwrap();
+
+    send(&mut app, &format!("/export {}", path)).await;
+    assert_eq!(app.password_prompt.as_ref().unwrap().field.shown(), "");
+    send(&mut app, "secret").await;
+    assert!(app.password_prompt.is_none());
+    assert_eq!(std::fs::read_to_string(path).unwrap(), ACCOUNT);
+
+    send(&mut app, "/pin").await;
+    type_text(&mut app, "wrong").await;
+    assert_eq!(app.password_prompt.as_ref().unwrap().field.shown(), "•••••");
+    assert!(app.data.input.is_empty());
+    press(&mut app, KeyCode::Enter).await;
+    process_events(&mut app, &backend).await;
+    send(&mut app, "/pin").await;
+    send(&mut app, "secret").await;
+    process_events(&mut app, &backend).await;
+    // Cancelled
+    send(&mut app, "/pin").await;
+    press(&mut app, KeyCode::Esc).await;
+    assert!(app.password_prompt.is_none());
+
+    let channel = &app.data.channels.items[0];
+    let infos: Vec<&str> = channel.messages.iter().map(|message| message.text()).collect();
+    assert!(infos.contains(&format!("Account backup written to {}", path).as_str()));
+    assert!(infos.contains(&"Cannot generate a PIN: wrong password"));
+    assert!(infos.iter().any(|info| info.starts_with("PIN: ABCD1234 (valid until ")));
+    assert!(channel.input_history.iter().all(|input| !input.contains("secret")));
+}
+
+#[tokio::test]
+async fn devices_panel_follows_renames_and_revocations() {
+    let backend = setup();
+    backend
+        .with_device(ACCOUNT, "device_1", "laptop")
+        .with_device(ACCOUNT, "device_2", "phone");
+    backend.state().passwords.insert(ACCOUNT.to_string(), String::from("secret"));
+    let mut app = new_app(&backend);
+
+    send(&mut app, "/devices").await;
+    assert_eq!(app.focus, Focus::Devices);
+    let names: Vec<(&str, bool)> = app.devices.items.iter().map(|d| (&d.name[..], d.current)).collect();
+    assert_eq!(names, vec![("laptop", true), ("phone", false)]);
+
+    // Rename this device from the panel
+    press(&mut app, KeyCode::Char('n')).await;
+    assert_eq!(app.data.input, "/device-name laptop");
+    type_text(&mut app, " pro").await;
+    press(&mut app, KeyCode::Enter).await;
+    process_events(&mut app, &backend).await;
+    assert_eq!(app.devices.items[0].name, "laptop pro");
+
+    // Revoke the other one
+    send(&mut app, "/devices").await;
+    press(&mut app, KeyCode::Down).await;
+    press(&mut app, KeyCode::Char('r')).await;
+    assert!(app.password_prompt.is_some());
+    send(&mut app, "secret").await;
+    process_events(&mut app, &backend).await;
+    assert_eq!(app.devices.items.len(), 1);
+    let channel = &app.data.channels.items[0];
+    assert_eq!(channel.messages.last().unwrap().text(), "Device device_2 revoked");
+    assert!(channel.input_history.iter().all(|input| !input.contains("secret")));
+}
@@ -0,0 +1,52 @@
+use jami_cli::backend::{Backend, Event};
+use jami_cli::backfill::Backfill;
+use jami_cli::fake_backend::FakeBackend;
+use jami_cli::search::SearchIndex;
+use jami_rs::ProfileManager;
+use std::time::Instant;
+
+const ACCOUNT: &str = "account";
+const PEER: &str = "peer";
+const CONVERSATION: &str = "conversation";
+
+/// Index the conversation from the start, return the number of pages loaded
+fn run(backend: &FakeBackend, index: &SearchIndex) -> usize {
+    let mut backfill = Backfill::default();
+    let conversations = vec![(ACCOUNT.to_string(), CONVERSATION.to_string())];
+    let mut request = backfill.start(index, &conversations, Instant::now());
+    let mut pages = 0;
+    while let Some(page) = request {
+        let id = backend.load_conversation(&page.account_id, &page.conversation_id, &page.from, 50);
+        backfill.on_requested(id, Instant::now());
+        let messages = match backend.pop_event() {
+            Some(Event::ConversationLoaded(_, _, _, messages)) => messages,
+            _ => panic!("no page loaded"),
+        };
+        assert!(backfill.is_page(id));
+        pages += 1;
+        request = backfill.on_page(index, &messages, &ProfileManager::new());
+    }
+    pages
+}
+
+#[test]
+fn history_is_indexed_once_then_only_new_messages() {
+    let backend = FakeBackend::new();
+    backend
+        .with_account(ACCOUNT)
+        .with_conversation(ACCOUNT, CONVERSATION, &[ACCOUNT, PEER]);
+    for i in 0..120 {
+        backend.receive_message(ACCOUNT, CONVERSATION, PEER, &format!("message {}", i));
+    }
+    backend.state().events.clear();
+    let index = SearchIndex::in_memory().unwrap();
+
+    assert_eq!(run(&backend, &index), 3);
+    assert_eq!(index.search(ACCOUNT, "message 0", 10).unwrap().len(), 1);
+    assert!(index.backfill_mark(ACCOUNT, CONVERSATION).unwrap().complete);
+
+    backend.receive_message(ACCOUNT, CONVERSATION, PEER, "new message");
+    backend.state().events.clear();
+    assert_eq!(run(&backend, &index), 1);
+    assert_eq!(index.search(ACCOUNT, "new message", 10).unwrap().len(), 1);
+}
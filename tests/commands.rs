@@ -0,0 +1,65 @@
+use jami_cli::commands::{self, CommandError};
+use jami_cli::util::ChannelType;
+
+#[test]
+fn split_args_supports_quotes_and_escapes() {
+    assert_eq!(
+        commands::split_args(r#"/send "my file.txt" other\ file 'a "b"'"#).unwrap(),
+        vec!["/send", "my file.txt", "other file", "a \"b\""]
+    );
+    assert_eq!(
+        commands::split_args("/send \"unterminated"),
+        Err(CommandError::UnterminatedQuote)
+    );
+}
+
+#[test]
+fn parse_validates_arguments() {
+    let command = commands::parse("/accept 42 \"/tmp/a b\"", &ChannelType::Group)
+        .unwrap()
+        .unwrap();
+    assert_eq!(command.name, "/accept");
+    assert_eq!(command.number(0), 42);
+    assert_eq!(command.arg(1), "/tmp/a b");
+
+    assert_eq!(
+        commands::parse("/accept abc", &ChannelType::Group).unwrap_err(),
+        CommandError::Usage(String::from("/accept <tid> [path]"))
+    );
+    assert_eq!(
+        commands::parse("/invite", &ChannelType::Group).unwrap_err(),
+        CommandError::Usage(String::from("/invite <hash|username>"))
+    );
+    assert_eq!(
+        commands::parse("/new", &ChannelType::Group).unwrap_err(),
+        CommandError::Unknown(String::from("/new"))
+    );
+    assert!(commands::parse("hello", &ChannelType::Group)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn rest_argument_takes_remaining_words() {
+    let command = commands::parse("/title My new room", &ChannelType::Group)
+        .unwrap()
+        .unwrap();
+    assert_eq!(command.args, vec!["My new room"]);
+
+    // Kept as typed
+    let command = commands::parse("/title it's  done", &ChannelType::Group)
+        .unwrap()
+        .unwrap();
+    assert_eq!(command.args, vec!["it's  done"]);
+    let command = commands::parse("/set Account.alias Bob's \"laptop\"", &ChannelType::Generated)
+        .unwrap()
+        .unwrap();
+    assert_eq!(command.args, vec!["Account.alias", "Bob's \"laptop\""]);
+}
+
+#[test]
+fn help_only_lists_commands_of_the_channel() {
+    let help = commands::help(&ChannelType::Invite);
+    assert!(help.contains(&String::from("/join: Accept the request")));
+    assert!(!help.iter().any(|line| line.starts_with("/kick")));
+}
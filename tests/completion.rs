@@ -0,0 +1,22 @@
+use jami_cli::commands;
+use jami_cli::completion::{escape, path_candidates};
+
+use std::fs;
+
+#[test]
+fn paths_are_escaped_for_the_command_parser() {
+    let path = std::env::temp_dir().join("jami-cli-completion");
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(path.join("My Documents")).unwrap();
+    fs::write(path.join("it's.txt"), "").unwrap();
+    let dir = escape(path.to_str().unwrap());
+
+    let candidates = path_candidates(&format!("{}/My\\ Do", dir));
+    assert_eq!(candidates, vec![format!("{}/My\\ Documents/", dir)]);
+    assert_eq!(path_candidates(&format!("{}/\"My Do", dir)), candidates);
+
+    let candidates = path_candidates(&format!("{}/it", dir));
+    assert_eq!(candidates, vec![format!("{}/it\\'s.txt", dir)]);
+    let args = commands::split_args(&format!("/send {}", candidates[0])).unwrap();
+    assert_eq!(args[1], path.join("it's.txt").to_str().unwrap());
+}
@@ -0,0 +1,30 @@
+use jami_cli::editor;
+
+#[test]
+fn layout_wraps_on_display_width() {
+    // Each char takes 2 columns
+    let (lines, cursor) = editor::layout("日本語", 3, 4);
+    assert_eq!(lines, vec!["日本", "語"]);
+    assert_eq!(cursor, (2, 1));
+
+    let (lines, cursor) = editor::layout("日本", 2, 4);
+    assert_eq!(lines, vec!["日本", ""]);
+    assert_eq!(cursor, (0, 1));
+}
+
+#[test]
+fn layout_keeps_new_lines() {
+    let (lines, cursor) = editor::layout("ab\ncd", 3, 10);
+    assert_eq!(lines, vec!["ab", "cd"]);
+    assert_eq!(cursor, (0, 1));
+}
+
+#[test]
+fn line_up_and_down_keep_the_column() {
+    let input = "abcd\nef\nghij";
+    assert_eq!(editor::line_up(input, 3), None);
+    assert_eq!(editor::line_down(input, 3), Some(7));
+    assert_eq!(editor::line_down(input, 7), Some(10));
+    assert_eq!(editor::line_up(input, 11), Some(7));
+    assert_eq!(editor::line_down(input, 11), None);
+}
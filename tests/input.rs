@@ -0,0 +1,29 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use jami_cli::input::{convert, pasted_files, InputEvent};
+
+#[test]
+fn keys_are_not_pastes() {
+    let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(convert(Event::Key(key)), Some(InputEvent::Key(key)));
+}
+
+#[test]
+fn pastes_are_one_event() {
+    let event = convert(Event::Paste(String::from("a\nb")));
+    assert_eq!(event, Some(InputEvent::Paste(String::from("a\nb"))));
+    assert_eq!(convert(Event::FocusGained), None);
+}
+
+#[test]
+fn dropped_paths_are_files() {
+    let dir = std::env::temp_dir();
+    let path = dir.join("jami-cli input test.txt");
+    std::fs::write(&path, "content").unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    assert_eq!(pasted_files(&format!("'{}'", path)), vec![path.clone()]);
+    assert_eq!(pasted_files(&path.replace(' ', "\\ ")), vec![path.clone()]);
+    assert_eq!(pasted_files(&format!("file://{}", path.replace(' ', "%20"))), vec![path.clone()]);
+    assert!(pasted_files("hello world").is_empty());
+    assert!(pasted_files(dir.to_str().unwrap()).is_empty());
+}
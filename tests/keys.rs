@@ -0,0 +1,65 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use jami_cli::config::{Config, ConfigError};
+use jami_cli::keys::{Action, KeyChord, KeyError};
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+#[test]
+fn chords_are_parsed() {
+    let chord: KeyChord = "ctrl+alt+left".parse().unwrap();
+    assert_eq!(chord.code, KeyCode::Left);
+    assert_eq!(chord.modifiers, KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+    let chord: KeyChord = "shift+n".parse().unwrap();
+    assert_eq!(chord.code, KeyCode::Char('N'));
+    assert_eq!(chord.modifiers, KeyModifiers::NONE);
+
+    assert_eq!("F5".parse::<KeyChord>().unwrap().code, KeyCode::F(5));
+    assert_eq!("ctrl++".parse::<KeyChord>().unwrap().code, KeyCode::Char('+'));
+    assert_eq!(
+        "hyper+x".parse::<KeyChord>(),
+        Err(KeyError::InvalidKey(String::from("hyper+x")))
+    );
+}
+
+#[test]
+fn defaults_are_kept_for_missing_actions() {
+    let config = Config::parse("[keys]\nnext_channel = [\"ctrl+n\", \"alt+j\"]\n").unwrap();
+    let keys = &config.keys;
+
+    assert_eq!(
+        keys.action(&key(KeyCode::Char('n'), KeyModifiers::CONTROL)),
+        Some(Action::NextChannel)
+    );
+    assert_eq!(
+        keys.action(&key(KeyCode::Char('j'), KeyModifiers::ALT)),
+        Some(Action::NextChannel)
+    );
+    assert_eq!(keys.action(&key(KeyCode::Down, KeyModifiers::ALT)), None);
+    assert_eq!(
+        keys.action(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+}
+
+#[test]
+fn conflicts_and_unknown_actions_are_errors() {
+    match Config::parse("[keys]\nsearch = \"ctrl+c\"\n") {
+        Err(ConfigError::Keys(_, KeyError::Conflict { first, second, .. })) => {
+            assert_eq!((first, second), (Action::Quit, Action::Search));
+        }
+        _ => panic!("conflict not detected"),
+    }
+    for chord in &["ctrl+a", "x", "shift+x", "enter", "alt+backspace", "up"] {
+        match Config::parse(&format!("[keys]\nscroll_up = \"{}\"\n", chord)) {
+            Err(ConfigError::Keys(_, KeyError::Reserved { action, .. })) => assert_eq!(action, Action::ScrollUp),
+            _ => panic!("{} can be bound", chord),
+        }
+    }
+    match Config::parse("[keys]\nfly = \"f1\"\n") {
+        Err(ConfigError::Keys(_, err)) => assert_eq!(err, KeyError::UnknownAction(String::from("fly"))),
+        _ => panic!("unknown action not detected"),
+    }
+}
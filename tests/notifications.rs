@@ -0,0 +1,19 @@
+use jami_cli::notifications::{mentions, NotificationError, NotifyMode};
+
+#[test]
+fn mentions_match_whole_names() {
+    let names = vec![String::from("alice"), String::new(), String::from("Alice Doe")];
+    assert!(mentions("hi @Alice!", &names));
+    assert!(mentions("ask alice doe", &names));
+    assert!(!mentions("malice", &names));
+    assert!(!mentions("", &names));
+}
+
+#[test]
+fn modes_are_parsed() {
+    assert_eq!(NotifyMode::parse("Mentions"), Ok(NotifyMode::Mentions));
+    assert_eq!(
+        NotifyMode::parse("some"),
+        Err(NotificationError::InvalidMode(String::from("some")))
+    );
+}
@@ -0,0 +1,47 @@
+use jami_cli::config::{Config, ConfigError};
+use jami_cli::theme::{parse_color, ThemeError};
+use tui::style::{Color, Modifier};
+
+#[test]
+fn colors_are_parsed() {
+    assert_eq!(parse_color("light_blue"), Ok(Color::LightBlue));
+    assert_eq!(parse_color("208"), Ok(Color::Indexed(208)));
+    assert_eq!(parse_color("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+    assert_eq!(
+        parse_color("#ff80"),
+        Err(ThemeError::InvalidColor(String::from("#ff80")))
+    );
+}
+
+#[test]
+fn theme_overrides_styles_and_palette() {
+    let config = Config::parse(
+        "[theme]\nmonochrome = false\npalette = \"256\"\ntime = { fg = \"#00ff00\", bold = true }\n",
+    )
+    .unwrap();
+    let theme = &config.theme;
+
+    assert_eq!(theme.time.fg, Some(Color::Rgb(0, 255, 0)));
+    assert!(theme.time.add_modifier.contains(Modifier::BOLD));
+    assert_eq!(theme.absent.fg, Some(Color::Red));
+    assert!(matches!(theme.author("alice").fg, Some(Color::Indexed(_))));
+}
+
+#[test]
+fn monochrome_theme_has_no_colors() {
+    let config = Config::parse("[theme]\nmonochrome = true\nabsent = { fg = \"red\", italic = true }\n").unwrap();
+    let theme = &config.theme;
+
+    assert_eq!(theme.absent.fg, None);
+    assert!(theme.absent.add_modifier.contains(Modifier::ITALIC));
+    assert_eq!(theme.author("alice").fg, None);
+    assert!(theme.selection.add_modifier.contains(Modifier::REVERSED));
+}
+
+#[test]
+fn invalid_color_is_an_error() {
+    assert!(matches!(
+        Config::parse("[theme]\ntext = { fg = \"purpleish\" }\n"),
+        Err(ConfigError::Theme(_, ThemeError::InvalidColor(_)))
+    ));
+}
@@ -0,0 +1,76 @@
+use jami_cli::config::{Config, ConfigError};
+use jami_cli::transfers::{free_path, parse_size, safe_file_name, TransferError};
+
+use std::fs;
+
+#[test]
+fn sizes_are_parsed() {
+    assert_eq!(parse_size("512"), Ok(512));
+    assert_eq!(parse_size("20 KiB"), Ok(20 * 1024));
+    assert_eq!(parse_size("1.5M"), Ok(3 * 512 * 1024));
+    assert_eq!(
+        parse_size("ten"),
+        Err(TransferError::InvalidSize(String::from("ten")))
+    );
+}
+
+#[test]
+fn policy_checks_origin_size_and_type() {
+    let config = Config::parse(
+        "[transfers.auto_accept]\ncontacts = true\nconversations = [\"work\"]\nmax_size = \"1 MiB\"\nallow = [\"image/*\", \"pdf\"]\ndeny = [\"svg\"]\n",
+    )
+    .unwrap();
+    let policy = &config.transfers;
+
+    assert!(policy.should_accept("work", false, "cat.JPG", 1000));
+    assert!(policy.should_accept("other", true, "report.pdf", 1000));
+    assert!(!policy.should_accept("other", false, "report.pdf", 1000));
+    assert!(!policy.should_accept("work", false, "movie.png", 2 << 20));
+    assert!(!policy.should_accept("work", false, "logo.svg", 1000));
+    assert!(!policy.should_accept("work", false, "script.sh", 1000));
+}
+
+#[test]
+fn download_dir_is_expanded_per_conversation() {
+    let config = Config::parse(
+        "[transfers]\ndownload_dir = \"/tmp/{conversation}\"\n[transfers.download_dirs]\nwork = \"/srv/{conversation_id}\"\n",
+    )
+    .unwrap();
+    let policy = &config.transfers;
+
+    assert_eq!(
+        policy.download_dir("abc", "Alice/Bob").unwrap().to_str(),
+        Some("/tmp/Alice_Bob")
+    );
+    assert_eq!(policy.download_dir("work", "Work").unwrap().to_str(), Some("/srv/work"));
+}
+
+#[test]
+fn free_path_does_not_overwrite_files() {
+    let dir = std::env::temp_dir().join("jami-cli-free-path");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "").unwrap();
+    fs::write(dir.join("a.txt_1"), "").unwrap();
+
+    assert_eq!(free_path(&dir, "a.txt"), dir.join("a.txt_2"));
+    assert_eq!(free_path(&dir, "b.txt"), dir.join("b.txt"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn invalid_max_size_is_an_error() {
+    assert!(matches!(
+        Config::parse("[transfers.auto_accept]\nmax_size = \"big\"\n"),
+        Err(ConfigError::Transfers(_, TransferError::InvalidSize(_)))
+    ));
+}
+
+#[test]
+fn sender_names_stay_in_the_download_directory() {
+    assert_eq!(safe_file_name("photo.png"), Some("photo.png"));
+    assert_eq!(safe_file_name("../../.bashrc"), Some(".bashrc"));
+    assert_eq!(safe_file_name("/etc/passwd"), Some("passwd"));
+    assert_eq!(safe_file_name(".."), None);
+    assert_eq!(safe_file_name(""), None);
+}
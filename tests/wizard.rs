@@ -0,0 +1,75 @@
+use jami_cli::wizard::{AccountWizard, NameStatus, Progress, WizardAction, WizardKind, LOOKUP_DELAY};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::Instant;
+
+fn press(wizard: &mut AccountWizard, code: KeyCode) -> WizardAction {
+    wizard.on_key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn type_text(wizard: &mut AccountWizard, text: &str) {
+    for c in text.chars() {
+        press(wizard, KeyCode::Char(c));
+    }
+}
+
+#[test]
+fn passwords_are_masked_and_must_match() {
+    let mut wizard = AccountWizard::new(WizardKind::Create);
+    press(&mut wizard, KeyCode::Enter);
+    press(&mut wizard, KeyCode::Enter);
+    assert_eq!(wizard.steps[wizard.step].title, "Password");
+
+    type_text(&mut wizard, "secret");
+    press(&mut wizard, KeyCode::Tab);
+    type_text(&mut wizard, "secre");
+    assert_eq!(wizard.steps[wizard.step].fields[0].shown(), "••••••");
+    assert_eq!(press(&mut wizard, KeyCode::Enter), WizardAction::None);
+    assert_eq!(wizard.error.as_deref(), Some("Passwords don't match"));
+
+    type_text(&mut wizard, "t");
+    assert_eq!(press(&mut wizard, KeyCode::Enter), WizardAction::Submit);
+    assert_eq!(wizard.details()["Account.archivePassword"], "secret");
+}
+
+#[test]
+fn username_is_checked_before_going_on() {
+    let mut wizard = AccountWizard::new(WizardKind::Create);
+    press(&mut wizard, KeyCode::Enter);
+    type_text(&mut wizard, "Not Valid");
+    assert_eq!(wizard.tick(Instant::now() + LOOKUP_DELAY), None);
+    assert_eq!(wizard.name_status, NameStatus::Invalid);
+    press(&mut wizard, KeyCode::Enter);
+    assert_eq!(wizard.step, 1);
+
+    wizard.on_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+    type_text(&mut wizard, "alice");
+    assert_eq!(wizard.tick(Instant::now()), None);
+    assert_eq!(wizard.tick(Instant::now() + LOOKUP_DELAY), Some(String::from("alice")));
+    wizard.on_name_found(0, "alice");
+    assert_eq!(wizard.name_status, NameStatus::Taken);
+    press(&mut wizard, KeyCode::Enter);
+    assert_eq!(wizard.step, 1);
+}
+
+#[test]
+fn link_failures_are_reported() {
+    let mut wizard = AccountWizard::new(WizardKind::Link);
+    press(&mut wizard, KeyCode::Enter);
+    assert_eq!(wizard.error.as_deref(), Some("Enter the PIN"));
+    type_text(&mut wizard, "1234abcd");
+    press(&mut wizard, KeyCode::Enter);
+    assert_eq!(press(&mut wizard, KeyCode::Enter), WizardAction::Submit);
+    assert_eq!(wizard.details()["Account.archivePin"], "1234abcd");
+
+    wizard.on_registration_state("INITIALIZING");
+    assert_eq!(wizard.progress, Progress::Working(String::from("Generating the keys…")));
+    wizard.account_id = String::from("account_1");
+    wizard.on_registration_state("ERROR_NETWORK");
+    assert!(matches!(wizard.progress, Progress::Failed(_)));
+    assert_eq!(wizard.broken_account().map(|id| &id[..]), Some("account_1"));
+    assert_eq!(press(&mut wizard, KeyCode::Enter), WizardAction::Retry);
+    wizard.retry();
+    assert_eq!(wizard.progress, Progress::Editing);
+    assert!(wizard.account_id.is_empty());
+}